@@ -1,8 +1,21 @@
+use bimbo_lashes_core::{
+    approval::{self, Decision},
+    models::{Booking, BLOCKED_NOTICE},
+    payments::{self, PaymentProvider},
+    refunds,
+    repo::{self, CancelledBy},
+    slots,
+    telegram_payments,
+    time::{moscow_now, moscow_today},
+    visits::{self, Outcome},
+};
 use sqlx::sqlite::SqlitePoolOptions;
+use std::sync::Arc;
 use teloxide::{
     prelude::*,
     types::{
-        ChatId, InlineKeyboardButton, InlineKeyboardMarkup, ParseMode, WebAppInfo,
+        ChatId, Currency, InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup,
+        ParseMode, PreCheckoutQuery, SuccessfulPayment, WebAppInfo,
     },
     utils::command::BotCommands,
 };
@@ -23,30 +36,21 @@ enum Command {
     OpenDay(String),
     #[command(description = "Расписание на дату: /schedule 2026-02-25")]
     Schedule(String),
+    #[command(description = "Сумма, полученная за визит: /collected 17 2500")]
+    Collected(String),
     #[command(description = "Помощь")]
     Help,
 }
 
-#[derive(Debug, Clone, sqlx::FromRow)]
-struct BookingInfo {
-    id: i64,
-    service_name: String,
-    service_price: i64,
-    date: String,
-    start_time: String,
-    end_time: String,
-    client_tg_id: i64,
-    client_username: Option<String>,
-    client_first_name: String,
-    payment_status: String,
-    prepaid_amount: i64,
-}
-
 #[derive(Clone)]
 struct BotState {
     pool: sqlx::SqlitePool,
     webapp_url: String,
     admin_tg_id: i64,
+    /// Base slot length in minutes (shared with the server via `SLOT_GRANULARITY_MIN`).
+    slot_granularity_min: u32,
+    /// Refunds on cancellation, configured like the server's (`PAYMENT_PROVIDER`).
+    payments: Arc<dyn PaymentProvider>,
 }
 
 #[tokio::main]
@@ -68,12 +72,17 @@ async fn main() -> anyhow::Result<()> {
         .expect("ADMIN_TG_ID must be set")
         .parse()
         .expect("ADMIN_TG_ID must be a number");
+    let slot_granularity_min = bimbo_lashes_core::slots::granularity_from_env()?;
+    let payments = payments::from_env()?;
 
     let pool = SqlitePoolOptions::new()
         .max_connections(3)
         .connect(&database_url)
         .await?;
 
+    // Same embedded migrations as the server: whichever starts first applies them
+    bimbo_lashes_core::migrations::run(&pool).await?;
+
     let bot = Bot::new(&bot_token);
 
     tracing::info!("💅 Bimbo Lashes bot starting...");
@@ -89,6 +98,8 @@ async fn main() -> anyhow::Result<()> {
         pool,
         webapp_url,
         admin_tg_id,
+        slot_granularity_min,
+        payments,
     };
 
     let cmd_handler = Update::filter_message()
@@ -115,9 +126,35 @@ async fn main() -> anyhow::Result<()> {
         }
     });
 
+    let pre_checkout_handler = Update::filter_pre_checkout_query().endpoint({
+        let state = state.clone();
+        move |bot: Bot, q: PreCheckoutQuery| {
+            let state = state.clone();
+            async move {
+                handle_pre_checkout(bot, q, &state).await?;
+                Ok::<(), Box<dyn std::error::Error + Send + Sync>>(())
+            }
+        }
+    });
+
+    let payment_handler = Update::filter_message()
+        .filter_map(|msg: Message| msg.successful_payment().cloned())
+        .endpoint({
+            let state = state.clone();
+            move |bot: Bot, msg: Message, payment: SuccessfulPayment| {
+                let state = state.clone();
+                async move {
+                    handle_successful_payment(bot, msg, payment, &state).await?;
+                    Ok::<(), Box<dyn std::error::Error + Send + Sync>>(())
+                }
+            }
+        });
+
     let handler = dptree::entry()
         .branch(cmd_handler)
-        .branch(callback_handler);
+        .branch(callback_handler)
+        .branch(pre_checkout_handler)
+        .branch(payment_handler);
 
     Dispatcher::builder(bot, handler)
         .enable_ctrlc_handler()
@@ -138,6 +175,20 @@ async fn handle_command(
 ) -> anyhow::Result<()> {
     match cmd {
         Command::Start => {
+            if let Some(user) = &msg.from {
+                let blocked = repo::blocked_client(
+                    &state.pool,
+                    user.id.0 as i64,
+                    user.username.as_deref(),
+                    None,
+                )
+                .await?;
+                if blocked.is_some() {
+                    bot.send_message(msg.chat.id, BLOCKED_NOTICE).await?;
+                    return Ok(());
+                }
+            }
+
             let keyboard = InlineKeyboardMarkup::new(vec![vec![
                 InlineKeyboardButton::web_app(
                     "💅 Записаться",
@@ -162,23 +213,7 @@ async fn handle_command(
         Command::MyBookings => {
             let user_id = msg.from.as_ref().map(|u| u.id.0 as i64).unwrap_or(0);
 
-            let bookings = sqlx::query_as::<_, BookingInfo>(
-                "SELECT b.id, s.name as service_name, s.price as service_price,
-                        COALESCE(b.date, sl.date) as date,
-                        COALESCE(b.start_time, sl.start_time) as start_time,
-                        COALESCE(b.end_time, sl.end_time) as end_time,
-                        b.client_tg_id, b.client_username, b.client_first_name,
-                        b.payment_status, b.prepaid_amount
-                 FROM bookings b
-                 JOIN services s ON s.id = b.service_id
-                 LEFT JOIN available_slots sl ON sl.id = b.slot_id
-                 WHERE b.client_tg_id = ? AND b.status IN ('confirmed', 'pending_payment')
-                 AND COALESCE(b.date, sl.date) >= date('now', '+3 hours')
-                 ORDER BY COALESCE(b.date, sl.date) ASC, COALESCE(b.start_time, sl.start_time) ASC",
-            )
-            .bind(user_id)
-            .fetch_all(&state.pool)
-            .await?;
+            let bookings = repo::upcoming_client_bookings(&state.pool, user_id).await?;
 
             if bookings.is_empty() {
                 let keyboard = InlineKeyboardMarkup::new(vec![vec![
@@ -196,9 +231,10 @@ async fn handle_command(
             } else {
                 let mut text = "📋 <b>Твои записи:</b>\n\n".to_string();
                 for b in &bookings {
-                    let payment_badge = match b.payment_status.as_str() {
-                        "paid" => format!("💳 {} ₽", b.prepaid_amount),
-                        "pending" => "⏳ ожидание оплаты".to_string(),
+                    let payment_badge = match (b.status.as_str(), b.payment_status.as_deref()) {
+                        ("awaiting_approval", _) => "🙋 ждёт подтверждения мастера".to_string(),
+                        (_, Some("paid")) => format!("💳 {} ₽", b.prepaid_amount.unwrap_or(0)),
+                        (_, Some("pending")) => "⏳ ожидание оплаты".to_string(),
                         _ => String::new(),
                     };
                     text.push_str(&format!(
@@ -237,7 +273,7 @@ async fn handle_command(
                 return Ok(());
             }
 
-            let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+            let today = moscow_today();
             send_day_bookings(&bot, msg.chat.id, &state.pool, &today, "Сегодня").await?;
         }
 
@@ -248,7 +284,7 @@ async fn handle_command(
                 return Ok(());
             }
 
-            let tomorrow = (chrono::Local::now() + chrono::TimeDelta::days(1))
+            let tomorrow = (moscow_now() + chrono::TimeDelta::days(1))
                 .format("%Y-%m-%d")
                 .to_string();
             send_day_bookings(&bot, msg.chat.id, &state.pool, &tomorrow, "Завтра").await?;
@@ -265,8 +301,11 @@ async fn handle_command(
             if date.is_empty() {
                 bot.send_message(
                     msg.chat.id,
-                    "📝 <b>Формат:</b>\n<code>/openday 2026-02-25</code>\n\n\
-                     Создаст 8 слотов по 1 часу: 12:00–20:00",
+                    format!(
+                        "📝 <b>Формат:</b>\n<code>/openday 2026-02-25</code>\n\n\
+                         Откроет слоты по {} мин: 12:00–20:00",
+                        state.slot_granularity_min
+                    ),
                 )
                 .parse_mode(ParseMode::Html)
                 .await?;
@@ -279,43 +318,23 @@ async fn handle_command(
                 return Ok(());
             }
 
-            let mut added = 0;
-            for hour in 12..20 {
-                let start = format!("{:02}:00", hour);
-                let end = format!("{:02}:00", hour + 1);
-
-                // Skip if already exists
-                let exists: bool = sqlx::query_scalar(
-                    "SELECT COUNT(*) > 0 FROM available_slots WHERE date = ? AND start_time = ?"
-                )
-                .bind(&date)
-                .bind(&start)
-                .fetch_one(&state.pool)
-                .await
-                .unwrap_or(false);
-
-                if !exists {
-                    let result = sqlx::query(
-                        "INSERT INTO available_slots (date, start_time, end_time) VALUES (?, ?, ?)"
-                    )
-                    .bind(&date)
-                    .bind(&start)
-                    .bind(&end)
-                    .execute(&state.pool)
-                    .await;
-
-                    if result.is_ok() {
-                        added += 1;
-                    }
+            let step = state.slot_granularity_min;
+            let added = match slots::open_day_slots(&state.pool, &date, 12, 20, step).await {
+                Ok(n) => n,
+                Err(e) => {
+                    tracing::error!(date = %date, error = %e, "/openday failed");
+                    bot.send_message(msg.chat.id, "❌ Не удалось открыть день, попробуй ещё раз")
+                        .await?;
+                    return Ok(());
                 }
-            }
+            };
 
             if added > 0 {
                 bot.send_message(
                     msg.chat.id,
                     format!(
-                        "✅ Открыт день {} ({})\n📅 {} слотов по 1 часу: 12:00–20:00",
-                        format_date_ru(&date), date, added
+                        "✅ Открыт день {} ({})\n📅 {} слотов по {} мин: 12:00–20:00",
+                        format_date_ru(&date), date, added, step
                     ),
                 )
                 .await?;
@@ -337,7 +356,7 @@ async fn handle_command(
 
             let date = args.trim();
             let date = if date.is_empty() {
-                chrono::Local::now().format("%Y-%m-%d").to_string()
+                moscow_today()
             } else {
                 if chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
                     bot.send_message(msg.chat.id, "❌ Формат: /schedule 2026-02-25")
@@ -391,7 +410,8 @@ async fn handle_command(
                         sqlx::query_as::<_, (String, String)>(
                             "SELECT s.name, b.client_first_name
                              FROM bookings b JOIN services s ON s.id = b.service_id
-                             WHERE b.id = ? AND b.status IN ('confirmed', 'pending_payment')"
+                             WHERE b.id = ? AND b.status IN ('confirmed', 'pending_payment', 'awaiting_approval',
+                                                             'completed', 'no_show')"
                         )
                         .bind(bid)
                         .fetch_optional(&state.pool)
@@ -423,6 +443,36 @@ async fn handle_command(
                 .await?;
         }
 
+        Command::Collected(args) => {
+            let user_id = msg.from.as_ref().map(|u| u.id.0 as i64).unwrap_or(0);
+            if user_id != state.admin_tg_id {
+                bot.send_message(msg.chat.id, "⛔ Только для мастера").await?;
+                return Ok(());
+            }
+
+            let mut parts = args.split_whitespace().map(str::parse::<i64>);
+            let (Some(Ok(booking_id)), Some(Ok(amount)), None) =
+                (parts.next(), parts.next(), parts.next())
+            else {
+                bot.send_message(
+                    msg.chat.id,
+                    "📝 <b>Формат:</b>\n<code>/collected 17 2500</code> — номер записи и сумма в ₽",
+                )
+                .parse_mode(ParseMode::Html)
+                .await?;
+                return Ok(());
+            };
+
+            let text = if amount < 0 {
+                "❌ Сумма не может быть отрицательной".to_string()
+            } else if repo::set_amount_collected(&state.pool, booking_id, amount).await? {
+                format!("✅ Запись #{}: получено {} ₽", booking_id, amount)
+            } else {
+                format!("❌ Запись #{} не найдена среди завершённых визитов", booking_id)
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+
         Command::Help => {
             let is_admin = msg.from.as_ref().map(|u| u.id.0 as i64).unwrap_or(0)
                 == state.admin_tg_id;
@@ -439,9 +489,10 @@ async fn handle_command(
                      /today — записи на сегодня\n\
                      /tomorrow — записи на завтра\n\
                      /schedule — расписание на дату\n\
-                     /openday — открыть день для записи\n\n\
+                     /openday — открыть день для записи\n\
+                     /collected — исправить сумму, полученную за визит\n\n\
                      <b>Примеры:</b>\n\
-                     <code>/openday 2026-02-25</code> — создаёт 8 слотов (12–20)\n\
+                     <code>/openday 2026-02-25</code> — открывает слоты 12–20\n\
                      <code>/schedule 2026-02-25</code>",
                 );
             }
@@ -457,6 +508,18 @@ async fn handle_command(
 
 // ── Callback query handler ──
 
+/// Cancel `booking` at the client's request, refunding under the same policy
+/// as cancelling from the web app.
+async fn cancel_by_client(
+    pool: &sqlx::SqlitePool,
+    payments: &dyn PaymentProvider,
+    booking: &Booking,
+) -> anyhow::Result<Option<refunds::RefundOutcome>> {
+    let refund = refunds::process_refund_if_needed(pool, payments, booking, None).await;
+    repo::cancel_booking(pool, booking, CancelledBy::Client).await?;
+    Ok(refund)
+}
+
 async fn handle_callback(
     bot: Bot,
     q: CallbackQuery,
@@ -469,51 +532,20 @@ async fn handle_callback(
     if let Some(booking_id_str) = data.strip_prefix("cancel:") {
         let booking_id: i64 = booking_id_str.parse().unwrap_or(0);
 
-        let booking = sqlx::query_as::<_, BookingInfo>(
-            "SELECT b.id, s.name as service_name, s.price as service_price,
-                    COALESCE(b.date, sl.date) as date,
-                    COALESCE(b.start_time, sl.start_time) as start_time,
-                    COALESCE(b.end_time, sl.end_time) as end_time,
-                    b.client_tg_id, b.client_username, b.client_first_name,
-                    b.payment_status, b.prepaid_amount
-             FROM bookings b
-             JOIN services s ON s.id = b.service_id
-             LEFT JOIN available_slots sl ON sl.id = b.slot_id
-             WHERE b.id = ? AND b.client_tg_id = ? AND b.status IN ('confirmed', 'pending_payment')",
-        )
-        .bind(booking_id)
-        .bind(user_id)
-        .fetch_optional(&state.pool)
-        .await?;
-
-        if let Some(b) = booking {
-            sqlx::query(
-                "UPDATE bookings SET status = 'cancelled', cancelled_at = datetime('now', '+3 hours') WHERE id = ?",
-            )
-            .bind(booking_id)
-            .execute(&state.pool)
-            .await?;
-
-            // Free all slots belonging to this booking
-            sqlx::query("UPDATE available_slots SET is_booked = 0, booking_id = NULL WHERE booking_id = ?")
-                .bind(booking_id)
-                .execute(&state.pool)
-                .await?;
-
-            // Also free by old slot_id reference
-            let slot_id: Option<i64> = sqlx::query_scalar(
-                "SELECT slot_id FROM bookings WHERE id = ?",
-            )
-            .bind(booking_id)
-            .fetch_optional(&state.pool)
-            .await?;
+        let booking = repo::active_booking(&state.pool, booking_id, Some(user_id)).await?;
+        let detail = repo::booking_detail(&state.pool, booking_id).await?;
 
-            if let Some(sid) = slot_id {
-                sqlx::query("UPDATE available_slots SET is_booked = 0, booking_id = NULL WHERE id = ?")
-                    .bind(sid)
-                    .execute(&state.pool)
-                    .await?;
-            }
+        if let (Some(booking), Some(b)) = (booking, detail) {
+            let refund_info =
+                cancel_by_client(&state.pool, state.payments.as_ref(), &booking).await?;
+            let refund_text = refund_info
+                .as_ref()
+                .map(|r| format!("\n💰 {}", r.text))
+                .unwrap_or_default();
+            let admin_refund_text = refund_info
+                .as_ref()
+                .map(|r| format!("\n💰 {}", r.admin_text()))
+                .unwrap_or_default();
 
             bot.answer_callback_query(&q.id).text("✅ Запись отменена").await?;
 
@@ -521,28 +553,24 @@ async fn handle_callback(
                 bot.send_message(
                     cid,
                     format!(
-                        "✅ Запись отменена:\n💅 {}\n📅 {} · {}",
+                        "✅ Запись отменена:\n💅 {}\n📅 {} · {}{}",
                         b.service_name,
                         format_date_ru(&b.date),
                         &b.start_time[..5],
+                        refund_text,
                     ),
                 )
                 .await?;
             }
 
             // Notify admin
-            let mention = b
-                .client_username
-                .as_ref()
-                .map(|u| format!("@{}", u))
-                .unwrap_or_else(|| b.client_first_name.clone());
-
             let admin_msg = format!(
-                "❌ Отмена записи\n\n👤 {}\n💅 {}\n📅 {} в {}",
-                mention,
+                "❌ Отмена записи\n\n👤 {}\n💅 {}\n📅 {} в {}{}",
+                b.client_mention(),
                 b.service_name,
                 format_date_ru(&b.date),
                 &b.start_time[..5],
+                admin_refund_text,
             );
 
             bot.send_message(ChatId(state.admin_tg_id), admin_msg).await?;
@@ -551,6 +579,18 @@ async fn handle_callback(
                 .text("Запись не найдена или уже отменена")
                 .await?;
         }
+    } else if let Some((decision, booking_id)) = approval::parse_callback(data) {
+        if user_id != state.admin_tg_id {
+            bot.answer_callback_query(&q.id).text("⛔").await?;
+            return Ok(());
+        }
+        handle_approval(&bot, &q, state, decision, booking_id).await?;
+    } else if let Some((outcome, booking_id)) = visits::parse_callback(data) {
+        if user_id != state.admin_tg_id {
+            bot.answer_callback_query(&q.id).text("⛔").await?;
+            return Ok(());
+        }
+        handle_visit_outcome(&bot, &q, state, outcome, booking_id).await?;
     } else if let Some(booking_id_str) = data.strip_prefix("admin_cancel:") {
         if user_id != state.admin_tg_id {
             bot.answer_callback_query(&q.id).text("⛔").await?;
@@ -559,49 +599,28 @@ async fn handle_callback(
 
         let booking_id: i64 = booking_id_str.parse().unwrap_or(0);
 
-        let booking = sqlx::query_as::<_, BookingInfo>(
-            "SELECT b.id, s.name as service_name, s.price as service_price,
-                    COALESCE(b.date, sl.date) as date,
-                    COALESCE(b.start_time, sl.start_time) as start_time,
-                    COALESCE(b.end_time, sl.end_time) as end_time,
-                    b.client_tg_id, b.client_username, b.client_first_name,
-                    b.payment_status, b.prepaid_amount
-             FROM bookings b
-             JOIN services s ON s.id = b.service_id
-             LEFT JOIN available_slots sl ON sl.id = b.slot_id
-             WHERE b.id = ? AND b.status IN ('confirmed', 'pending_payment')",
-        )
-        .bind(booking_id)
-        .fetch_optional(&state.pool)
-        .await?;
-
-        if let Some(b) = booking {
-            sqlx::query(
-                "UPDATE bookings SET status = 'cancelled', cancelled_at = datetime('now', '+3 hours') WHERE id = ?",
-            )
-            .bind(booking_id)
-            .execute(&state.pool)
-            .await?;
-
-            // Free all slots
-            sqlx::query("UPDATE available_slots SET is_booked = 0, booking_id = NULL WHERE booking_id = ?")
-                .bind(booking_id)
-                .execute(&state.pool)
-                .await?;
+        let booking = repo::active_booking(&state.pool, booking_id, None).await?;
+        let detail = repo::booking_detail(&state.pool, booking_id).await?;
 
-            let slot_id: Option<i64> = sqlx::query_scalar(
-                "SELECT slot_id FROM bookings WHERE id = ?",
+        if let (Some(booking), Some(b)) = (booking, detail) {
+            // Admin cancellation → always refund if paid
+            let refund_info = refunds::process_refund_if_needed(
+                &state.pool,
+                state.payments.as_ref(),
+                &booking,
+                Some(booking.prepaid_amount),
             )
-            .bind(booking_id)
-            .fetch_optional(&state.pool)
-            .await?;
+            .await;
+            let refund_text = refund_info
+                .as_ref()
+                .map(|r| format!("\n\n💰 {}", r.text))
+                .unwrap_or_default();
+            let admin_refund_text = refund_info
+                .as_ref()
+                .map(|r| format!("\n\n💰 {}", r.admin_text()))
+                .unwrap_or_default();
 
-            if let Some(sid) = slot_id {
-                sqlx::query("UPDATE available_slots SET is_booked = 0, booking_id = NULL WHERE id = ?")
-                    .bind(sid)
-                    .execute(&state.pool)
-                    .await?;
-            }
+            repo::cancel_booking(&state.pool, &booking, CancelledBy::Admin).await?;
 
             bot.answer_callback_query(&q.id)
                 .text("✅ Запись отменена")
@@ -611,10 +630,11 @@ async fn handle_callback(
             bot.send_message(
                 ChatId(b.client_tg_id),
                 format!(
-                    "😔 Твоя запись на {} в {} была отменена мастером.\n\n\
+                    "😔 Твоя запись на {} в {} была отменена мастером.{}\n\n\
                      Выбери другое время 💕",
                     format_date_ru(&b.date),
                     &b.start_time[..5],
+                    refund_text,
                 ),
             )
             .await
@@ -623,7 +643,7 @@ async fn handle_callback(
             if let Some(cid) = chat_id {
                 bot.send_message(
                     cid,
-                    format!("✅ Запись {} отменена", b.client_first_name),
+                    format!("✅ Запись {} отменена{}", b.client_first_name, admin_refund_text),
                 )
                 .await?;
             }
@@ -637,6 +657,318 @@ async fn handle_callback(
     Ok(())
 }
 
+/// The master's answer to a booking awaiting approval; a decline refunds in full.
+async fn handle_approval(
+    bot: &Bot,
+    q: &CallbackQuery,
+    state: &BotState,
+    decision: Decision,
+    booking_id: i64,
+) -> anyhow::Result<()> {
+    let booking = repo::active_booking(&state.pool, booking_id, None)
+        .await?
+        .filter(|b| b.status == "awaiting_approval");
+    let detail = repo::booking_detail(&state.pool, booking_id).await?;
+
+    let (Some(booking), Some(b)) = (booking, detail) else {
+        bot.answer_callback_query(&q.id)
+            .text("Заявка уже обработана")
+            .await?;
+        return Ok(());
+    };
+
+    let (client_text, admin_text) = match decision {
+        Decision::Approve => {
+            if !repo::approve_booking(&state.pool, booking_id).await? {
+                bot.answer_callback_query(&q.id)
+                    .text("Заявка уже обработана")
+                    .await?;
+                return Ok(());
+            }
+            (
+                format!(
+                    "🎉 Мастер подтвердил твою запись!\n💅 {}\n📅 {} · {}",
+                    b.service_name,
+                    format_date_ru(&b.date),
+                    &b.start_time[..5],
+                ),
+                format!("✅ Запись {} подтверждена", b.client_first_name),
+            )
+        }
+        Decision::Decline => {
+            let refund_info = refunds::process_refund_if_needed(
+                &state.pool,
+                state.payments.as_ref(),
+                &booking,
+                Some(booking.prepaid_amount),
+            )
+            .await;
+            let refund_text = refund_info
+                .as_ref()
+                .map(|r| format!("\n\n💰 {}", r.text))
+                .unwrap_or_default();
+            // A Telegram prepayment is returned by the master: the notice says what
+            let admin_refund_text = refund_info
+                .as_ref()
+                .map(|r| format!("\n\n💰 {}", r.admin_text()))
+                .unwrap_or_default();
+
+            repo::cancel_booking(&state.pool, &booking, CancelledBy::Admin).await?;
+            (
+                format!(
+                    "😔 Мастер не сможет принять тебя {} в {}.{}\n\nВыбери другое время 💕",
+                    format_date_ru(&b.date),
+                    &b.start_time[..5],
+                    refund_text,
+                ),
+                format!("❌ Заявка {} отклонена{}", b.client_first_name, admin_refund_text),
+            )
+        }
+    };
+
+    bot.answer_callback_query(&q.id).text("Готово").await?;
+    bot.send_message(ChatId(b.client_tg_id), client_text).await.ok();
+
+    if let Some(message) = &q.message {
+        // Drop the buttons so the request can't be answered twice
+        bot.edit_message_reply_markup(message.chat().id, message.id())
+            .await
+            .ok();
+        bot.send_message(message.chat().id, admin_text).await?;
+    }
+
+    Ok(())
+}
+
+/// Close a past visit from the Completed/No-show buttons. A completed visit records
+/// the expected amount; `/collected` corrects it.
+async fn handle_visit_outcome(
+    bot: &Bot,
+    q: &CallbackQuery,
+    state: &BotState,
+    outcome: Outcome,
+    booking_id: i64,
+) -> anyhow::Result<()> {
+    let Some(b) = repo::booking_detail(&state.pool, booking_id).await? else {
+        bot.answer_callback_query(&q.id).text("Запись не найдена").await?;
+        return Ok(());
+    };
+
+    let amount = match outcome {
+        Outcome::Completed => b.amount_to_collect(),
+        Outcome::NoShow => 0,
+    };
+    if !repo::close_visit(&state.pool, booking_id, outcome, amount).await? {
+        bot.answer_callback_query(&q.id)
+            .text("Визит уже закрыт или ещё не начался")
+            .await?;
+        return Ok(());
+    }
+
+    bot.answer_callback_query(&q.id).text("Готово").await?;
+
+    let Some(message) = &q.message else {
+        return Ok(());
+    };
+    // Keep the buttons of the other visits in the same message
+    let remaining: Vec<Vec<InlineKeyboardButton>> = message
+        .regular_message()
+        .and_then(|m| m.reply_markup())
+        .map(|markup| {
+            markup
+                .inline_keyboard
+                .iter()
+                .filter(|row| {
+                    !row.iter().any(|button| {
+                        matches!(&button.kind, InlineKeyboardButtonKind::CallbackData(data)
+                            if visits::parse_callback(data).map(|(_, id)| id) == Some(booking_id))
+                    })
+                })
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default();
+    let edit = bot.edit_message_reply_markup(message.chat().id, message.id());
+    if remaining.is_empty() {
+        edit.await.ok();
+    } else {
+        edit.reply_markup(InlineKeyboardMarkup::new(remaining)).await.ok();
+    }
+
+    let text = match outcome {
+        Outcome::Completed => format!(
+            "✅ {} · {} — визит закрыт, получено {} ₽\n\
+             Другая сумма: <code>/collected {} СУММА</code>",
+            b.client_first_name,
+            &b.start_time[..5],
+            amount,
+            booking_id,
+        ),
+        Outcome::NoShow => format!(
+            "🚫 {} · {} — отмечена неявка",
+            b.client_first_name,
+            &b.start_time[..5],
+        ),
+    };
+    bot.send_message(message.chat().id, text)
+        .parse_mode(ParseMode::Html)
+        .await?;
+
+    Ok(())
+}
+
+/// Approve/Decline buttons for a booking awaiting approval.
+fn approval_keyboard(booking_id: i64) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![approval::buttons(booking_id)
+        .into_iter()
+        .map(|(text, data)| InlineKeyboardButton::callback(text, data))
+        .collect::<Vec<_>>()])
+}
+
+// ── Telegram Payments ──
+
+/// Approve the invoice only while its booking still awaits payment.
+async fn handle_pre_checkout(
+    bot: Bot,
+    q: PreCheckoutQuery,
+    state: &BotState,
+) -> anyhow::Result<()> {
+    let booking = match telegram_payments::parse_invoice_payload(&q.invoice_payload) {
+        Some(id) => fetch_booking(&state.pool, id).await?,
+        None => None,
+    };
+
+    let check = if q.currency != Currency::RUB {
+        Err("Неверная валюта счёта")
+    } else {
+        telegram_payments::check_pre_checkout(booking.as_ref(), q.total_amount as i64)
+    };
+
+    match check {
+        Ok(()) => {
+            bot.answer_pre_checkout_query(&q.id, true).await?;
+        }
+        Err(reason) => {
+            tracing::warn!(payload = %q.invoice_payload, reason, "Pre-checkout rejected");
+            bot.answer_pre_checkout_query(&q.id, false)
+                .error_message(reason)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Confirm the booking, the same way the server does on `payment.succeeded`.
+async fn handle_successful_payment(
+    bot: Bot,
+    msg: Message,
+    payment: SuccessfulPayment,
+    state: &BotState,
+) -> anyhow::Result<()> {
+    let Some(booking_id) = telegram_payments::parse_invoice_payload(&payment.invoice_payload)
+    else {
+        tracing::warn!(payload = %payment.invoice_payload, "Payment with unknown payload");
+        return Ok(());
+    };
+    let amount = payment.total_amount as i64 / 100;
+
+    let confirmed = repo::confirm_payment(
+        &state.pool,
+        booking_id,
+        Some(amount),
+        Some(&payment.telegram_payment_charge_id),
+    )
+    .await?;
+
+    if !confirmed {
+        // Expired between pre-checkout and payment: money arrived without a booking
+        tracing::error!(
+            booking_id,
+            charge_id = %payment.telegram_payment_charge_id,
+            "Telegram payment for a booking that is no longer pending"
+        );
+        bot.send_message(
+            msg.chat.id,
+            "😔 Оплата пришла после истечения брони. Мастер вернёт деньги или подберёт тебе время.",
+        )
+        .await?;
+        bot.send_message(
+            ChatId(state.admin_tg_id),
+            format!(
+                "⚠️ Оплата {} ₽ за просроченную запись #{} (Telegram, charge {}). Нужен возврат вручную.",
+                amount, booking_id, payment.telegram_payment_charge_id
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    tracing::info!(booking_id, amount, "Telegram payment succeeded");
+
+    if let Some(b) = repo::booking_detail(&state.pool, booking_id).await? {
+        let awaiting_approval = b.status == "awaiting_approval";
+        let client_text = if awaiting_approval {
+            "✅ Оплата получена! Мастер подтвердит запись в ближайшее время."
+        } else {
+            "✅ Оплата получена, ты записана!"
+        };
+        bot.send_message(
+            msg.chat.id,
+            format!(
+                "{}\n💅 {}\n📅 {} · {}",
+                client_text,
+                b.service_name,
+                format_date_ru(&b.date),
+                &b.start_time[..5],
+            ),
+        )
+        .await?;
+
+        let addon_text = if b.with_lower_lashes.unwrap_or(false) {
+            "\n   + нижние ресницы"
+        } else {
+            ""
+        };
+        let title = if awaiting_approval {
+            "🙋 Новая заявка — нужно подтверждение!"
+        } else {
+            "📋 Новая запись!"
+        };
+        let admin_msg = bot.send_message(
+            ChatId(state.admin_tg_id),
+            format!(
+                "{} 💳 Оплачено в Telegram\n\n👤 {}\n💅 {}{}\n📅 {} в {} — {}\n💰 Предоплата {} ₽",
+                title,
+                b.client_mention(),
+                b.service_name,
+                addon_text,
+                format_date_ru(&b.date),
+                &b.start_time[..5],
+                &b.end_time[..5],
+                amount,
+            ),
+        );
+        if awaiting_approval {
+            admin_msg.reply_markup(approval_keyboard(booking_id)).await?;
+        } else {
+            admin_msg.await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn fetch_booking(
+    pool: &sqlx::SqlitePool,
+    booking_id: i64,
+) -> Result<Option<bimbo_lashes_core::models::Booking>, sqlx::Error> {
+    sqlx::query_as("SELECT * FROM bookings WHERE id = ?")
+        .bind(booking_id)
+        .fetch_optional(pool)
+        .await
+}
+
 // ── Admin helpers ──
 
 async fn send_day_bookings(
@@ -646,22 +978,7 @@ async fn send_day_bookings(
     date: &str,
     label: &str,
 ) -> anyhow::Result<()> {
-    let bookings = sqlx::query_as::<_, BookingInfo>(
-        "SELECT b.id, s.name as service_name, s.price as service_price,
-                COALESCE(b.date, sl.date) as date,
-                COALESCE(b.start_time, sl.start_time) as start_time,
-                COALESCE(b.end_time, sl.end_time) as end_time,
-                b.client_tg_id, b.client_username, b.client_first_name,
-                b.payment_status, b.prepaid_amount
-         FROM bookings b
-         JOIN services s ON s.id = b.service_id
-         LEFT JOIN available_slots sl ON sl.id = b.slot_id
-         WHERE COALESCE(b.date, sl.date) = ? AND b.status IN ('confirmed', 'pending_payment')
-         ORDER BY COALESCE(b.start_time, sl.start_time) ASC",
-    )
-    .bind(date)
-    .fetch_all(pool)
-    .await?;
+    let bookings = repo::day_bookings(pool, date).await?;
 
     if bookings.is_empty() {
         bot.send_message(
@@ -678,36 +995,45 @@ async fn send_day_bookings(
         format_date_ru(date)
     );
 
-    let total: i64 = bookings.iter().map(|b| b.service_price).sum();
+    let total: i64 = bookings
+        .iter()
+        .filter(|b| b.status != "no_show")
+        .map(|b| b.total_price.unwrap_or(b.service_price))
+        .sum();
+    let to_collect: i64 = bookings.iter().map(|b| b.amount_to_collect()).sum();
 
     for (i, b) in bookings.iter().enumerate() {
-        let mention = b
-            .client_username
-            .as_ref()
-            .map(|u| format!("@{}", u))
-            .unwrap_or_else(|| b.client_first_name.clone());
-
-        let payment_badge = match b.payment_status.as_str() {
-            "paid" => format!(" · 💳 {} ₽", b.prepaid_amount),
-            "pending" => " · ⏳".to_string(),
+        let payment_badge = match (b.status.as_str(), b.payment_status.as_deref()) {
+            ("awaiting_approval", _) => " · 🙋 ждёт подтверждения".to_string(),
+            ("completed", _) => format!(" · ✅ получено {} ₽", b.amount_collected.unwrap_or(0)),
+            ("no_show", _) => " · 🚫 неявка".to_string(),
+            (_, Some("paid")) => format!(" · 💳 {} ₽", b.prepaid_amount.unwrap_or(0)),
+            (_, Some("pending")) => " · ⏳".to_string(),
+            _ => String::new(),
+        };
+        let tip_badge = match b.tip_amount {
+            Some(tip) if tip > 0 => format!(" (чаевые {} ₽)", tip),
             _ => String::new(),
         };
         text.push_str(&format!(
-            "{}. <b>{} — {}</b>\n   👤 {} · 💅 {}\n   💰 {} ₽{}\n\n",
+            "{}. <b>{} — {}</b>\n   👤 {} · 💅 {}\n   💰 {} ₽{}{}\n   💵 Получить на месте: <b>{} ₽</b>\n\n",
             i + 1,
             &b.start_time[..5],
             &b.end_time[..5],
-            mention,
+            b.client_mention(),
             b.service_name,
-            b.service_price,
+            b.total_price.unwrap_or(b.service_price),
             payment_badge,
+            tip_badge,
+            b.amount_to_collect(),
         ));
     }
 
     text.push_str(&format!(
-        "━━━━━━━━━━━━━\n📊 Всего записей: <b>{}</b>\n💰 Итого: <b>{} ₽</b>",
+        "━━━━━━━━━━━━━\n📊 Всего записей: <b>{}</b>\n💰 Итого: <b>{} ₽</b>\n💵 Получить на месте: <b>{} ₽</b>",
         bookings.len(),
         total,
+        to_collect,
     ));
 
     let buttons: Vec<Vec<InlineKeyboardButton>> = bookings
@@ -744,25 +1070,11 @@ async fn send_reminders(bot: Bot, pool: sqlx::SqlitePool) {
     loop {
         ticker.tick().await;
 
-        let tomorrow = (chrono::Local::now() + chrono::TimeDelta::days(1))
+        let tomorrow = (moscow_now() + chrono::TimeDelta::days(1))
             .format("%Y-%m-%d")
             .to_string();
 
-        let bookings = sqlx::query_as::<_, BookingInfo>(
-            "SELECT b.id, s.name as service_name, s.price as service_price,
-                    COALESCE(b.date, sl.date) as date,
-                    COALESCE(b.start_time, sl.start_time) as start_time,
-                    COALESCE(b.end_time, sl.end_time) as end_time,
-                    b.client_tg_id, b.client_username, b.client_first_name,
-                    b.payment_status, b.prepaid_amount
-             FROM bookings b
-             JOIN services s ON s.id = b.service_id
-             LEFT JOIN available_slots sl ON sl.id = b.slot_id
-             WHERE COALESCE(b.date, sl.date) = ? AND b.status = 'confirmed' AND b.reminder_sent = 0",
-        )
-        .bind(&tomorrow)
-        .fetch_all(&pool)
-        .await;
+        let bookings = repo::bookings_to_remind(&pool, &tomorrow).await;
 
         if let Err(e) = &bookings {
             tracing::error!("Reminder query failed: {}", e);
@@ -814,6 +1126,7 @@ fn format_date_ru(date_str: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use bimbo_lashes_core::{testing, time};
 
     #[test]
     fn test_format_date_ru_basic() {
@@ -854,4 +1167,67 @@ mod tests {
     fn test_format_date_ru_two_parts() {
         assert_eq!(format_date_ru("2026-02"), "2026-02");
     }
+
+    // ── cancel: ──
+
+    /// A paid 1000 ₽ prepayment `hours_ahead` from now; refunds are 100% over 48h
+    /// and 50% over 24h before the visit.
+    async fn paid_booking(hours_ahead: i64) -> (sqlx::SqlitePool, Booking) {
+        let pool = testing::memory_db().await;
+        sqlx::query(
+            "DELETE FROM cancellation_tiers;
+             INSERT INTO cancellation_tiers (service_id, more_than_hours, refund_percent)
+             VALUES (NULL, 48, 100), (NULL, 24, 50)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let start = moscow_now() + chrono::TimeDelta::hours(hours_ahead);
+        let date = start.format("%Y-%m-%d").to_string();
+        let time = start.format("%H:%M").to_string();
+        let end = time::minutes_to_time(time::time_to_minutes(&time).unwrap() + 60);
+        let booking = testing::paid_booking(&pool, &date, &time, &end, 1000).await;
+        (pool, booking)
+    }
+
+    async fn booking_status(pool: &sqlx::SqlitePool) -> String {
+        sqlx::query_scalar("SELECT status FROM bookings WHERE id = 1")
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_cancel_in_time_refunds_in_full() {
+        let (pool, booking) = paid_booking(72).await;
+        let provider = payments::MockProvider::new();
+
+        let outcome = cancel_by_client(&pool, &provider, &booking).await.unwrap().unwrap();
+        assert_eq!(provider.refunded("pay-1"), 1000);
+        assert!(outcome.text.contains("1000 ₽ будет возвращена"));
+        assert_eq!(outcome.manual, None);
+        assert_eq!(booking_status(&pool).await, "cancelled");
+    }
+
+    #[tokio::test]
+    async fn test_cancel_within_partial_tier_refunds_part() {
+        let (pool, booking) = paid_booking(30).await;
+        let provider = payments::MockProvider::new();
+
+        let outcome = cancel_by_client(&pool, &provider, &booking).await.unwrap().unwrap();
+        assert_eq!(provider.refunded("pay-1"), 500);
+        assert!(outcome.text.contains("500 ₽ из 1000 ₽"));
+        assert_eq!(booking_status(&pool).await, "cancelled");
+    }
+
+    #[tokio::test]
+    async fn test_late_cancel_refunds_nothing() {
+        let (pool, booking) = paid_booking(2).await;
+        let provider = payments::MockProvider::new();
+
+        cancel_by_client(&pool, &provider, &booking).await.unwrap();
+        assert_eq!(provider.refunded("pay-1"), 0);
+        assert_eq!(booking_status(&pool).await, "cancelled");
+    }
 }
@@ -0,0 +1,4 @@
+-- One-time fix: remove duplicate services (keep lowest ID per name)
+DELETE FROM services WHERE id NOT IN (
+    SELECT MIN(id) FROM services GROUP BY name
+);
@@ -0,0 +1,10 @@
+-- Replace services with new catalog
+
+-- Deactivate all old services
+UPDATE services SET is_active = 0;
+
+-- Insert new catalog
+INSERT INTO services (name, description, price, duration_min, sort_order, is_active) VALUES
+    ('Наращивание ресниц', 'Любой объём', 2500, 120, 1, 1),
+    ('Наращивание нижних', 'Наращивание только нижних ресниц', 500, 20, 2, 1),
+    ('Коррекция', 'Коррекция наращивания', 1500, 60, 3, 1);
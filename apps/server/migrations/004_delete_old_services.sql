@@ -0,0 +1,2 @@
+-- Delete old inactive services (no booking history)
+DELETE FROM services WHERE is_active = 0;
@@ -0,0 +1,17 @@
+-- Smart slots: 1-hour base slots, multi-slot bookings, addon support
+
+-- Multi-slot booking tracking
+ALTER TABLE available_slots ADD COLUMN booking_id INTEGER;
+
+-- Store date/time directly on bookings (no more JOIN dependency)
+ALTER TABLE bookings ADD COLUMN date TEXT;
+ALTER TABLE bookings ADD COLUMN start_time TEXT;
+ALTER TABLE bookings ADD COLUMN end_time TEXT;
+ALTER TABLE bookings ADD COLUMN with_lower_lashes INTEGER NOT NULL DEFAULT 0;
+
+-- Service type: 'main' (bookable) vs 'addon' (checkbox add-on)
+ALTER TABLE services ADD COLUMN service_type TEXT NOT NULL DEFAULT 'main';
+UPDATE services SET service_type = 'addon' WHERE name LIKE '%нижних%';
+
+-- Clear old slots (no booking history exists)
+DELETE FROM available_slots;
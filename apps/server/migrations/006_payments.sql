@@ -0,0 +1,4 @@
+-- Payment support (YooKassa prepayment)
+ALTER TABLE bookings ADD COLUMN payment_status TEXT NOT NULL DEFAULT 'none';
+ALTER TABLE bookings ADD COLUMN yookassa_payment_id TEXT;
+ALTER TABLE bookings ADD COLUMN prepaid_amount INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,8 @@
+-- Performance indexes
+CREATE INDEX IF NOT EXISTS idx_bookings_client_tg_id ON bookings(client_tg_id);
+CREATE INDEX IF NOT EXISTS idx_bookings_date ON bookings(date);
+CREATE INDEX IF NOT EXISTS idx_bookings_status ON bookings(status);
+CREATE INDEX IF NOT EXISTS idx_bookings_payment_status ON bookings(payment_status);
+CREATE INDEX IF NOT EXISTS idx_slots_date ON available_slots(date);
+CREATE INDEX IF NOT EXISTS idx_slots_booking_id ON available_slots(booking_id);
+CREATE INDEX IF NOT EXISTS idx_slots_date_booked ON available_slots(date, is_booked);
@@ -0,0 +1,17 @@
+-- Weekly working-hours template + per-date exceptions
+
+-- One row per working weekday (0 = Monday … 6 = Sunday); missing row = day off
+CREATE TABLE IF NOT EXISTS schedule_template (
+    weekday INTEGER PRIMARY KEY,
+    start_hour INTEGER NOT NULL,
+    end_hour INTEGER NOT NULL
+);
+
+-- Date overrides: either a day off or custom hours for that date
+CREATE TABLE IF NOT EXISTS schedule_exceptions (
+    date TEXT PRIMARY KEY,
+    is_day_off BOOLEAN NOT NULL DEFAULT 1,
+    start_hour INTEGER,
+    end_hour INTEGER,
+    note TEXT NOT NULL DEFAULT ''
+);
@@ -0,0 +1,3 @@
+-- Per-service cleanup buffers around appointments
+ALTER TABLE services ADD COLUMN buffer_before_min INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE services ADD COLUMN buffer_after_min INTEGER NOT NULL DEFAULT 0;
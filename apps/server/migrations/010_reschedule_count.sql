@@ -0,0 +1,2 @@
+-- Count client-initiated reschedules per booking
+ALTER TABLE bookings ADD COLUMN reschedule_count INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,14 @@
+-- Cancellation refund tiers: cancelling more than `more_than_hours` before the
+-- appointment returns `refund_percent` of the prepayment. The best matching tier wins.
+-- service_id NULL = default policy; a service with its own rows ignores the default.
+CREATE TABLE IF NOT EXISTS cancellation_tiers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    service_id INTEGER REFERENCES services(id) ON DELETE CASCADE,
+    more_than_hours INTEGER NOT NULL,
+    refund_percent INTEGER NOT NULL CHECK (refund_percent BETWEEN 0 AND 100)
+);
+
+CREATE INDEX IF NOT EXISTS idx_cancellation_tiers_service ON cancellation_tiers(service_id);
+
+-- Default keeps the previous rule: full refund when cancelling more than 24h ahead
+INSERT INTO cancellation_tiers (service_id, more_than_hours, refund_percent) VALUES (NULL, 24, 100);
@@ -0,0 +1,15 @@
+-- Refund history: one row per refund request sent to YooKassa (full or partial)
+CREATE TABLE IF NOT EXISTS refunds (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    booking_id INTEGER NOT NULL REFERENCES bookings(id),
+    payment_id TEXT NOT NULL,
+    yookassa_refund_id TEXT UNIQUE,
+    amount INTEGER NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',  -- pending | succeeded | canceled | failed
+    reason TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL DEFAULT (datetime('now', '+3 hours')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now', '+3 hours'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_refunds_booking_id ON refunds(booking_id);
+CREATE INDEX IF NOT EXISTS idx_refunds_status ON refunds(status);
@@ -0,0 +1,3 @@
+-- How the prepayment is collected: YooKassa redirect or a Telegram invoice
+ALTER TABLE bookings ADD COLUMN payment_method TEXT NOT NULL DEFAULT 'yookassa';
+ALTER TABLE bookings ADD COLUMN telegram_charge_id TEXT;
@@ -0,0 +1,10 @@
+-- Mismatches between our bookings and the payment provider found by reconciliation;
+-- one row per problem so the admin is told once
+CREATE TABLE IF NOT EXISTS payment_discrepancies (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    booking_id INTEGER NOT NULL REFERENCES bookings(id),
+    payment_id TEXT NOT NULL,
+    detail TEXT NOT NULL,
+    detected_at TEXT NOT NULL DEFAULT (datetime('now', '+3 hours')),
+    UNIQUE (booking_id, detail)
+);
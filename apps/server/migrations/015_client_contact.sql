@@ -0,0 +1,3 @@
+-- Client contact for fiscal receipts (54-FZ): phone as 7XXXXXXXXXX and/or email
+ALTER TABLE bookings ADD COLUMN client_phone TEXT;
+ALTER TABLE bookings ADD COLUMN client_email TEXT;
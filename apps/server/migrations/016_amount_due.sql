@@ -0,0 +1,11 @@
+-- Price of the visit at booking time (service + addon) and the tip paid online.
+-- prepaid_amount stays the amount charged online, tip included.
+ALTER TABLE bookings ADD COLUMN amount_due INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE bookings ADD COLUMN tip_amount INTEGER NOT NULL DEFAULT 0;
+
+UPDATE bookings SET amount_due =
+    COALESCE((SELECT price FROM services WHERE services.id = bookings.service_id), 0)
+    + CASE WHEN with_lower_lashes = 1
+           THEN COALESCE((SELECT price FROM services WHERE service_type = 'addon' AND is_active = 1 LIMIT 1), 500)
+           ELSE 0
+      END;
@@ -0,0 +1,8 @@
+-- Bookings confirmed without prepayment: per service, and for clients the master trusts
+ALTER TABLE services ADD COLUMN prepayment_required INTEGER NOT NULL DEFAULT 1;
+
+CREATE TABLE IF NOT EXISTS trusted_clients (
+    client_tg_id INTEGER PRIMARY KEY,
+    note TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL DEFAULT (datetime('now', '+3 hours'))
+);
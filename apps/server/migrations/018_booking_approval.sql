@@ -0,0 +1,3 @@
+-- Bookings the master vets before confirming (BOOKING_APPROVAL): once paid, or
+-- right away without prepayment, they wait in status 'awaiting_approval'
+ALTER TABLE bookings ADD COLUMN needs_approval INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,4 @@
+-- Bookings the master enters for phone and walk-in clients (client_tg_id = 0 when
+-- the client has no Telegram). Slots created for them outside open hours are ad hoc:
+-- dropped rather than reopened once the booking no longer holds them.
+ALTER TABLE available_slots ADD COLUMN ad_hoc INTEGER NOT NULL DEFAULT 0;
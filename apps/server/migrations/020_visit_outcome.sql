@@ -0,0 +1,5 @@
+-- Visit outcome: the master closes past bookings as 'completed' or 'no_show'
+ALTER TABLE bookings ADD COLUMN amount_collected INTEGER;  -- RUB taken at the visit
+ALTER TABLE bookings ADD COLUMN closed_at TEXT;
+-- 0: not asked yet, 1: asked after the appointment, 2: reminded the next morning
+ALTER TABLE bookings ADD COLUMN outcome_prompts INTEGER NOT NULL DEFAULT 0;
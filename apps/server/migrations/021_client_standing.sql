@@ -0,0 +1,9 @@
+-- Who cancelled a booking: 'client' or 'admin' (NULL for older cancellations).
+-- Late client cancellations count against the client's standing.
+ALTER TABLE bookings ADD COLUMN cancelled_by TEXT;
+
+-- The master forgave a client: no-shows and late cancellations before reset_at don't count
+CREATE TABLE IF NOT EXISTS client_standing_resets (
+    client_tg_id INTEGER PRIMARY KEY,
+    reset_at TEXT NOT NULL
+);
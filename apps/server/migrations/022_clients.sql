@@ -0,0 +1,29 @@
+-- Client profiles: Telegram identity refreshed on every authenticated request,
+-- plus the master's notes. Visit stats are derived from bookings.
+CREATE TABLE IF NOT EXISTS clients (
+    tg_id INTEGER PRIMARY KEY,
+    username TEXT,
+    first_name TEXT NOT NULL,
+    last_name TEXT,
+    phone TEXT,                           -- 7XXXXXXXXXX
+    lash_style TEXT NOT NULL DEFAULT '',  -- e.g. classic, 2D, "fox eye"
+    curl TEXT NOT NULL DEFAULT '',        -- C, D, L...
+    length TEXT NOT NULL DEFAULT '',      -- e.g. "9-12"
+    allergies TEXT NOT NULL DEFAULT '',
+    notes TEXT NOT NULL DEFAULT '',
+    tags TEXT NOT NULL DEFAULT '',        -- comma-separated
+    created_at TEXT NOT NULL DEFAULT (datetime('now', '+3 hours')),
+    last_seen_at TEXT NOT NULL DEFAULT (datetime('now', '+3 hours'))
+);
+
+-- Everyone who has booked so far, with the name and contact of their latest booking
+INSERT OR IGNORE INTO clients (tg_id, username, first_name, phone, created_at, last_seen_at)
+SELECT b.client_tg_id, b.client_username, b.client_first_name,
+       (SELECT p.client_phone FROM bookings p
+        WHERE p.client_tg_id = b.client_tg_id AND p.client_phone IS NOT NULL
+        ORDER BY p.id DESC LIMIT 1),
+       (SELECT MIN(f.created_at) FROM bookings f WHERE f.client_tg_id = b.client_tg_id),
+       b.created_at
+FROM bookings b
+WHERE b.client_tg_id != 0
+  AND b.id = (SELECT MAX(l.id) FROM bookings l WHERE l.client_tg_id = b.client_tg_id);
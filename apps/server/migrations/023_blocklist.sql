@@ -0,0 +1,13 @@
+-- People the master refuses to book: matched by Telegram id, username or phone
+CREATE TABLE IF NOT EXISTS blocked_clients (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    tg_id INTEGER UNIQUE,
+    username TEXT COLLATE NOCASE,  -- without @
+    phone TEXT,                    -- 7XXXXXXXXXX
+    reason TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL DEFAULT (datetime('now', '+3 hours')),
+    CHECK (tg_id IS NOT NULL OR username IS NOT NULL OR phone IS NOT NULL)
+);
+
+CREATE INDEX IF NOT EXISTS idx_blocked_clients_username ON blocked_clients(username);
+CREATE INDEX IF NOT EXISTS idx_blocked_clients_phone ON blocked_clients(phone);
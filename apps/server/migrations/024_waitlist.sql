@@ -0,0 +1,23 @@
+-- Clients waiting for a service on a date range. Freed blocks are offered
+-- first come, first served, each one held for the client for a few minutes.
+CREATE TABLE IF NOT EXISTS waitlist (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    client_tg_id INTEGER NOT NULL,
+    client_first_name TEXT NOT NULL,
+    service_id INTEGER NOT NULL REFERENCES services(id),
+    date_from TEXT NOT NULL,
+    date_to TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'waiting',  -- waiting | offered | booked | expired | cancelled
+    offered_date TEXT,
+    offered_time TEXT,                       -- appointment start, HH:MM
+    -- While offered, other clients see hold_start..hold_end (buffers included)
+    -- on offered_date as taken until hold_until
+    hold_start TEXT,
+    hold_end TEXT,
+    hold_until TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now', '+3 hours'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_waitlist_status ON waitlist(status, created_at);
+CREATE INDEX IF NOT EXISTS idx_waitlist_client ON waitlist(client_tg_id);
+CREATE INDEX IF NOT EXISTS idx_waitlist_offered ON waitlist(offered_date) WHERE status = 'offered';
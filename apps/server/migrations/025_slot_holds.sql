@@ -0,0 +1,13 @@
+-- A block a client keeps while confirming a booking (POST /api/holds):
+-- other clients see its slots as taken until expires_at
+CREATE TABLE IF NOT EXISTS slot_holds (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    client_tg_id INTEGER NOT NULL,
+    date TEXT NOT NULL,
+    start_time TEXT NOT NULL,  -- block start, buffers included
+    end_time TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_slot_holds_date ON slot_holds(date);
+CREATE INDEX IF NOT EXISTS idx_slot_holds_client ON slot_holds(client_tg_id);
@@ -0,0 +1,8 @@
+-- Dates the weekly schedule has opened slots for, with the hours used. The
+-- materializer skips them, so a slot the master deletes stays deleted; new hours
+-- for the date (template or exception) open it again
+CREATE TABLE IF NOT EXISTS materialized_days (
+    date TEXT PRIMARY KEY,
+    start_hour INTEGER NOT NULL,
+    end_hour INTEGER NOT NULL
+);
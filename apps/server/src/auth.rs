@@ -4,6 +4,7 @@ use axum::{
     middleware::Next,
     response::Response,
 };
+use bimbo_lashes_core::repo;
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
 use std::collections::BTreeMap;
@@ -98,6 +99,31 @@ pub async fn require_auth(
     Ok(next.run(req).await)
 }
 
+/// Axum middleware that refreshes the client's profile from valid Telegram auth.
+/// Never rejects: the handlers still check auth themselves.
+pub async fn track_client(State(state): State<Arc<AppState>>, req: Request, next: Next) -> Response {
+    let user = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|h| extract_user_from_header(h, &state.bot_token));
+
+    if let Some(user) = user {
+        if let Err(e) = repo::upsert_client(
+            &state.db,
+            user.id,
+            user.username.as_deref(),
+            &user.first_name,
+            user.last_name.as_deref(),
+        )
+        .await
+        {
+            tracing::error!(tg_id = user.id, error = %e, "Failed to update client profile");
+        }
+    }
+    next.run(req).await
+}
+
 /// Check if the authenticated user is the admin.
 pub fn is_admin(user: &TelegramUser, admin_tg_id: i64) -> bool {
     user.id == admin_tg_id
@@ -0,0 +1,130 @@
+//! Real client IP behind reverse proxies (Caddy → nginx → server).
+//!
+//! `X-Forwarded-For` is only believed when the TCP peer is a trusted proxy, and is
+//! read right to left: the first hop that isn't a trusted proxy is the client.
+
+use axum::http::HeaderMap;
+use ipnet::IpNet;
+use std::net::IpAddr;
+
+/// Loopback and private networks: the proxies run on the same host / docker network.
+pub const DEFAULT_TRUSTED_PROXIES: &str =
+    "127.0.0.0/8,::1/128,10.0.0.0/8,172.16.0.0/12,192.168.0.0/16,fc00::/7";
+
+/// A list of networks (CIDR).
+#[derive(Debug, Clone, Default)]
+pub struct IpNets(Vec<IpNet>);
+
+impl IpNets {
+    /// Parse CIDRs; a bare address is a single-host network.
+    pub fn parse<'a>(items: impl IntoIterator<Item = &'a str>) -> anyhow::Result<Self> {
+        let mut nets = Vec::new();
+        for item in items.into_iter().map(str::trim).filter(|s| !s.is_empty()) {
+            let net = match item.parse::<IpNet>() {
+                Ok(net) => net,
+                Err(_) => item
+                    .parse::<IpAddr>()
+                    .map(IpNet::from)
+                    .map_err(|_| anyhow::anyhow!("invalid network: {}", item))?,
+            };
+            nets.push(net);
+        }
+        Ok(Self(nets))
+    }
+
+    /// Parse a comma-separated list, e.g. the `TRUSTED_PROXIES` env var.
+    pub fn parse_list(list: &str) -> anyhow::Result<Self> {
+        Self::parse(list.split(','))
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = canonical(ip);
+        self.0.iter().any(|net| net.contains(&ip))
+    }
+}
+
+/// Client address for a request received from `peer`.
+pub fn client_ip(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &IpNets) -> IpAddr {
+    let peer = canonical(peer);
+    if !trusted_proxies.contains(peer) {
+        return peer;
+    }
+
+    let hops: Vec<IpAddr> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|hop| hop.trim().parse().ok())
+        .collect();
+
+    let mut client = peer;
+    for hop in hops.into_iter().rev() {
+        client = canonical(hop);
+        if !trusted_proxies.contains(client) {
+            break;
+        }
+    }
+    client
+}
+
+/// IPv4-mapped IPv6 (`::ffff:1.2.3.4`) as plain IPv4, so v4 ranges match.
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        v4 => v4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn forwarded(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", value.parse().unwrap());
+        headers
+    }
+
+    fn trusted() -> IpNets {
+        IpNets::parse_list(DEFAULT_TRUSTED_PROXIES).unwrap()
+    }
+
+    #[test]
+    fn test_cidr_matching() {
+        let nets = IpNets::parse(["185.71.76.0/27", "77.75.156.35", "2a02:5180::/32"]).unwrap();
+        assert!(nets.contains(ip("185.71.76.31")));
+        assert!(!nets.contains(ip("185.71.76.32")));
+        assert!(nets.contains(ip("77.75.156.35")));
+        assert!(!nets.contains(ip("77.75.156.36")));
+        assert!(nets.contains(ip("2a02:5180::1")));
+        assert!(nets.contains(ip("::ffff:185.71.76.1")));
+    }
+
+    #[test]
+    fn test_invalid_network_rejected() {
+        assert!(IpNets::parse_list("10.0.0.0/8, not-an-ip").is_err());
+    }
+
+    #[test]
+    fn test_direct_peer_ignores_header() {
+        let headers = forwarded("185.71.76.1");
+        assert_eq!(client_ip(ip("203.0.113.9"), &headers, &trusted()), ip("203.0.113.9"));
+    }
+
+    #[test]
+    fn test_proxy_chain_takes_first_untrusted_from_right() {
+        // Spoofed first entry, then the real client as seen by Caddy, then nginx
+        let headers = forwarded("185.71.76.1, 203.0.113.9, 172.18.0.3");
+        assert_eq!(client_ip(ip("172.18.0.2"), &headers, &trusted()), ip("203.0.113.9"));
+    }
+
+    #[test]
+    fn test_proxy_without_header_is_the_client() {
+        assert_eq!(client_ip(ip("127.0.0.1"), &HeaderMap::new(), &trusted()), ip("127.0.0.1"));
+    }
+}
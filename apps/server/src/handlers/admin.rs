@@ -3,12 +3,17 @@ use axum::{
     http::{header, StatusCode},
     Json,
 };
+use bimbo_lashes_core::{
+    models::NO_TELEGRAM,
+    receipts, refunds, repo, slots,
+    time::{minutes_to_time, moscow_now, time_to_minutes},
+};
 use std::sync::Arc;
 
 use crate::{auth, models::*, AppState};
 
 /// Helper: extract admin user (validates both auth and admin status).
-fn extract_admin(
+pub fn extract_admin(
     auth_header: Option<&str>,
     state: &AppState,
 ) -> Result<TelegramUser, (StatusCode, Json<ApiResponse<()>>)> {
@@ -44,7 +49,8 @@ pub async fn list_all_services(
     extract_admin(auth_header, &state)?;
 
     let services = sqlx::query_as::<_, Service>(
-        "SELECT id, name, description, price, duration_min, is_active, sort_order, service_type
+        "SELECT id, name, description, price, duration_min, is_active, sort_order, service_type,
+         buffer_before_min, buffer_after_min, prepayment_required
          FROM services ORDER BY sort_order ASC",
     )
     .fetch_all(&state.db)
@@ -67,14 +73,18 @@ pub async fn create_service(
     extract_admin(auth_header, &state)?;
 
     let id = sqlx::query(
-        "INSERT INTO services (name, description, price, duration_min, sort_order)
-         VALUES (?, ?, ?, ?, ?)",
+        "INSERT INTO services (name, description, price, duration_min, sort_order,
+         buffer_before_min, buffer_after_min, prepayment_required)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
     )
     .bind(&body.name)
     .bind(body.description.as_deref().unwrap_or(""))
     .bind(body.price)
     .bind(body.duration_min)
     .bind(body.sort_order.unwrap_or(0))
+    .bind(body.buffer_before_min.unwrap_or(0).max(0))
+    .bind(body.buffer_after_min.unwrap_or(0).max(0))
+    .bind(body.prepayment_required.unwrap_or(true))
     .execute(&state.db)
     .await
     .map_err(|e| {
@@ -84,7 +94,8 @@ pub async fn create_service(
     .last_insert_rowid();
 
     let service = sqlx::query_as::<_, Service>(
-        "SELECT id, name, description, price, duration_min, is_active, sort_order, service_type
+        "SELECT id, name, description, price, duration_min, is_active, sort_order, service_type,
+         buffer_before_min, buffer_after_min, prepayment_required
          FROM services WHERE id = ?",
     )
     .bind(id)
@@ -117,7 +128,10 @@ pub async fn update_service(
          price = COALESCE(?, price),
          duration_min = COALESCE(?, duration_min),
          is_active = COALESCE(?, is_active),
-         sort_order = COALESCE(?, sort_order)
+         sort_order = COALESCE(?, sort_order),
+         buffer_before_min = COALESCE(?, buffer_before_min),
+         buffer_after_min = COALESCE(?, buffer_after_min),
+         prepayment_required = COALESCE(?, prepayment_required)
          WHERE id = ?",
     )
     .bind(&body.name)
@@ -126,6 +140,9 @@ pub async fn update_service(
     .bind(body.duration_min)
     .bind(body.is_active)
     .bind(body.sort_order)
+    .bind(body.buffer_before_min.map(|m| m.max(0)))
+    .bind(body.buffer_after_min.map(|m| m.max(0)))
+    .bind(body.prepayment_required)
     .bind(id)
     .execute(&state.db)
     .await
@@ -135,7 +152,8 @@ pub async fn update_service(
     })?;
 
     let service = sqlx::query_as::<_, Service>(
-        "SELECT id, name, description, price, duration_min, is_active, sort_order, service_type
+        "SELECT id, name, description, price, duration_min, is_active, sort_order, service_type,
+         buffer_before_min, buffer_after_min, prepayment_required
          FROM services WHERE id = ?",
     )
     .bind(id)
@@ -149,6 +167,19 @@ pub async fn update_service(
     Ok(Json(ApiResponse::success(service)))
 }
 
+/// A day's slots for the master, booked ones included. Free ad hoc slots left
+/// behind by a cancelled or moved admin booking are not part of the schedule.
+async fn day_slots(db: &sqlx::SqlitePool, date: &str) -> Result<Vec<AvailableSlot>, sqlx::Error> {
+    sqlx::query_as::<_, AvailableSlot>(
+        "SELECT id, date, start_time, end_time, is_booked, booking_id
+         FROM available_slots WHERE date = ? AND (ad_hoc = 0 OR is_booked = 1)
+         ORDER BY start_time ASC",
+    )
+    .bind(date)
+    .fetch_all(db)
+    .await
+}
+
 /// GET /api/admin/slots?date=YYYY-MM-DD — list slots (all, including booked).
 pub async fn list_slots(
     State(state): State<Arc<AppState>>,
@@ -158,18 +189,12 @@ pub async fn list_slots(
     let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
     extract_admin(auth_header, &state)?;
 
-    let slots = sqlx::query_as::<_, AvailableSlot>(
-        "SELECT id, date, start_time, end_time, is_booked, booking_id
-         FROM available_slots WHERE date = ?
-         ORDER BY start_time ASC",
-    )
-    .bind(&query.date)
-    .fetch_all(&state.db)
-    .await
-    .map_err(|e| {
-        tracing::error!("list_slots: {}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
-    })?;
+    let slots = day_slots(&state.db, &query.date)
+        .await
+        .map_err(|e| {
+            tracing::error!("list_slots: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+        })?;
 
     Ok(Json(ApiResponse::success(slots)))
 }
@@ -195,24 +220,19 @@ pub async fn create_slots(
                 (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
             })?;
     }
+    state.waitlist.wake();
 
-    let slots = sqlx::query_as::<_, AvailableSlot>(
-        "SELECT id, date, start_time, end_time, is_booked, booking_id
-         FROM available_slots WHERE date = ?
-         ORDER BY start_time ASC",
-    )
-    .bind(&body.date)
-    .fetch_all(&state.db)
-    .await
-    .map_err(|e| {
-        tracing::error!("create_slots fetch: {}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
-    })?;
+    let slots = day_slots(&state.db, &body.date)
+        .await
+        .map_err(|e| {
+            tracing::error!("create_slots fetch: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+        })?;
 
     Ok(Json(ApiResponse::success(slots)))
 }
 
-/// POST /api/admin/openday — create 1-hour slots for a working day.
+/// POST /api/admin/openday — create base-granularity slots for a working day.
 pub async fn open_day(
     State(state): State<Arc<AppState>>,
     headers: axum::http::HeaderMap,
@@ -238,49 +258,20 @@ pub async fn open_day(
         ));
     }
 
-    for hour in start_h..end_h {
-        let start = format!("{:02}:00", hour);
-        let end = format!("{:02}:00", hour + 1);
-
-        // Idempotent: skip if already exists
-        let exists: bool = sqlx::query_scalar(
-            "SELECT COUNT(*) > 0 FROM available_slots WHERE date = ? AND start_time = ?",
-        )
-        .bind(&body.date)
-        .bind(&start)
-        .fetch_one(&state.db)
+    slots::open_day_slots(&state.db, &body.date, start_h, end_h, state.slot_granularity_min)
         .await
         .map_err(|e| {
-            tracing::error!("open_day exists check: {}", e);
+            tracing::error!("open_day: {}", e);
             (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
         })?;
+    state.waitlist.wake();
 
-        if !exists {
-            sqlx::query("INSERT INTO available_slots (date, start_time, end_time) VALUES (?, ?, ?)")
-                .bind(&body.date)
-                .bind(&start)
-                .bind(&end)
-                .execute(&state.db)
-                .await
-                .map_err(|e| {
-                    tracing::error!("open_day insert: {}", e);
-                    (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
-                })?;
-        }
-    }
-
-    let slots = sqlx::query_as::<_, AvailableSlot>(
-        "SELECT id, date, start_time, end_time, is_booked, booking_id
-         FROM available_slots WHERE date = ?
-         ORDER BY start_time ASC",
-    )
-    .bind(&body.date)
-    .fetch_all(&state.db)
-    .await
-    .map_err(|e| {
-        tracing::error!("open_day fetch: {}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
-    })?;
+    let slots = day_slots(&state.db, &body.date)
+        .await
+        .map_err(|e| {
+            tracing::error!("open_day fetch: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+        })?;
 
     Ok(Json(ApiResponse::success(slots)))
 }
@@ -334,21 +325,13 @@ pub async fn list_bookings(
     let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
     extract_admin(auth_header, &state)?;
 
-    let base = super::client::booking_detail_select();
+    let base = repo::BOOKING_DETAIL_SELECT;
 
     let bookings = if let Some(date) = &query.date {
-        let sql = format!(
-            "{} WHERE COALESCE(b.date, sl.date) = ? AND b.status IN ('confirmed', 'pending_payment')
-             ORDER BY COALESCE(b.start_time, sl.start_time) ASC",
-            base
-        );
-        sqlx::query_as::<_, BookingDetail>(&sql)
-            .bind(date)
-            .fetch_all(&state.db)
-            .await
+        repo::day_bookings(&state.db, date).await
     } else if let (Some(from), Some(to)) = (&query.from, &query.to) {
         let sql = format!(
-            "{} WHERE COALESCE(b.date, sl.date) BETWEEN ? AND ? AND b.status IN ('confirmed', 'pending_payment')
+            "{} WHERE COALESCE(b.date, sl.date) BETWEEN ? AND ? AND b.status IN ('confirmed', 'pending_payment', 'awaiting_approval', 'completed', 'no_show')
              ORDER BY COALESCE(b.date, sl.date) ASC, COALESCE(b.start_time, sl.start_time) ASC",
             base
         );
@@ -359,7 +342,7 @@ pub async fn list_bookings(
             .await
     } else {
         let sql = format!(
-            "{} WHERE COALESCE(b.date, sl.date) >= date('now', '+3 hours') AND b.status IN ('confirmed', 'pending_payment')
+            "{} WHERE COALESCE(b.date, sl.date) >= date('now', '+3 hours') AND b.status IN ('confirmed', 'pending_payment', 'awaiting_approval', 'completed', 'no_show')
              ORDER BY COALESCE(b.date, sl.date) ASC, COALESCE(b.start_time, sl.start_time) ASC",
             base
         );
@@ -375,42 +358,41 @@ pub async fn list_bookings(
     Ok(Json(ApiResponse::success(bookings)))
 }
 
-/// POST /api/admin/bookings/:id/cancel — admin cancels a booking (always refund if paid).
+/// POST /api/admin/bookings/:id/cancel?refund_amount= — admin cancels a booking.
+///
+/// Refunds the full prepayment unless `refund_amount` overrides it.
 pub async fn cancel_booking(
     State(state): State<Arc<AppState>>,
     headers: axum::http::HeaderMap,
     Path(id): Path<i64>,
+    Query(query): Query<AdminCancelQuery>,
 ) -> Result<Json<ApiResponse<&'static str>>, (StatusCode, Json<ApiResponse<()>>)> {
     let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
     extract_admin(auth_header, &state)?;
 
-    let booking = sqlx::query_as::<_, Booking>(
-        "SELECT * FROM bookings WHERE id = ? AND status IN ('confirmed', 'pending_payment')",
+    let booking = repo::active_booking(&state.db, id, None)
+        .await
+        .map_err(|e| {
+            tracing::error!("admin cancel_booking fetch: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+        })?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(ApiResponse::error("Запись не найдена"))))?;
+
+    // Admin cancellation → full refund if paid, unless the admin chose an amount
+    let refund_amount = query.refund_amount.unwrap_or(booking.prepaid_amount);
+    let refund_info = refunds::process_refund_if_needed(
+        &state.db,
+        state.payments.as_ref(),
+        &booking,
+        Some(refund_amount),
     )
-    .bind(id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|e| {
-        tracing::error!("admin cancel_booking fetch: {}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
-    })?
-    .ok_or_else(|| (StatusCode::NOT_FOUND, Json(ApiResponse::error("Запись не найдена"))))?;
+    .await;
 
-    // Admin cancellation → always refund if paid
-    let refund_info = super::client::process_refund_if_needed(&state, &booking, true).await;
-
-    if let Err(e) = sqlx::query(
-        "UPDATE bookings SET status = 'cancelled', cancelled_at = datetime('now', '+3 hours') WHERE id = ?",
-    )
-    .bind(id)
-    .execute(&state.db)
-    .await
-    {
+    if let Err(e) = repo::cancel_booking(&state.db, &booking, repo::CancelledBy::Admin).await {
         tracing::error!("admin cancel_booking update: {}", e);
         return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error"))));
     }
-
-    super::client::free_booking_slots(&state.db, id, booking.slot_id).await;
+    state.waitlist.wake();
 
     // Notify client
     let b_date = booking.date.as_deref().unwrap_or("?");
@@ -418,8 +400,11 @@ pub async fn cancel_booking(
 
     let refund_text = refund_info
         .as_ref()
-        .map(|r| format!("\n\n💰 {}", r))
+        .map(|r| format!("\n\n💰 {}", r.text))
         .unwrap_or_default();
+    if let Some(manual) = refund_info.as_ref().and_then(|r| r.manual.as_ref()) {
+        super::client::notify_admin(&state.bot_token, state.admin_tg_id, &manual.notice()).await;
+    }
 
     let message = format!(
         "😔 Твоя запись на {} в {} была отменена мастером.{}",
@@ -442,3 +427,413 @@ pub async fn cancel_booking(
 
     Ok(Json(ApiResponse::success("Запись отменена")))
 }
+
+/// POST /api/admin/bookings — book a phone or walk-in client.
+///
+/// Any time is accepted: free slots in the way are taken and the rest of the range
+/// gets ad hoc slots. Nothing is charged online; the booking is confirmed right away.
+pub async fn create_booking(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Json(mut body): Json<AdminCreateBookingRequest>,
+) -> Result<Json<ApiResponse<BookingDetail>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    extract_admin(auth_header, &state)?;
+
+    let bad_request = |msg: &str| (StatusCode::BAD_REQUEST, Json(ApiResponse::error(msg)));
+
+    body.client_name = body.client_name.trim().to_string();
+    if body.client_name.is_empty() || body.client_name.chars().count() > 100 {
+        return Err(bad_request("Укажите имя клиента"));
+    }
+    body.phone = match body.phone.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+        Some(phone) => Some(
+            receipts::normalize_phone(phone).ok_or_else(|| bad_request("Неверный номер телефона"))?,
+        ),
+        None => None,
+    };
+    if chrono::NaiveDate::parse_from_str(&body.date, "%Y-%m-%d").is_err() {
+        return Err(bad_request("Неверный формат даты"));
+    }
+    let start_min = match time_to_minutes(&body.start_time) {
+        Some(m) if body.start_time.len() == 5 && m < 24 * 60 => m,
+        _ => return Err(bad_request("Неверный формат времени")),
+    };
+
+    let service = sqlx::query_as::<_, Service>(
+        "SELECT id, name, description, price, duration_min, is_active, sort_order, service_type,
+         buffer_before_min, buffer_after_min, prepayment_required
+         FROM services WHERE id = ? AND is_active = 1",
+    )
+    .bind(body.service_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("admin create_booking service: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?
+    .ok_or_else(|| (StatusCode::NOT_FOUND, Json(ApiResponse::error("Услуга не найдена"))))?;
+
+    let end_min = match body.end_time.as_deref() {
+        Some(end) => match time_to_minutes(end) {
+            Some(m) if end.len() == 5 => m,
+            _ => return Err(bad_request("Неверный формат времени")),
+        },
+        None => start_min + service.duration_min.max(0) as u32,
+    };
+    if end_min <= start_min || end_min > 24 * 60 {
+        return Err(bad_request("Запись должна закончиться в тот же день"));
+    }
+
+    let addon_price = if body.with_lower_lashes {
+        sqlx::query_scalar::<_, i64>(
+            "SELECT price FROM services WHERE service_type = 'addon' AND is_active = 1 LIMIT 1",
+        )
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error"))))?
+        .unwrap_or(500)
+    } else {
+        0
+    };
+
+    // Buffers around the appointment are held like for client bookings
+    let hold = (
+        start_min.saturating_sub(service.buffer_before_min.max(0) as u32),
+        (end_min + service.buffer_after_min.max(0) as u32).min(24 * 60),
+    );
+    let created_at = moscow_now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let booking_id = reserve_admin_booking(
+        &state.db,
+        &body,
+        (start_min, end_min),
+        hold,
+        service.price + addon_price,
+        &created_at,
+    )
+    .await?;
+
+    let detail = repo::booking_detail(&state.db, booking_id)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error"))))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(ApiResponse::error("Запись не найдена"))))?;
+
+    if let (Some(tg_id), Some(phone)) = (body.client_tg_id, &body.phone) {
+        super::clients::remember_phone(&state.db, tg_id, phone).await;
+    }
+    if let Some(tg_id) = body.client_tg_id {
+        super::client::send_message(
+            &state.bot_token,
+            tg_id,
+            &format!(
+                "💅 Мастер записал тебя на <b>{}</b>\n📅 {} в {}\n\nЖдём тебя! ✨",
+                detail.service_name, detail.date, detail.start_time
+            ),
+            None,
+        )
+        .await;
+    }
+
+    Ok(Json(ApiResponse::success(detail)))
+}
+
+/// Insert an admin booking and lock `hold` (minutes) on its date in one transaction.
+///
+/// Fails with 409 if a booked slot overlaps `hold`.
+async fn reserve_admin_booking(
+    db: &sqlx::SqlitePool,
+    body: &AdminCreateBookingRequest,
+    (start_min, end_min): (u32, u32),
+    hold: (u32, u32),
+    amount_due: i64,
+    created_at: &str,
+) -> Result<i64, (StatusCode, Json<ApiResponse<()>>)> {
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("admin create_booking: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    };
+
+    let mut tx = db.begin_with("BEGIN IMMEDIATE").await.map_err(db_error)?;
+
+    let overlapping = sqlx::query_as::<_, AvailableSlot>(
+        "SELECT id, date, start_time, end_time, is_booked, booking_id
+         FROM available_slots WHERE date = ? AND start_time < ? AND end_time > ?
+         ORDER BY start_time ASC",
+    )
+    .bind(&body.date)
+    .bind(minutes_to_time(hold.1))
+    .bind(minutes_to_time(hold.0))
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(db_error)?;
+
+    if overlapping.iter().any(|s| s.is_booked) {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error("Это время пересекается с другой записью")),
+        ));
+    }
+
+    // (start, slot id) of every slot the booking will hold
+    let mut held: Vec<(u32, i64)> = overlapping
+        .iter()
+        .map(|s| (time_to_minutes(&s.start_time).unwrap_or(0), s.id))
+        .collect();
+    for (from, to) in uncovered_ranges(&overlapping, hold) {
+        let id = sqlx::query(
+            "INSERT INTO available_slots (date, start_time, end_time, is_booked, ad_hoc)
+             VALUES (?, ?, ?, 1, 1)",
+        )
+        .bind(&body.date)
+        .bind(minutes_to_time(from))
+        .bind(minutes_to_time(to))
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?
+        .last_insert_rowid();
+        held.push((from, id));
+    }
+    held.sort_unstable();
+
+    let booking_id = sqlx::query(
+        "INSERT INTO bookings (service_id, slot_id, client_tg_id, client_first_name,
+         status, date, start_time, end_time, with_lower_lashes,
+         payment_status, payment_method, prepaid_amount, created_at, client_phone, amount_due)
+         VALUES (?, ?, ?, ?, 'confirmed', ?, ?, ?, ?, 'none', ?, 0, ?, ?, ?)",
+    )
+    .bind(body.service_id)
+    .bind(held[0].1)
+    .bind(body.client_tg_id.unwrap_or(NO_TELEGRAM))
+    .bind(&body.client_name)
+    .bind(&body.date)
+    .bind(minutes_to_time(start_min))
+    .bind(minutes_to_time(end_min))
+    .bind(body.with_lower_lashes)
+    .bind(body.payment.as_str())
+    .bind(created_at)
+    .bind(&body.phone)
+    .bind(amount_due)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?
+    .last_insert_rowid();
+
+    for (_, slot_id) in &held {
+        sqlx::query("UPDATE available_slots SET is_booked = 1, booking_id = ? WHERE id = ?")
+            .bind(booking_id)
+            .bind(slot_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+    }
+
+    tx.commit().await.map_err(db_error)?;
+    Ok(booking_id)
+}
+
+/// Parts of `range` (minutes) not covered by any of `slots`, in order.
+fn uncovered_ranges(slots: &[AvailableSlot], (from, to): (u32, u32)) -> Vec<(u32, u32)> {
+    let mut covered: Vec<(u32, u32)> = slots
+        .iter()
+        .filter_map(|s| Some((time_to_minutes(&s.start_time)?, time_to_minutes(&s.end_time)?)))
+        .collect();
+    covered.sort_unstable();
+
+    let mut gaps = Vec::new();
+    let mut cursor = from;
+    for (start, end) in covered {
+        if start > cursor {
+            gaps.push((cursor, start.min(to)));
+        }
+        cursor = cursor.max(end);
+        if cursor >= to {
+            return gaps;
+        }
+    }
+    if cursor < to {
+        gaps.push((cursor, to));
+    }
+    gaps
+}
+
+// ── Shared helpers (pub for main.rs) ──
+
+/// Split free slots longer than `granularity_min` into base-length pieces.
+///
+/// Migration path for existing 1-hour slots after `SLOT_GRANULARITY_MIN` is lowered.
+/// Booked slots are left intact (they keep working as one longer slot), and so are
+/// free ad hoc ones; slots whose length is not a multiple of the granularity are skipped.
+pub async fn split_free_slots(db: &sqlx::SqlitePool, granularity_min: u32) -> anyhow::Result<u32> {
+    let free = sqlx::query_as::<_, AvailableSlot>(
+        "SELECT id, date, start_time, end_time, is_booked, booking_id
+         FROM available_slots WHERE is_booked = 0 AND ad_hoc = 0
+         AND date >= date('now', '+3 hours')",
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut tx = db.begin().await?;
+    let mut split = 0;
+
+    for slot in &free {
+        let bounds = (
+            time_to_minutes(&slot.start_time),
+            time_to_minutes(&slot.end_time),
+        );
+        let (Some(start), Some(end)) = bounds else {
+            continue;
+        };
+        let length = end.saturating_sub(start);
+        if length <= granularity_min || length % granularity_min != 0 {
+            continue;
+        }
+
+        sqlx::query("DELETE FROM available_slots WHERE id = ? AND is_booked = 0")
+            .bind(slot.id)
+            .execute(&mut *tx)
+            .await?;
+        for m in (start..end).step_by(granularity_min as usize) {
+            sqlx::query("INSERT INTO available_slots (date, start_time, end_time) VALUES (?, ?, ?)")
+                .bind(&slot.date)
+                .bind(minutes_to_time(m))
+                .bind(minutes_to_time(m + granularity_min))
+                .execute(&mut *tx)
+                .await?;
+        }
+        split += 1;
+    }
+
+    tx.commit().await?;
+    Ok(split)
+}
+
+// ── Tests ──
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bimbo_lashes_core::testing;
+
+    fn slot(start: &str, end: &str) -> AvailableSlot {
+        AvailableSlot {
+            id: 0,
+            date: "2030-01-01".into(),
+            start_time: start.into(),
+            end_time: end.into(),
+            is_booked: false,
+            booking_id: None,
+        }
+    }
+
+    #[test]
+    fn test_uncovered_ranges() {
+        let slots = [slot("10:00", "11:00"), slot("12:00", "13:00")];
+        assert_eq!(uncovered_ranges(&slots, (570, 810)), vec![(570, 600), (660, 720), (780, 810)]);
+        assert!(uncovered_ranges(&slots, (610, 650)).is_empty());
+        assert_eq!(uncovered_ranges(&[], (1320, 1440)), vec![(1320, 1440)]);
+    }
+
+    #[tokio::test]
+    async fn test_admin_booking_outside_open_slots() {
+        let db = testing::memory_db().await;
+        testing::open_slots(&db, "2030-01-01", &[("10:00", "11:00"), ("11:00", "12:00")]).await;
+        let service_id: i64 = sqlx::query_scalar("SELECT MIN(id) FROM services")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        let body = AdminCreateBookingRequest {
+            service_id,
+            date: "2030-01-01".into(),
+            start_time: "11:30".into(),
+            end_time: Some("13:00".into()),
+            with_lower_lashes: false,
+            client_name: "Вера".into(),
+            client_tg_id: None,
+            phone: Some("79001234567".into()),
+            payment: OnSitePayment::Cash,
+        };
+        let created_at = "2030-01-01 09:00:00";
+
+        // 11:00 slot taken, 12:00–13:00 added ad hoc
+        let id = reserve_admin_booking(&db, &body, (690, 780), (690, 780), 2500, created_at)
+            .await
+            .unwrap();
+        let held: Vec<(String, bool)> = sqlx::query_as(
+            "SELECT start_time, ad_hoc FROM available_slots WHERE booking_id = ? ORDER BY start_time",
+        )
+        .bind(id)
+        .fetch_all(&db)
+        .await
+        .unwrap();
+        assert_eq!(held, vec![("11:00".into(), false), ("12:00".into(), true)]);
+
+        let detail = repo::booking_detail(&db, id).await.unwrap().unwrap();
+        assert_eq!(detail.client_tg_id, NO_TELEGRAM);
+        assert_eq!(detail.status, "confirmed");
+        assert_eq!(detail.amount_to_collect(), 2500);
+        assert_eq!(repo::day_bookings(&db, "2030-01-01").await.unwrap().len(), 1);
+
+        let err = reserve_admin_booking(&db, &body, (750, 800), (750, 800), 2500, created_at)
+            .await
+            .unwrap_err();
+        assert_eq!(err.0, StatusCode::CONFLICT);
+
+        // Cancelling reopens the open-hours slot and drops the ad hoc one
+        let booking = repo::active_booking(&db, id, None).await.unwrap().unwrap();
+        repo::cancel_booking(&db, &booking, repo::CancelledBy::Admin).await.unwrap();
+        let left: Vec<String> =
+            sqlx::query_scalar("SELECT start_time FROM available_slots ORDER BY start_time")
+                .fetch_all(&db)
+                .await
+                .unwrap();
+        assert_eq!(left, vec!["10:00", "11:00"]);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_booking_outside_open_hours_leaves_no_open_slot() {
+        let db = testing::memory_db().await;
+        testing::open_slots(&db, "2030-01-01", &[("10:00", "11:00")]).await;
+        let body = AdminCreateBookingRequest {
+            service_id: sqlx::query_scalar("SELECT MIN(id) FROM services")
+                .fetch_one(&db)
+                .await
+                .unwrap(),
+            date: "2030-01-01".into(),
+            start_time: "18:00".into(),
+            end_time: Some("20:00".into()),
+            with_lower_lashes: false,
+            client_name: "Вера".into(),
+            client_tg_id: None,
+            phone: None,
+            payment: OnSitePayment::Cash,
+        };
+
+        // Every held slot is ad hoc, the first one stays the booking's slot_id
+        let id = reserve_admin_booking(&db, &body, (1080, 1200), (1080, 1200), 2500, "x")
+            .await
+            .unwrap();
+        let booking = repo::active_booking(&db, id, None).await.unwrap().unwrap();
+        repo::cancel_booking(&db, &booking, repo::CancelledBy::Admin).await.unwrap();
+
+        let listed: Vec<String> = day_slots(&db, "2030-01-01")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|s| s.start_time)
+            .collect();
+        assert_eq!(listed, vec!["10:00"]);
+        assert_eq!(split_free_slots(&db, 30).await.unwrap(), 1);
+        let regular: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM available_slots WHERE ad_hoc = 0 AND start_time >= '18:00'",
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        assert_eq!(regular, 0);
+
+        // The same time can be booked again
+        reserve_admin_booking(&db, &body, (1080, 1200), (1080, 1200), 2500, "x")
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,139 @@
+use bimbo_lashes_core::{approval::ApprovalMode, models::Booking, refunds, repo};
+
+use crate::AppState;
+
+/// Which new bookings wait for the master, and for how long (env BOOKING_APPROVAL /
+/// APPROVAL_TIMEOUT_HOURS). Approve/Decline answers are handled by the bot.
+#[derive(Debug, Clone, Copy)]
+pub struct ApprovalPolicy {
+    pub mode: ApprovalMode,
+    /// Unanswered requests expire this many hours after the booking was made.
+    pub timeout_hours: i64,
+}
+
+/// Whether the client has had a booking confirmed before, including visits already closed.
+pub async fn is_returning_client(db: &sqlx::SqlitePool, tg_id: i64) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT COUNT(*) > 0 FROM bookings
+         WHERE client_tg_id = ? AND status IN ('confirmed', 'completed')",
+    )
+    .bind(tg_id)
+    .fetch_one(db)
+    .await
+}
+
+/// Expire bookings the master didn't answer in time: refund the prepayment in
+/// full, free the slots and tell both sides. Called periodically from `main.rs`.
+pub async fn expire_unapproved_bookings(state: &AppState) {
+    let overdue = match sqlx::query_as::<_, Booking>(&format!(
+        "SELECT * FROM bookings
+         WHERE status = 'awaiting_approval'
+         AND datetime(created_at, '+{} hours') < datetime('now', '+3 hours')",
+        state.approval.timeout_hours
+    ))
+    .fetch_all(&state.db)
+    .await
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            tracing::error!("expire_unapproved_bookings query failed: {}", e);
+            return;
+        }
+    };
+
+    for booking in overdue {
+        match expire_booking(&state.db, &booking).await {
+            Ok(true) => {}
+            // Answered in the meantime
+            Ok(false) => continue,
+            Err(e) => {
+                tracing::error!(booking_id = booking.id, error = %e, "Failed to expire unapproved booking");
+                continue;
+            }
+        }
+        tracing::info!(booking_id = booking.id, "Approval request expired");
+        state.waitlist.wake();
+
+        // Not the client's fault: the whole prepayment goes back
+        let refund_info = refunds::process_refund_if_needed(
+            &state.db,
+            state.payments.as_ref(),
+            &booking,
+            Some(booking.prepaid_amount),
+        )
+        .await;
+        let refund_text = refund_info
+            .as_ref()
+            .map(|r| format!("\n\n💰 {}", r.text))
+            .unwrap_or_default();
+        let admin_refund_text = refund_info
+            .as_ref()
+            .map(|r| format!("\n\n💰 {}", r.admin_text()))
+            .unwrap_or_default();
+
+        let date = booking.date.as_deref().unwrap_or("?");
+        let start = booking.start_time.as_deref().unwrap_or("?");
+        super::client::send_message(
+            &state.bot_token,
+            booking.client_tg_id,
+            &format!(
+                "😔 Мастер не успел подтвердить запись на {} в {}.{}\n\nВыбери другое время 💕",
+                date, start, refund_text
+            ),
+            None,
+        )
+        .await;
+        super::client::notify_admin(
+            &state.bot_token,
+            state.admin_tg_id,
+            &format!(
+                "⌛ Заявка #{} ({}, {} в {}) истекла без ответа{}",
+                booking.id, booking.client_first_name, date, start, admin_refund_text
+            ),
+        )
+        .await;
+    }
+}
+
+/// Mark an unapproved booking expired and free its slots.
+///
+/// Returns `false` when it no longer awaits approval.
+async fn expire_booking(db: &sqlx::SqlitePool, booking: &Booking) -> Result<bool, sqlx::Error> {
+    let mut tx = db.begin().await?;
+    let expired = sqlx::query(
+        "UPDATE bookings SET status = 'expired', cancelled_at = datetime('now', '+3 hours')
+         WHERE id = ? AND status = 'awaiting_approval'",
+    )
+    .bind(booking.id)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected()
+        > 0;
+    if !expired {
+        return Ok(false);
+    }
+    repo::free_booking_slots(&mut tx, booking.id, booking.slot_id).await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bimbo_lashes_core::testing;
+
+    #[tokio::test]
+    async fn test_completed_visit_makes_a_returning_client() {
+        let db = testing::memory_db().await;
+        testing::paid_booking(&db, "2000-01-01", "10:00", "11:00", 0).await;
+        sqlx::query("UPDATE bookings SET status = 'completed'")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        assert!(is_returning_client(&db, 42).await.unwrap());
+        assert!(!ApprovalMode::NewClients.applies(is_returning_client(&db, 42).await.unwrap()));
+        // Someone with no visits still waits for the master
+        assert!(ApprovalMode::NewClients.applies(is_returning_client(&db, 7).await.unwrap()));
+    }
+}
@@ -0,0 +1,173 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    Json,
+};
+use bimbo_lashes_core::{receipts, repo};
+use std::sync::Arc;
+
+use crate::{models::*, AppState};
+
+/// GET /api/admin/blocklist — everyone the master refuses to book, latest first.
+pub async fn list_blocked(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+) -> Result<Json<ApiResponse<Vec<BlockedClient>>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    let blocked = sqlx::query_as::<_, BlockedClient>(
+        "SELECT id, tg_id, username, phone, reason, created_at
+         FROM blocked_clients ORDER BY created_at DESC, id DESC",
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("list_blocked: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+
+    Ok(Json(ApiResponse::success(blocked)))
+}
+
+/// POST /api/admin/blocklist — block a Telegram id, username and/or phone.
+///
+/// Upcoming bookings stay; they are returned and sent to the master in the bot
+/// with cancel buttons (the usual admin cancellation, refund included).
+pub async fn block_client(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Json(body): Json<BlockClientRequest>,
+) -> Result<Json<ApiResponse<BlockClientResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    let bad_request = |msg: &str| (StatusCode::BAD_REQUEST, Json(ApiResponse::error(msg)));
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("block_client: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    };
+
+    let username = body
+        .username
+        .as_deref()
+        .map(|u| u.trim().trim_start_matches('@'))
+        .filter(|u| !u.is_empty());
+    let phone = match body.phone.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+        Some(phone) => Some(
+            receipts::normalize_phone(phone).ok_or_else(|| bad_request("Неверный номер телефона"))?,
+        ),
+        None => None,
+    };
+    if body.tg_id.is_none() && username.is_none() && phone.is_none() {
+        return Err(bad_request("Укажите Telegram ID, username или телефон"));
+    }
+    if body.tg_id == Some(state.admin_tg_id) {
+        return Err(bad_request("Нельзя заблокировать мастера"));
+    }
+    let reason = body.reason.as_deref().unwrap_or("").trim();
+
+    // Blocking the same Telegram id again updates the entry
+    let id: i64 = sqlx::query_scalar(
+        "INSERT INTO blocked_clients (tg_id, username, phone, reason) VALUES (?, ?, ?, ?)
+         ON CONFLICT(tg_id) DO UPDATE SET username = excluded.username,
+         phone = excluded.phone, reason = excluded.reason
+         RETURNING id",
+    )
+    .bind(body.tg_id)
+    .bind(username)
+    .bind(&phone)
+    .bind(reason)
+    .fetch_one(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    let entry = sqlx::query_as::<_, BlockedClient>(
+        "SELECT id, tg_id, username, phone, reason, created_at FROM blocked_clients WHERE id = ?",
+    )
+    .bind(id)
+    .fetch_one(&state.db)
+    .await
+    .map_err(db_error)?;
+    let upcoming =
+        repo::upcoming_matching_bookings(&state.db, body.tg_id, username, phone.as_deref())
+            .await
+            .map_err(db_error)?;
+
+    if !upcoming.is_empty() {
+        offer_cancellation(&state, &upcoming).await;
+    }
+
+    Ok(Json(ApiResponse::success(BlockClientResponse { entry, upcoming })))
+}
+
+/// DELETE /api/admin/blocklist/:id — let the client book again.
+pub async fn unblock_client(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<Json<ApiResponse<String>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    let removed = sqlx::query("DELETE FROM blocked_clients WHERE id = ?")
+        .bind(id)
+        .execute(&state.db)
+        .await
+        .map_err(|e| {
+            tracing::error!("unblock_client: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+        })?
+        .rows_affected();
+
+    if removed == 0 {
+        return Err((StatusCode::NOT_FOUND, Json(ApiResponse::error("Запись не найдена"))));
+    }
+
+    Ok(Json(ApiResponse::success("Клиент разблокирован".into())))
+}
+
+/// Whether the user may not book at all. The booking form's phone is checked too.
+pub async fn is_blocked(
+    db: &sqlx::SqlitePool,
+    user: &TelegramUser,
+    phone: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let entry = repo::blocked_client(db, user.id, user.username.as_deref(), phone).await?;
+    if let Some(entry) = &entry {
+        tracing::info!(tg_id = user.id, entry = entry.id, "Blocked client refused");
+    }
+    Ok(entry.is_some())
+}
+
+/// Ask the master in the bot whether to cancel a blocked client's upcoming bookings.
+async fn offer_cancellation(state: &AppState, upcoming: &[BookingDetail]) {
+    let mut text = String::from("⛔ Клиент заблокирован. Остались записи:\n");
+    for b in upcoming {
+        text.push_str(&format!(
+            "\n• {} {} · {} · {}",
+            b.date,
+            &b.start_time[..5],
+            b.client_mention(),
+            b.service_name
+        ));
+    }
+    text.push_str("\n\nОтменить? Предоплата вернётся клиенту.");
+
+    let rows: Vec<serde_json::Value> = upcoming
+        .iter()
+        .map(|b| {
+            serde_json::json!([{
+                "text": format!("❌ {} {}", b.date, &b.start_time[..5]),
+                "callback_data": format!("admin_cancel:{}", b.id)
+            }])
+        })
+        .collect();
+    super::client::send_message(
+        &state.bot_token,
+        state.admin_tg_id,
+        &text,
+        Some(serde_json::json!({ "inline_keyboard": rows })),
+    )
+    .await;
+}
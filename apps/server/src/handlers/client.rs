@@ -3,7 +3,15 @@ use axum::{
     http::{header, StatusCode},
     Json,
 };
-use chrono::{Datelike, FixedOffset, Utc};
+use bimbo_lashes_core::{
+    models::NO_TELEGRAM,
+    payments::{CreatedPayment, PaymentRequest},
+    receipts, refunds, repo,
+    rules::MAX_TIP,
+    telegram_payments,
+    time::{hours_until, minutes_to_time, moscow_now, moscow_today, time_to_minutes},
+};
+use chrono::Datelike;
 use std::collections::HashMap;
 use std::sync::Arc;
 
@@ -11,27 +19,11 @@ use crate::{auth, models::*, AppState};
 
 // ── Constants ──
 
-/// Moscow timezone offset (UTC+3).
-const MSK_OFFSET_SECS: i32 = 3 * 3600;
-
-/// Prepayment amount in RUB.
-const PREPAID_AMOUNT: i64 = 500;
-
 /// Days threshold for "tight" booking mode (adjacent slots only).
 const TIGHT_MODE_DAYS: i64 = 3;
 
-/// Moscow timezone (UTC+3).
-fn moscow_now() -> chrono::DateTime<FixedOffset> {
-    let msk = FixedOffset::east_opt(MSK_OFFSET_SECS).unwrap();
-    Utc::now().with_timezone(&msk)
-}
-
-fn moscow_today() -> String {
-    moscow_now().format("%Y-%m-%d").to_string()
-}
-
 /// Helper: extract TelegramUser from Authorization header.
-fn extract_user(
+pub(super) fn extract_user(
     auth_header: Option<&str>,
     bot_token: &str,
 ) -> Result<TelegramUser, (StatusCode, Json<ApiResponse<()>>)> {
@@ -49,40 +41,95 @@ fn extract_user(
     })
 }
 
-/// Calculate how many 1-hour slots a service needs.
-fn slots_needed_for_duration(duration_min: i64) -> usize {
-    (duration_min as f64 / 60.0).ceil() as usize
+/// Calculate how many base slots a service needs.
+fn slots_needed_for_duration(duration_min: i64, granularity_min: u32) -> usize {
+    (duration_min as f64 / granularity_min as f64).ceil() as usize
 }
 
-// ── Shared booking query (eliminates duplication across client/admin) ──
-
-/// The shared SELECT columns for booking detail queries.
-const BOOKING_DETAIL_SELECT: &str =
-    "SELECT b.id, s.name as service_name, s.price as service_price,
-            COALESCE(b.date, sl.date) as date,
-            COALESCE(b.start_time, sl.start_time) as start_time,
-            COALESCE(b.end_time, sl.end_time) as end_time,
-            b.client_tg_id, b.client_username, b.client_first_name,
-            b.status, b.created_at,
-            CASE WHEN b.with_lower_lashes = 1 THEN 1 ELSE 0 END as with_lower_lashes,
-            CASE WHEN b.with_lower_lashes = 1
-                 THEN s.price + COALESCE((SELECT price FROM services WHERE service_type = 'addon' AND is_active = 1 LIMIT 1), 500)
-                 ELSE s.price
-            END as total_price,
-            b.payment_status,
-            b.prepaid_amount
-     FROM bookings b
-     JOIN services s ON s.id = b.service_id
-     LEFT JOIN available_slots sl ON sl.id = b.slot_id";
+/// Minutes of schedule a service occupies, rounded up to whole base slots.
+fn block_minutes(duration_min: i64, granularity_min: u32) -> u32 {
+    slots_needed_for_duration(duration_min, granularity_min) as u32 * granularity_min
+}
+
+/// How a booking occupies the schedule: appointment plus buffers, in whole base slots.
+///
+/// The appointment starts `lead_min` after the block start, so the visible start
+/// time stays slot-aligned and buffers are locked with the booking but never shown.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BlockShape {
+    /// Slots reserved before the appointment (`buffer_before_min`, rounded up).
+    lead_min: u32,
+    /// Total minutes of slots locked, buffers included.
+    total_min: u32,
+    /// Appointment length shown to the client.
+    duration_min: u32,
+}
+
+impl BlockShape {
+    fn new(duration_min: i64, before_min: i64, after_min: i64, granularity_min: u32) -> Self {
+        let duration_min = duration_min.max(0);
+        let lead_min = if before_min > 0 {
+            block_minutes(before_min, granularity_min)
+        } else {
+            0
+        };
+        Self {
+            lead_min,
+            total_min: lead_min + block_minutes(duration_min + after_min.max(0), granularity_min),
+            duration_min: duration_min as u32,
+        }
+    }
+
+    fn for_service(service: &Service, granularity_min: u32) -> Self {
+        Self::new(
+            service.duration_min,
+            service.buffer_before_min,
+            service.buffer_after_min,
+            granularity_min,
+        )
+    }
+}
+
+/// Limits on client-initiated rescheduling (env RESCHEDULE_MAX / RESCHEDULE_CUTOFF_HOURS).
+#[derive(Debug, Clone, Copy)]
+pub struct ReschedulePolicy {
+    /// How many times one booking may be moved.
+    pub max_reschedules: i64,
+    /// Moving is closed this many hours before the appointment.
+    pub cutoff_hours: i64,
+}
+
+impl ReschedulePolicy {
+    /// Check whether a booking moved `reschedule_count` times, `hours_until` hours
+    /// before the appointment, may be moved again. `Err` carries the user-facing reason.
+    fn check(&self, reschedule_count: i64, hours_until: i64) -> Result<(), String> {
+        if reschedule_count >= self.max_reschedules {
+            return Err(format!(
+                "Запись можно перенести не более {} раз",
+                self.max_reschedules
+            ));
+        }
+        if hours_until < self.cutoff_hours {
+            return Err(format!(
+                "Перенос возможен не позднее чем за {}ч до записи",
+                self.cutoff_hours
+            ));
+        }
+        Ok(())
+    }
+}
 
 // ── Endpoints ──
 
 /// GET /api/services — list active main services (hides addons).
+///
+/// `prepayment_required` already accounts for the deployment-wide switch.
 pub async fn list_services(
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<ApiResponse<Vec<Service>>>, StatusCode> {
-    let services = sqlx::query_as::<_, Service>(
-        "SELECT id, name, description, price, duration_min, is_active, sort_order, service_type
+    let mut services = sqlx::query_as::<_, Service>(
+        "SELECT id, name, description, price, duration_min, is_active, sort_order, service_type,
+         buffer_before_min, buffer_after_min, prepayment_required
          FROM services WHERE is_active = 1 AND service_type = 'main' ORDER BY sort_order ASC",
     )
     .fetch_all(&state.db)
@@ -91,6 +138,9 @@ pub async fn list_services(
         tracing::error!("list_services: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
+    for service in &mut services {
+        service.prepayment_required &= state.prepayment.required;
+    }
 
     Ok(Json(ApiResponse::success(services)))
 }
@@ -123,9 +173,10 @@ pub async fn available_dates_for_service(
     State(state): State<Arc<AppState>>,
     Query(query): Query<AvailableDatesQuery>,
 ) -> Result<Json<ApiResponse<Vec<String>>>, StatusCode> {
-    let slots_needed = if let Some(service_id) = query.service_id {
+    let block_min = if let Some(service_id) = query.service_id {
         let service = sqlx::query_as::<_, Service>(
-            "SELECT id, name, description, price, duration_min, is_active, sort_order, service_type
+            "SELECT id, name, description, price, duration_min, is_active, sort_order, service_type,
+             buffer_before_min, buffer_after_min, prepayment_required
              FROM services WHERE id = ? AND is_active = 1",
         )
         .bind(service_id)
@@ -134,17 +185,17 @@ pub async fn available_dates_for_service(
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
         match service {
-            Some(s) => slots_needed_for_duration(s.duration_min) as i64,
+            Some(s) => BlockShape::for_service(&s, state.slot_granularity_min).total_min,
             None => return Ok(Json(ApiResponse::success(vec![]))),
         }
     } else {
-        1
+        state.slot_granularity_min
     };
 
     // Get all dates with free slots in the future
     let dates: Vec<String> = sqlx::query_scalar(
         "SELECT DISTINCT date FROM available_slots
-         WHERE is_booked = 0 AND date >= date('now', '+3 hours')
+         WHERE is_booked = 0 AND ad_hoc = 0 AND date >= date('now', '+3 hours')
          ORDER BY date ASC",
     )
     .fetch_all(&state.db)
@@ -154,16 +205,11 @@ pub async fn available_dates_for_service(
     // Filter: only dates with enough consecutive free slots
     let mut valid_dates = Vec::new();
     for date in &dates {
-        let slots = sqlx::query_as::<_, AvailableSlot>(
-            "SELECT id, date, start_time, end_time, is_booked, booking_id
-             FROM available_slots WHERE date = ? ORDER BY start_time ASC",
-        )
-        .bind(date)
-        .fetch_all(&state.db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let slots = day_slots(&state.db, date, NO_TELEGRAM)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-        if has_consecutive_free_slots(&slots, slots_needed) {
+        if has_consecutive_free_slots(&slots, block_min) {
             valid_dates.push(date.clone());
         }
     }
@@ -172,18 +218,36 @@ pub async fn available_dates_for_service(
 }
 
 /// GET /api/available-times?date=YYYY-MM-DD&service_id=N — smart slot availability.
+///
+/// Anonymous requests are answered; a blocked user gets 403.
 pub async fn available_times(
     State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
     Query(query): Query<AvailableTimesQuery>,
-) -> Result<Json<ApiResponse<AvailableTimesResponse>>, StatusCode> {
+) -> Result<Json<ApiResponse<AvailableTimesResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let db_error = |_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")));
+    let user = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|h| auth::extract_user_from_header(h, &state.bot_token));
+    if let Some(user) = &user {
+        if super::blocklist::is_blocked(&state.db, user, None)
+            .await
+            .map_err(db_error)?
+        {
+            return Err((StatusCode::FORBIDDEN, Json(ApiResponse::error(BLOCKED_NOTICE))));
+        }
+    }
+
     let service = sqlx::query_as::<_, Service>(
-        "SELECT id, name, description, price, duration_min, is_active, sort_order, service_type
+        "SELECT id, name, description, price, duration_min, is_active, sort_order, service_type,
+         buffer_before_min, buffer_after_min, prepayment_required
          FROM services WHERE id = ? AND is_active = 1",
     )
     .bind(query.service_id)
     .fetch_optional(&state.db)
     .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .map_err(db_error)?;
 
     let service = match service {
         Some(s) => s,
@@ -195,59 +259,113 @@ pub async fn available_times(
         }
     };
 
-    let slots_needed = slots_needed_for_duration(service.duration_min);
-
-    let slots = sqlx::query_as::<_, AvailableSlot>(
-        "SELECT id, date, start_time, end_time, is_booked, booking_id
-         FROM available_slots WHERE date = ? ORDER BY start_time ASC",
-    )
-    .bind(&query.date)
-    .fetch_all(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    let today = moscow_today();
-    let days_until = days_between(&today, &query.date);
-    let is_tight = days_until <= TIGHT_MODE_DAYS;
-
-    let time_blocks = find_bookable_blocks(&slots, slots_needed, is_tight);
+    let client_tg_id = user.map_or(NO_TELEGRAM, |u| u.id);
+    let time_blocks =
+        bookable_times(&state.db, state.slot_granularity_min, &service, &query.date, client_tg_id)
+            .await
+            .map_err(db_error)?;
 
     Ok(Json(ApiResponse::success(AvailableTimesResponse {
-        mode: if is_tight { "tight".into() } else { "free".into() },
+        mode: if is_tight(&query.date) { "tight".into() } else { "free".into() },
         times: time_blocks,
     })))
 }
 
-/// POST /api/bookings — create a new booking with YooKassa prepayment.
+/// Appointment times `client_tg_id` may book for `service` on `date`, as `available_times` lists them.
+pub(super) async fn bookable_times(
+    db: &sqlx::SqlitePool,
+    granularity_min: u32,
+    service: &Service,
+    date: &str,
+    client_tg_id: i64,
+) -> Result<Vec<TimeBlock>, sqlx::Error> {
+    let shape = BlockShape::for_service(service, granularity_min);
+    let slots = day_slots(db, date, client_tg_id).await?;
+    Ok(find_bookable_blocks(&slots, shape, is_tight(date)))
+}
+
+/// The slots a booking of `service` at `start_time` would lock among `day_slots`.
+///
+/// 404 when the block runs past the open slots, 409 when part of it is taken.
+pub(super) fn block_slots(
+    day_slots: &[AvailableSlot],
+    service: &Service,
+    granularity_min: u32,
+    start_time: &str,
+) -> Result<Vec<AvailableSlot>, (StatusCode, Json<ApiResponse<()>>)> {
+    let start_min = match time_to_minutes(start_time) {
+        Some(m) if start_time.len() == 5 => m,
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::error("Неверный формат времени")),
+            ))
+        }
+    };
+    resolve_block(day_slots, start_min, BlockShape::for_service(service, granularity_min))
+}
+
+/// The slots a booking of `service` at `start_time` locks, buffers included, as `(start, end)`.
+pub(super) fn locked_span(
+    service: &Service,
+    granularity_min: u32,
+    start_time: &str,
+) -> Option<(String, String)> {
+    let shape = BlockShape::for_service(service, granularity_min);
+    let start = time_to_minutes(start_time)?.checked_sub(shape.lead_min)?;
+    Some((minutes_to_time(start), minutes_to_time(start + shape.total_min)))
+}
+
+/// POST /api/bookings — create a new booking with prepayment, or confirm it
+/// right away when no prepayment is required.
 pub async fn create_booking(
     State(state): State<Arc<AppState>>,
     headers: axum::http::HeaderMap,
-    Json(body): Json<CreateBookingRequest>,
+    Json(mut body): Json<CreateBookingRequest>,
 ) -> Result<Json<ApiResponse<CreateBookingResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
     let auth_header = headers
         .get(header::AUTHORIZATION)
         .and_then(|v| v.to_str().ok());
     let user = extract_user(auth_header, &state.bot_token)?;
 
-    // Validate date format
-    if chrono::NaiveDate::parse_from_str(&body.date, "%Y-%m-%d").is_err() {
+    normalize_contact(&mut body)
+        .map_err(|msg| (StatusCode::BAD_REQUEST, Json(ApiResponse::error(msg))))?;
+    if super::blocklist::is_blocked(&state.db, &user, body.phone.as_deref())
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error"))))?
+    {
+        return Err((StatusCode::FORBIDDEN, Json(ApiResponse::error(BLOCKED_NOTICE))));
+    }
+    if !(0..=MAX_TIP).contains(&body.tip) {
         return Err((
             StatusCode::BAD_REQUEST,
-            Json(ApiResponse::error("Неверный формат даты")),
+            Json(ApiResponse::error("Неверная сумма чаевых")),
         ));
     }
 
-    // Validate time format
-    if body.start_time.len() != 5 || !body.start_time.contains(':') {
+    // Validate date format
+    if chrono::NaiveDate::parse_from_str(&body.date, "%Y-%m-%d").is_err() {
         return Err((
             StatusCode::BAD_REQUEST,
-            Json(ApiResponse::error("Неверный формат времени")),
+            Json(ApiResponse::error("Неверный формат даты")),
         ));
     }
 
+    // Validate time format
+    let start_min = match time_to_minutes(&body.start_time) {
+        Some(m) if body.start_time.len() == 5 => m,
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::error("Неверный формат времени")),
+            ))
+        }
+    };
+
     // Get service
     let service = sqlx::query_as::<_, Service>(
-        "SELECT id, name, description, price, duration_min, is_active, sort_order, service_type
+        "SELECT id, name, description, price, duration_min, is_active, sort_order, service_type,
+         buffer_before_min, buffer_after_min, prepayment_required
          FROM services WHERE id = ? AND is_active = 1",
     )
     .bind(body.service_id)
@@ -259,38 +377,6 @@ pub async fn create_booking(
     // Calculate end_time
     let end_time = add_minutes_to_time(&body.start_time, service.duration_min as u32);
 
-    // Find all slots between start_time and end_time on this date
-    let slots = sqlx::query_as::<_, AvailableSlot>(
-        "SELECT id, date, start_time, end_time, is_booked, booking_id
-         FROM available_slots
-         WHERE date = ? AND start_time >= ? AND end_time <= ?
-         ORDER BY start_time ASC",
-    )
-    .bind(&body.date)
-    .bind(&body.start_time)
-    .bind(&end_time)
-    .fetch_all(&state.db)
-    .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error"))))?;
-
-    let slots_needed = slots_needed_for_duration(service.duration_min);
-    if slots.len() < slots_needed {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ApiResponse::error("Недостаточно слотов для записи")),
-        ));
-    }
-
-    // Verify all are free
-    for slot in &slots {
-        if slot.is_booked {
-            return Err((
-                StatusCode::CONFLICT,
-                Json(ApiResponse::error("Одно из выбранных времён уже занято")),
-            ));
-        }
-    }
-
     // Calculate price
     let addon_price = if body.with_lower_lashes {
         sqlx::query_scalar::<_, i64>(
@@ -305,92 +391,120 @@ pub async fn create_booking(
     };
     let total_price = service.price + addon_price;
 
-    // Create booking as pending_payment
-    let first_slot_id = slots[0].id;
-    let created_at = moscow_now().format("%Y-%m-%d %H:%M:%S").to_string();
-    let booking_id = sqlx::query(
-        "INSERT INTO bookings (service_id, slot_id, client_tg_id, client_username, client_first_name,
-         status, date, start_time, end_time, with_lower_lashes,
-         payment_status, prepaid_amount, created_at)
-         VALUES (?, ?, ?, ?, ?, 'pending_payment', ?, ?, ?, ?, 'pending', ?, ?)",
-    )
-    .bind(body.service_id)
-    .bind(first_slot_id)
-    .bind(user.id)
-    .bind(&user.username)
-    .bind(&user.first_name)
-    .bind(&body.date)
-    .bind(&body.start_time)
-    .bind(&end_time)
-    .bind(body.with_lower_lashes)
-    .bind(PREPAID_AMOUNT)
-    .bind(&created_at)
-    .execute(&state.db)
-    .await
-    .map_err(|e| {
-        tracing::error!("create_booking INSERT failed: {}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
-    })?
-    .last_insert_rowid();
-
-    // Lock slots immediately (prevent double booking)
-    for slot in &slots {
-        if let Err(e) = sqlx::query(
-            "UPDATE available_slots SET is_booked = 1, booking_id = ? WHERE id = ? AND is_booked = 0",
-        )
-        .bind(booking_id)
-        .bind(slot.id)
-        .execute(&state.db)
+    let db_error = |_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")));
+    let restriction = super::standing::client_restriction(&state, user.id)
+        .await
+        .map_err(db_error)?;
+    if restriction == Restriction::Blocked {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ApiResponse::error(super::standing::BLOCKED_ONLINE)),
+        ));
+    }
+    // After no-shows the whole price is paid up front, whatever the other settings
+    let full_prepayment = restriction == Restriction::FullPrepayment;
+    let trusted = super::trusted::is_trusted(&state.db, user.id)
         .await
+        .map_err(db_error)?;
+    let prepayment =
+        full_prepayment || (state.prepayment.required && service.prepayment_required && !trusted);
+    let payment_plan = if full_prepayment {
+        PaymentPlan::Full
+    } else {
+        body.payment_plan
+    };
+    // Trusted clients are already vetted
+    let needs_approval = !trusted
+        && state.approval.mode.applies(
+            super::approval::is_returning_client(&state.db, user.id)
+                .await
+                .map_err(db_error)?,
+        );
+    // Without prepayment nothing is charged online, tip included
+    let charge = if prepayment {
+        Charge {
+            amount_due: total_price,
+            prepaid: payment_plan.amount(total_price) + body.tip,
+            tip: body.tip,
+        }
+    } else {
+        Charge {
+            amount_due: total_price,
+            prepaid: 0,
+            tip: 0,
+        }
+    };
+
+    let payment_method = if charge.is_online() {
+        let method = state
+            .prepayment
+            .resolve(body.payment_method)
+            .map_err(|msg| (StatusCode::BAD_REQUEST, Json(ApiResponse::error(msg))))?;
+        // Receipt contact (54-FZ)
+        if method == PaymentMethod::Yookassa
+            && state.payments.receipts_required()
+            && body.phone.is_none()
+            && body.email.is_none()
         {
-            tracing::error!("Failed to lock slot {}: {}", slot.id, e);
-            // Rollback booking
-            rollback_booking(&state.db, booking_id, &slots).await;
             return Err((
-                StatusCode::CONFLICT,
-                Json(ApiResponse::error("Не удалось забронировать слоты. Попробуйте снова.")),
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::error("Укажите телефон или email для чека")),
             ));
         }
+        Some(method)
+    } else {
+        None
+    };
+
+    // Create the booking and lock its slots atomically
+    let shape = BlockShape::for_service(&service, state.slot_granularity_min);
+    let created_at = moscow_now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let (booking_id, slots) = reserve_booking(
+        &state.db,
+        &body,
+        &user,
+        shape,
+        start_min,
+        charge,
+        needs_approval,
+        &created_at,
+    )
+    .await?;
+
+    if let Some(phone) = &body.phone {
+        super::clients::remember_phone(&state.db, user.id, phone).await;
     }
+    super::waitlist::booked(&state.db, user.id, body.service_id, &body.date).await;
 
-    // Create YooKassa payment
     let addon_text = if body.with_lower_lashes {
         format!("{} + нижние", service.name)
     } else {
         service.name.clone()
     };
-    let description = format!("Предоплата: {} на {}", addon_text, body.date);
-
-    let payment_result = super::payment::create_yookassa_payment(
-        &state.yookassa_shop_id,
-        &state.yookassa_secret_key,
-        booking_id,
-        PREPAID_AMOUNT,
-        &description,
-        &state.webapp_url,
-    )
-    .await;
+    let description = format!("{}: {} на {}", charge.label(), addon_text, body.date);
 
-    let payment_url = match payment_result {
-        Ok((payment_id, confirmation_url)) => {
-            // Save payment_id
-            if let Err(e) = sqlx::query("UPDATE bookings SET yookassa_payment_id = ? WHERE id = ?")
-                .bind(&payment_id)
-                .bind(booking_id)
-                .execute(&state.db)
-                .await
-            {
-                tracing::error!("Failed to save payment_id for booking {}: {}", booking_id, e);
+    let (payment_url, paid) = match payment_method {
+        None => {
+            super::payment::notify_new_booking(&state, booking_id).await;
+            (None, false)
+        }
+        Some(PaymentMethod::Yookassa) => {
+            let payment =
+                create_provider_payment(&state, booking_id, &description, &slots, charge.prepaid)
+                    .await?;
+            // The mock provider confirms on creation; no webhook will follow
+            let paid = payment.status == "succeeded";
+            if paid {
+                if let Err(e) = super::payment::mark_paid(&state, booking_id).await {
+                    tracing::error!("Failed to confirm booking {}: {}", booking_id, e);
+                }
             }
-            Some(confirmation_url)
+            (Some(payment.confirmation_url), paid)
         }
-        Err(e) => {
-            tracing::error!("YooKassa payment creation failed for booking {}: {}", booking_id, e);
-            rollback_booking(&state.db, booking_id, &slots).await;
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::error("Ошибка создания платежа. Попробуйте позже.")),
-            ));
+        Some(PaymentMethod::Telegram) => {
+            send_telegram_invoice(&state, booking_id, user.id, &description, &slots, charge)
+                .await?;
+            (None, false)
         }
     };
 
@@ -404,17 +518,33 @@ pub async fn create_booking(
         client_tg_id: user.id,
         client_username: user.username,
         client_first_name: user.first_name,
-        status: "pending_payment".into(),
+        status: match (paid || payment_method.is_none(), needs_approval) {
+            (false, _) => "pending_payment",
+            (true, true) => "awaiting_approval",
+            (true, false) => "confirmed",
+        }
+        .into(),
         created_at,
         with_lower_lashes: Some(body.with_lower_lashes),
         total_price: Some(total_price),
-        payment_status: Some("pending".into()),
-        prepaid_amount: Some(PREPAID_AMOUNT),
+        payment_status: Some(
+            match (paid, payment_method) {
+                (true, _) => "paid",
+                (false, None) => "none",
+                (false, Some(_)) => "pending",
+            }
+            .into(),
+        ),
+        prepaid_amount: Some(charge.prepaid),
+        tip_amount: Some(charge.tip),
+        client_phone: body.phone,
+        amount_collected: None,
     };
 
     Ok(Json(ApiResponse::success(CreateBookingResponse {
         booking: detail,
         payment_url,
+        payment_method,
     })))
 }
 
@@ -428,16 +558,7 @@ pub async fn my_bookings(
         .and_then(|v| v.to_str().ok());
     let user = extract_user(auth_header, &state.bot_token)?;
 
-    let query = format!(
-        "{} WHERE b.client_tg_id = ? AND b.status IN ('confirmed', 'pending_payment')
-         AND COALESCE(b.date, sl.date) >= date('now', '+3 hours')
-         ORDER BY COALESCE(b.date, sl.date) ASC, COALESCE(b.start_time, sl.start_time) ASC",
-        BOOKING_DETAIL_SELECT
-    );
-
-    let bookings = sqlx::query_as::<_, BookingDetail>(&query)
-        .bind(user.id)
-        .fetch_all(&state.db)
+    let bookings = repo::upcoming_client_bookings(&state.db, user.id)
         .await
         .map_err(|e| {
             tracing::error!("my_bookings: {}", e);
@@ -459,32 +580,25 @@ pub async fn cancel_booking(
     let user = extract_user(auth_header, &state.bot_token)?;
 
     // Verify booking belongs to this user
-    let booking = sqlx::query_as::<_, Booking>(
-        "SELECT * FROM bookings WHERE id = ? AND client_tg_id = ? AND status IN ('confirmed', 'pending_payment')",
-    )
-    .bind(id)
-    .bind(user.id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error"))))?
-    .ok_or_else(|| (StatusCode::NOT_FOUND, Json(ApiResponse::error("Запись не найдена"))))?;
-
-    let refund_info = process_refund_if_needed(&state, &booking, false).await;
+    let booking = repo::active_booking(&state.db, id, Some(user.id))
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error"))))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(ApiResponse::error("Запись не найдена"))))?;
 
-    // Cancel booking
-    if let Err(e) = sqlx::query(
-        "UPDATE bookings SET status = 'cancelled', cancelled_at = datetime('now', '+3 hours') WHERE id = ?",
+    let refund_info = refunds::process_refund_if_needed(
+        &state.db,
+        state.payments.as_ref(),
+        &booking,
+        None,
     )
-    .bind(id)
-    .execute(&state.db)
-    .await
-    {
+    .await;
+
+    // Cancel booking and free all its slots
+    if let Err(e) = repo::cancel_booking(&state.db, &booking, repo::CancelledBy::Client).await {
         tracing::error!("Failed to cancel booking {}: {}", id, e);
         return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error"))));
     }
-
-    // Free all slots belonging to this booking
-    free_booking_slots(&state.db, id, booking.slot_id).await;
+    state.waitlist.wake();
 
     // Notify admin
     let service_name = sqlx::query_scalar::<_, String>(
@@ -505,7 +619,8 @@ pub async fn cancel_booking(
 
     let b_date = booking.date.as_deref().unwrap_or("?");
     let b_start = booking.start_time.as_deref().unwrap_or("?");
-    let refund_text = refund_info.as_deref().unwrap_or("");
+    // The admin also gets what to refund by hand
+    let refund_text = refund_info.as_ref().map(|r| r.admin_text()).unwrap_or_default();
 
     let message = format!(
         "❌ Отмена записи\n\n\
@@ -526,10 +641,159 @@ pub async fn cancel_booking(
 
     Ok(Json(ApiResponse::success(CancelBookingResponse {
         message: "Запись отменена".into(),
-        refund_info,
+        refund_info: refund_info.map(|r| r.text),
     })))
 }
 
+/// POST /api/bookings/:id/reschedule — move a confirmed booking to another time.
+///
+/// The prepayment stays attached to the booking; old slots are freed and new ones
+/// locked in one transaction, so a failed move leaves the booking untouched.
+pub async fn reschedule_booking(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(id): Path<i64>,
+    Json(body): Json<RescheduleBookingRequest>,
+) -> Result<Json<ApiResponse<BookingDetail>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok());
+    let user = extract_user(auth_header, &state.bot_token)?;
+
+    if chrono::NaiveDate::parse_from_str(&body.date, "%Y-%m-%d").is_err() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error("Неверный формат даты")),
+        ));
+    }
+    let start_min = match time_to_minutes(&body.start_time) {
+        Some(m) if body.start_time.len() == 5 => m,
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::error("Неверный формат времени")),
+            ))
+        }
+    };
+    if hours_until(&body.date, &body.start_time).is_none_or(|h| h < 0) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error("Нельзя перенести запись в прошлое")),
+        ));
+    }
+
+    let booking = sqlx::query_as::<_, Booking>(
+        "SELECT * FROM bookings WHERE id = ? AND client_tg_id = ? AND status = 'confirmed'",
+    )
+    .bind(id)
+    .bind(user.id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error"))))?
+    .ok_or_else(|| (StatusCode::NOT_FOUND, Json(ApiResponse::error("Запись не найдена"))))?;
+
+    let old_date = booking.date.clone().unwrap_or_default();
+    let old_start = booking.start_time.clone().unwrap_or_default();
+    let hours_left = hours_until(&old_date, &old_start).unwrap_or(i64::MAX);
+    state
+        .reschedule_policy
+        .check(booking.reschedule_count, hours_left)
+        .map_err(|reason| (StatusCode::FORBIDDEN, Json(ApiResponse::error(reason))))?;
+
+    // Deactivated services keep their existing bookings movable
+    let service = sqlx::query_as::<_, Service>(
+        "SELECT id, name, description, price, duration_min, is_active, sort_order, service_type,
+         buffer_before_min, buffer_after_min, prepayment_required
+         FROM services WHERE id = ?",
+    )
+    .bind(booking.service_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error"))))?
+    .ok_or_else(|| (StatusCode::NOT_FOUND, Json(ApiResponse::error("Услуга не найдена"))))?;
+
+    let end_time = add_minutes_to_time(&body.start_time, service.duration_min as u32);
+    let shape = BlockShape::for_service(&service, state.slot_granularity_min);
+
+    let mut tx = state.db.begin_with("BEGIN IMMEDIATE").await.map_err(|e| {
+        tracing::error!("reschedule_booking: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+
+    // Free the old block first so the new one may overlap it
+    repo::free_booking_slots(&mut tx, id, booking.slot_id)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error"))))?;
+
+    let day_slots = day_slots(&mut *tx, &body.date, user.id)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error"))))?;
+
+    let slots = resolve_block(&day_slots, start_min, shape)?;
+
+    let locked = lock_slots(&mut tx, id, &slots).await.map_err(|e| {
+        tracing::error!("reschedule_booking lock failed for booking {}: {}", id, e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+    if !locked {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error("Одно из выбранных времён уже занято")),
+        ));
+    }
+    super::holds::release_booked(&mut tx, user.id, &slots)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error"))))?;
+
+    sqlx::query(
+        "UPDATE bookings SET date = ?, start_time = ?, end_time = ?, slot_id = ?,
+         reschedule_count = reschedule_count + 1, reminder_sent = 0
+         WHERE id = ?",
+    )
+    .bind(&body.date)
+    .bind(&body.start_time)
+    .bind(&end_time)
+    .bind(slots[0].id)
+    .bind(id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("reschedule_booking UPDATE failed: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+
+    tx.commit().await.map_err(|e| {
+        tracing::error!("reschedule_booking commit failed: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+    // The old time is free now
+    state.waitlist.wake();
+
+    let mention = user
+        .username
+        .as_ref()
+        .map(|u| format!("@{}", u))
+        .unwrap_or_else(|| user.first_name.clone());
+    let message = format!(
+        "🔄 Перенос записи\n\n\
+         👤 {}\n\
+         💅 {}\n\
+         📅 {} в {} → {} в {}",
+        mention, service.name, old_date, old_start, body.date, body.start_time
+    );
+    notify_admin(&state.bot_token, state.admin_tg_id, &message).await;
+
+    let detail = repo::booking_detail(&state.db, id)
+        .await
+        .map_err(|e| {
+            tracing::error!("reschedule_booking: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+        })?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(ApiResponse::error("Запись не найдена"))))?;
+
+    Ok(Json(ApiResponse::success(detail)))
+}
+
 /// GET /api/bookings/:id/status — poll booking payment status.
 pub async fn booking_status(
     State(state): State<Arc<AppState>>,
@@ -559,14 +823,18 @@ pub async fn booking_status(
 
 /// GET /api/calendar?year=2026&month=2&service_id=1 — calendar data with slot stats.
 ///
-/// Fetches ALL slots for the month in a single query (no N+1).
+/// Fetches ALL slots for the month in a single query (no N+1). Blocks held for
+/// other clients count as booked.
 pub async fn calendar(
     State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
     Query(query): Query<CalendarQuery>,
 ) -> Result<Json<ApiResponse<Vec<CalendarDay>>>, StatusCode> {
-    let slots_needed = if let Some(service_id) = query.service_id {
+    let granularity = state.slot_granularity_min;
+    let block_min = if let Some(service_id) = query.service_id {
         let service = sqlx::query_as::<_, Service>(
-            "SELECT id, name, description, price, duration_min, is_active, sort_order, service_type
+            "SELECT id, name, description, price, duration_min, is_active, sort_order, service_type,
+             buffer_before_min, buffer_after_min, prepayment_required
              FROM services WHERE id = ? AND is_active = 1",
         )
         .bind(service_id)
@@ -575,11 +843,11 @@ pub async fn calendar(
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
         match service {
-            Some(s) => slots_needed_for_duration(s.duration_min) as i64,
-            None => 1,
+            Some(s) => BlockShape::for_service(&s, granularity).total_min,
+            None => granularity,
         }
     } else {
-        1
+        granularity
     };
 
     let year = query.year;
@@ -600,12 +868,18 @@ pub async fn calendar(
     let month_start = format!("{:04}-{:02}-01", year, month);
     let month_end = format!("{:04}-{:02}-{:02}", year, month, days_in_month);
 
-    let all_slots = sqlx::query_as::<_, AvailableSlot>(
-        "SELECT id, date, start_time, end_time, is_booked, booking_id
-         FROM available_slots
-         WHERE date >= ? AND date <= ?
-         ORDER BY date ASC, start_time ASC",
-    )
+    let client_tg_id = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|h| auth::extract_user_from_header(h, &state.bot_token))
+        .map_or(NO_TELEGRAM, |u| u.id);
+    let all_slots = sqlx::query_as::<_, AvailableSlot>(&format!(
+        "SELECT {} FROM available_slots s
+         WHERE s.date >= ?2 AND s.date <= ?3 AND (s.ad_hoc = 0 OR s.is_booked = 1)
+         ORDER BY s.date ASC, s.start_time ASC",
+        CLIENT_SLOT_COLUMNS
+    ))
+    .bind(client_tg_id)
     .bind(&month_start)
     .bind(&month_end)
     .fetch_all(&state.db)
@@ -630,15 +904,22 @@ pub async fn calendar(
             continue;
         }
 
+        // Counted in base-slot units so legacy 1-hour slots weigh the same as new ones
         let slots = slots_by_date.get(&date);
-        let total = slots.map_or(0, |s| s.len() as i64);
-        let free = slots.map_or(0, |s| s.iter().filter(|sl| !sl.is_booked).count() as i64);
+        let total = slots.map_or(0, |s| s.iter().map(slot_minutes).sum::<u32>() / granularity) as i64;
+        let free = slots.map_or(0, |s| {
+            s.iter()
+                .filter(|sl| !sl.is_booked)
+                .map(slot_minutes)
+                .sum::<u32>()
+                / granularity
+        }) as i64;
 
         let bookable = if total == 0 {
             false
         } else if query.service_id.is_some() {
             slots
-                .is_some_and(|s| has_consecutive_free_slots(s, slots_needed))
+                .is_some_and(|s| has_consecutive_free_slots(s, block_min))
         } else {
             free > 0
         };
@@ -656,116 +937,145 @@ pub async fn calendar(
 
 // ── Shared helpers (pub for admin.rs) ──
 
-/// The shared booking detail SELECT string (used by admin.rs too).
-pub fn booking_detail_select() -> &'static str {
-    BOOKING_DETAIL_SELECT
-}
-
 /// Send a message to admin via Telegram Bot API.
 pub async fn notify_admin(bot_token: &str, chat_id: i64, text: &str) {
-    let url = format!("https://api.telegram.org/bot{}/sendMessage", bot_token);
-    let client = reqwest::Client::new();
-    if let Err(e) = client
-        .post(&url)
-        .json(&serde_json::json!({
-            "chat_id": chat_id,
-            "text": text,
-            "parse_mode": "HTML"
-        }))
-        .send()
-        .await
-    {
-        tracing::error!("Failed to notify admin: {}", e);
-    }
+    send_message(bot_token, chat_id, text, None).await;
 }
 
-/// Free all slots belonging to a booking.
-pub async fn free_booking_slots(db: &sqlx::SqlitePool, booking_id: i64, slot_id: i64) {
-    if let Err(e) = sqlx::query(
-        "UPDATE available_slots SET is_booked = 0, booking_id = NULL WHERE booking_id = ?",
-    )
-    .bind(booking_id)
-    .execute(db)
-    .await
-    {
-        tracing::error!("Failed to free slots for booking {}: {}", booking_id, e);
+/// Send an HTML message from the bot, optionally with an inline keyboard.
+pub async fn send_message(
+    bot_token: &str,
+    chat_id: i64,
+    text: &str,
+    reply_markup: Option<serde_json::Value>,
+) {
+    let url = format!("https://api.telegram.org/bot{}/sendMessage", bot_token);
+    let mut body = serde_json::json!({
+        "chat_id": chat_id,
+        "text": text,
+        "parse_mode": "HTML"
+    });
+    if let Some(markup) = reply_markup {
+        body["reply_markup"] = markup;
     }
-
-    // Also free by slot_id for backward compat
-    if let Err(e) = sqlx::query(
-        "UPDATE available_slots SET is_booked = 0, booking_id = NULL WHERE id = ?",
-    )
-    .bind(slot_id)
-    .execute(db)
-    .await
-    {
-        tracing::error!("Failed to free slot_id {} for booking: {}", slot_id, e);
+    if let Err(e) = reqwest::Client::new().post(&url).json(&body).send().await {
+        tracing::error!(chat_id, "Failed to send Telegram message: {}", e);
     }
 }
 
-/// Process refund logic for a booking cancellation.
-///
-/// - `admin_override`: if true, always refund (admin cancel). Otherwise, check 24h rule.
-pub async fn process_refund_if_needed(
-    state: &AppState,
-    booking: &Booking,
-    admin_override: bool,
-) -> Option<String> {
-    if booking.payment_status != "paid" {
-        return None;
-    }
+// ── Private helpers ──
 
-    let b_date = booking.date.as_deref().unwrap_or("2099-01-01");
-    let b_time = booking.start_time.as_deref().unwrap_or("00:00");
-    let appointment_str = format!("{} {}", b_date, b_time);
+/// Normalize the receipt contact in place; blank fields become `None`.
+fn normalize_contact(body: &mut CreateBookingRequest) -> Result<(), &'static str> {
+    body.phone = match body.phone.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+        Some(phone) => Some(receipts::normalize_phone(phone).ok_or("Неверный номер телефона")?),
+        None => None,
+    };
+    body.email = match body.email.as_deref().map(str::trim).filter(|e| !e.is_empty()) {
+        Some(email) if receipts::is_valid_email(email) => Some(email.to_lowercase()),
+        Some(_) => return Err("Неверный email"),
+        None => None,
+    };
+    Ok(())
+}
 
-    let hours_until = chrono::NaiveDateTime::parse_from_str(&appointment_str, "%Y-%m-%d %H:%M")
-        .map(|appointment| {
-            let now = moscow_now().naive_local();
-            (appointment - now).num_hours()
+/// Create the provider payment for a new booking; rolls the booking back on failure.
+async fn create_provider_payment(
+    state: &AppState,
+    booking_id: i64,
+    description: &str,
+    slots: &[AvailableSlot],
+    amount: i64,
+) -> Result<CreatedPayment, (StatusCode, Json<ApiResponse<()>>)> {
+    let receipt = match receipts::for_booking(&state.db, booking_id).await {
+        Ok(receipt) => receipt,
+        Err(e) => {
+            tracing::error!("Failed to load receipt data for booking {}: {}", booking_id, e);
+            None
+        }
+    };
+    let payment_result = state
+        .payments
+        .create_payment(&PaymentRequest {
+            booking_id,
+            amount,
+            description,
+            return_url: &state.webapp_url,
+            receipt,
         })
-        .unwrap_or(999); // Default to refundable on parse error
-
-    let should_refund = admin_override || hours_until > 24;
-
-    if should_refund {
-        if let Some(payment_id) = &booking.yookassa_payment_id {
-            let refund_result = super::payment::create_yookassa_refund(
-                &state.yookassa_shop_id,
-                &state.yookassa_secret_key,
-                payment_id,
-                booking.prepaid_amount,
-            )
-            .await;
+        .await;
 
-            if refund_result.is_ok() {
-                if let Err(e) = sqlx::query(
-                    "UPDATE bookings SET payment_status = 'refunded' WHERE id = ?",
-                )
-                .bind(booking.id)
+    match payment_result {
+        Ok(payment) => {
+            // Save payment_id
+            if let Err(e) = sqlx::query("UPDATE bookings SET yookassa_payment_id = ? WHERE id = ?")
+                .bind(&payment.id)
+                .bind(booking_id)
                 .execute(&state.db)
                 .await
-                {
-                    tracing::error!("Failed to update payment_status for booking {}: {}", booking.id, e);
-                }
-                Some(format!("Предоплата {} ₽ будет возвращена", booking.prepaid_amount))
-            } else {
-                tracing::error!("Refund failed for booking {}", booking.id);
-                Some("Возврат будет обработан вручную".into())
+            {
+                tracing::error!("Failed to save payment_id for booking {}: {}", booking_id, e);
             }
-        } else {
-            None
+            Ok(payment)
+        }
+        Err(e) => {
+            tracing::error!(
+                "{} payment creation failed for booking {}: {}",
+                state.payments.name(),
+                booking_id,
+                e
+            );
+            rollback_booking(&state.db, booking_id, slots).await;
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error("Ошибка создания платежа. Попробуйте позже.")),
+            ))
         }
-    } else {
-        // ≤24h → no refund
-        Some(format!(
-            "Предоплата {} ₽ не возвращается (отмена менее чем за 24ч)",
-            booking.prepaid_amount
-        ))
     }
 }
 
-// ── Private helpers ──
+/// Send a Telegram invoice for a new booking; the bot confirms it on `successful_payment`.
+async fn send_telegram_invoice(
+    state: &AppState,
+    booking_id: i64,
+    chat_id: i64,
+    description: &str,
+    slots: &[AvailableSlot],
+    charge: Charge,
+) -> Result<(), (StatusCode, Json<ApiResponse<()>>)> {
+    let provider_token = state.prepayment.telegram_token.as_deref().unwrap_or_default();
+    let mut prices = vec![(charge.label(), charge.prepaid - charge.tip)];
+    if charge.tip > 0 {
+        prices.push(("Чаевые", charge.tip));
+    }
+
+    let result = async {
+        sqlx::query("UPDATE bookings SET payment_method = 'telegram' WHERE id = ?")
+            .bind(booking_id)
+            .execute(&state.db)
+            .await?;
+        telegram_payments::send_invoice(
+            &state.bot_token,
+            provider_token,
+            chat_id,
+            booking_id,
+            description,
+            &prices,
+        )
+        .await
+    }
+    .await;
+
+    if let Err(e) = result {
+        tracing::error!("Telegram invoice failed for booking {}: {}", booking_id, e);
+        rollback_booking(&state.db, booking_id, slots).await;
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error("Ошибка создания платежа. Попробуйте позже.")),
+        ));
+    }
+    Ok(())
+}
 
 /// Rollback a failed booking: set to expired and free slots.
 async fn rollback_booking(db: &sqlx::SqlitePool, booking_id: i64, slots: &[AvailableSlot]) {
@@ -783,92 +1093,279 @@ async fn rollback_booking(db: &sqlx::SqlitePool, booking_id: i64, slots: &[Avail
     }
 }
 
-/// Check if there are N consecutive free slots in the list.
-fn has_consecutive_free_slots(slots: &[AvailableSlot], needed: i64) -> bool {
-    let needed = needed as usize;
-    for i in 0..slots.len() {
-        if slots[i].is_booked {
-            continue;
+/// What a new booking costs and what is charged online, in RUB.
+#[derive(Debug, Clone, Copy)]
+struct Charge {
+    /// Service + addon.
+    amount_due: i64,
+    /// Charged online, tip included.
+    prepaid: i64,
+    tip: i64,
+}
+
+impl Charge {
+    /// Whether anything is charged online; otherwise the booking is confirmed unpaid.
+    fn is_online(&self) -> bool {
+        self.prepaid > 0
+    }
+
+    /// Payment description: the whole price or a prepayment.
+    fn label(&self) -> &'static str {
+        if self.prepaid - self.tip >= self.amount_due {
+            "Оплата"
+        } else {
+            "Предоплата"
+        }
+    }
+}
+
+/// Insert a booking and lock its slots in one `BEGIN IMMEDIATE` transaction, so
+/// concurrent requests for the same block cannot both succeed.
+///
+/// The booking is `pending_payment` when `charge` is paid online, otherwise
+/// `confirmed` (or `awaiting_approval`) with `payment_status = 'none'`.
+#[allow(clippy::too_many_arguments)]
+async fn reserve_booking(
+    db: &sqlx::SqlitePool,
+    body: &CreateBookingRequest,
+    user: &TelegramUser,
+    shape: BlockShape,
+    start_min: u32,
+    charge: Charge,
+    needs_approval: bool,
+    created_at: &str,
+) -> Result<(i64, Vec<AvailableSlot>), (StatusCode, Json<ApiResponse<()>>)> {
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("create_booking: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    };
+
+    // IMMEDIATE takes the write lock up front: the slot check below sees the final state
+    let mut tx = db.begin_with("BEGIN IMMEDIATE").await.map_err(db_error)?;
+
+    // Resolve the block in memory: slot lengths may differ (legacy 1-hour slots)
+    let day_slots = day_slots(&mut *tx, &body.date, user.id)
+        .await
+        .map_err(db_error)?;
+
+    let slots = resolve_block(&day_slots, start_min, shape)?;
+
+    let (status, payment_status, payment_method) = match (charge.is_online(), needs_approval) {
+        (true, _) => ("pending_payment", "pending", "yookassa"),
+        (false, true) => ("awaiting_approval", "none", "none"),
+        (false, false) => ("confirmed", "none", "none"),
+    };
+
+    let booking_id = sqlx::query(
+        "INSERT INTO bookings (service_id, slot_id, client_tg_id, client_username, client_first_name,
+         status, date, start_time, end_time, with_lower_lashes,
+         payment_status, payment_method, prepaid_amount, created_at, client_phone, client_email,
+         amount_due, tip_amount, needs_approval)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(body.service_id)
+    .bind(slots[0].id)
+    .bind(user.id)
+    .bind(&user.username)
+    .bind(&user.first_name)
+    .bind(status)
+    .bind(&body.date)
+    .bind(&body.start_time)
+    .bind(add_minutes_to_time(&body.start_time, shape.duration_min))
+    .bind(body.with_lower_lashes)
+    .bind(payment_status)
+    .bind(payment_method)
+    .bind(charge.prepaid)
+    .bind(created_at)
+    .bind(&body.phone)
+    .bind(&body.email)
+    .bind(charge.amount_due)
+    .bind(charge.tip)
+    .bind(needs_approval)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?
+    .last_insert_rowid();
+
+    // Dropping the transaction on conflict rolls back the INSERT as well
+    if !lock_slots(&mut tx, booking_id, &slots).await.map_err(db_error)? {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error("Не удалось забронировать слоты. Попробуйте снова.")),
+        ));
+    }
+    // The client's hold on this time becomes the booking
+    super::holds::release_booked(&mut tx, user.id, &slots)
+        .await
+        .map_err(db_error)?;
+
+    tx.commit().await.map_err(db_error)?;
+    Ok((booking_id, slots))
+}
+
+/// Slot columns of `available_slots s` as client `?1` sees them: a block held
+/// for someone else or offered to them from the waitlist counts as booked.
+const CLIENT_SLOT_COLUMNS: &str = "s.id, s.date, s.start_time, s.end_time,
+    s.is_booked OR EXISTS (
+        SELECT 1 FROM slot_holds h
+        WHERE h.date = s.date AND h.client_tg_id != ?1
+        AND h.start_time < s.end_time AND h.end_time > s.start_time
+        AND h.expires_at > datetime('now', '+3 hours')
+    ) OR EXISTS (
+        SELECT 1 FROM waitlist w
+        WHERE w.status = 'offered' AND w.offered_date = s.date
+        AND w.client_tg_id != ?1
+        AND w.hold_start < s.end_time AND w.hold_end > s.start_time
+        AND w.hold_until > datetime('now', '+3 hours')
+    ) AS is_booked,
+    s.booking_id";
+
+/// A day's slots as `client_tg_id` sees them: free ad hoc slots are hidden and
+/// blocks held for or offered to someone else count as booked.
+pub(super) async fn day_slots<'e>(
+    executor: impl sqlx::SqliteExecutor<'e>,
+    date: &str,
+    client_tg_id: i64,
+) -> Result<Vec<AvailableSlot>, sqlx::Error> {
+    sqlx::query_as::<_, AvailableSlot>(&format!(
+        "SELECT {} FROM available_slots s
+         WHERE s.date = ?2 AND (s.ad_hoc = 0 OR s.is_booked = 1)
+         ORDER BY s.start_time ASC",
+        CLIENT_SLOT_COLUMNS
+    ))
+    .bind(client_tg_id)
+    .bind(date)
+    .fetch_all(executor)
+    .await
+}
+
+/// Lock `slots` for a booking inside a transaction.
+///
+/// Returns `false` if any slot was already taken; the caller must then drop the transaction.
+async fn lock_slots(
+    tx: &mut sqlx::SqliteConnection,
+    booking_id: i64,
+    slots: &[AvailableSlot],
+) -> Result<bool, sqlx::Error> {
+    for slot in slots {
+        let result = sqlx::query(
+            "UPDATE available_slots SET is_booked = 1, booking_id = ? WHERE id = ? AND is_booked = 0",
+        )
+        .bind(booking_id)
+        .bind(slot.id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() != 1 {
+            return Ok(false);
         }
-        if i + needed > slots.len() {
-            break;
+    }
+    Ok(true)
+}
+
+/// Last index of the contiguous free run starting at `i` that covers `block_min` minutes.
+fn block_end_index(slots: &[AvailableSlot], i: usize, block_min: u32) -> Option<usize> {
+    let mut covered = 0;
+    for j in i..slots.len() {
+        if slots[j].is_booked {
+            return None;
         }
-        let mut ok = true;
-        for j in 0..needed {
-            let idx = i + j;
-            if slots[idx].is_booked {
-                ok = false;
-                break;
-            }
-            if j > 0 && slots[i + j - 1].end_time != slots[idx].start_time {
-                ok = false;
-                break;
-            }
+        if j > i && slots[j - 1].end_time != slots[j].start_time {
+            return None;
         }
-        if ok {
-            return true;
+        covered += slot_minutes(&slots[j]);
+        if covered >= block_min {
+            return Some(j);
         }
     }
-    false
+    None
+}
+
+/// Check if there is a contiguous free run of at least `block_min` minutes.
+fn has_consecutive_free_slots(slots: &[AvailableSlot], block_min: u32) -> bool {
+    (0..slots.len()).any(|i| block_end_index(slots, i, block_min).is_some())
 }
 
 /// Find all bookable time blocks given a list of slots.
 ///
+/// Each block occupies `shape.total_min` minutes of slots; the returned time is the
+/// appointment itself, without buffers.
+///
 /// In tight mode (within 3 days), only shows blocks adjacent to existing bookings
 /// to minimize schedule fragmentation.
 fn find_bookable_blocks(
     slots: &[AvailableSlot],
-    slots_needed: usize,
+    shape: BlockShape,
     is_tight: bool,
 ) -> Vec<TimeBlock> {
     let mut blocks = Vec::new();
     let has_bookings = slots.iter().any(|s| s.is_booked);
 
     for i in 0..slots.len() {
-        if slots[i].is_booked || i + slots_needed > slots.len() {
+        let Some(last) = block_end_index(slots, i, shape.total_min) else {
             continue;
-        }
+        };
 
-        // Check N consecutive free slots
-        let mut valid = true;
-        for j in 0..slots_needed {
-            let idx = i + j;
-            if slots[idx].is_booked {
-                valid = false;
-                break;
-            }
-            if j > 0 && slots[i + j - 1].end_time != slots[idx].start_time {
-                valid = false;
-                break;
-            }
-        }
+        let block_start = &slots[i].start_time;
+        let block_end = &slots[last].end_time;
 
-        if !valid {
+        // Tight mode: only adjacent to booked slots
+        if is_tight && has_bookings && !is_adjacent_to_booked(block_start, block_end, slots) {
             continue;
         }
 
-        let block_start = &slots[i].start_time;
-        let block_end = &slots[i + slots_needed - 1].end_time;
-
-        if is_tight && has_bookings {
-            // Tight mode: only adjacent to booked slots
-            if is_adjacent_to_booked(block_start, block_end, slots) {
-                blocks.push(TimeBlock {
-                    start_time: block_start.clone(),
-                    end_time: block_end.clone(),
-                });
-            }
-        } else {
-            blocks.push(TimeBlock {
-                start_time: block_start.clone(),
-                end_time: block_end.clone(),
-            });
-        }
+        let start_min = time_to_minutes(block_start).unwrap_or(0) + shape.lead_min;
+        blocks.push(TimeBlock {
+            start_time: minutes_to_time(start_min),
+            end_time: minutes_to_time(start_min + shape.duration_min),
+        });
     }
 
     blocks
 }
 
+/// Pick the slots a booking at `start_min` would lock (buffers included).
+///
+/// Returns 404 when the block runs past the open slots and 409 when part of it is taken.
+fn resolve_block(
+    day_slots: &[AvailableSlot],
+    start_min: u32,
+    shape: BlockShape,
+) -> Result<Vec<AvailableSlot>, (StatusCode, Json<ApiResponse<()>>)> {
+    // The locked block starts `lead_min` before the appointment (buffer_before_min)
+    let block_start = start_min.checked_sub(shape.lead_min).map(minutes_to_time);
+    let first = day_slots
+        .iter()
+        .position(|s| Some(&s.start_time) == block_start.as_ref())
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("Недостаточно слотов для записи")),
+            )
+        })?;
+
+    match block_end_index(day_slots, first, shape.total_min) {
+        Some(last) => Ok(day_slots[first..=last].to_vec()),
+        None => {
+            // Tell a taken block apart from one that runs past the open slots
+            let block_end = start_min - shape.lead_min + shape.total_min;
+            let taken = day_slots[first..].iter().any(|s| {
+                s.is_booked && time_to_minutes(&s.start_time).is_some_and(|m| m < block_end)
+            });
+            Err(if taken {
+                (
+                    StatusCode::CONFLICT,
+                    Json(ApiResponse::error("Одно из выбранных времён уже занято")),
+                )
+            } else {
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(ApiResponse::error("Недостаточно слотов для записи")),
+                )
+            })
+        }
+    }
+}
+
 /// Check if a time block is adjacent to a booked slot.
 fn is_adjacent_to_booked(block_start: &str, block_end: &str, all_slots: &[AvailableSlot]) -> bool {
     all_slots.iter().any(|slot| {
@@ -876,6 +1373,11 @@ fn is_adjacent_to_booked(block_start: &str, block_end: &str, all_slots: &[Availa
     })
 }
 
+/// Whether `date` is close enough for tight mode.
+fn is_tight(date: &str) -> bool {
+    days_between(&moscow_today(), date) <= TIGHT_MODE_DAYS
+}
+
 /// Calculate days between two date strings (YYYY-MM-DD).
 fn days_between(from: &str, to: &str) -> i64 {
     let from_date = chrono::NaiveDate::parse_from_str(from, "%Y-%m-%d");
@@ -887,6 +1389,14 @@ fn days_between(from: &str, to: &str) -> i64 {
     }
 }
 
+/// Length of a slot in minutes (0 if its times are malformed).
+fn slot_minutes(slot: &AvailableSlot) -> u32 {
+    match (time_to_minutes(&slot.start_time), time_to_minutes(&slot.end_time)) {
+        (Some(start), Some(end)) => end.saturating_sub(start),
+        _ => 0,
+    }
+}
+
 /// Add minutes to a time string "HH:MM" → "HH:MM".
 fn add_minutes_to_time(time: &str, minutes: u32) -> String {
     let parts: Vec<&str> = time.split(':').collect();
@@ -904,6 +1414,7 @@ fn add_minutes_to_time(time: &str, minutes: u32) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use bimbo_lashes_core::testing;
 
     /// Helper: build an AvailableSlot without boilerplate.
     fn make_slot(id: i64, date: &str, start: &str, end: &str, booked: bool) -> AvailableSlot {
@@ -917,36 +1428,113 @@ mod tests {
         }
     }
 
+    /// Helper: block shape without buffers.
+    fn shape(total_min: u32, duration_min: u32) -> BlockShape {
+        BlockShape {
+            lead_min: 0,
+            total_min,
+            duration_min,
+        }
+    }
+
+    fn make_service(duration: i64, before: i64, after: i64) -> Service {
+        Service {
+            id: 1,
+            name: "Наращивание".into(),
+            description: String::new(),
+            price: 2500,
+            duration_min: duration,
+            is_active: true,
+            sort_order: 1,
+            service_type: "main".into(),
+            buffer_before_min: before,
+            buffer_after_min: after,
+            prepayment_required: true,
+        }
+    }
+
+    // ── normalize_contact ──
+
+    fn contact_request(phone: Option<&str>, email: Option<&str>) -> CreateBookingRequest {
+        CreateBookingRequest {
+            service_id: 1,
+            date: "2026-03-02".into(),
+            start_time: "10:00".into(),
+            with_lower_lashes: false,
+            payment_method: None,
+            phone: phone.map(Into::into),
+            email: email.map(Into::into),
+            payment_plan: PaymentPlan::Deposit,
+            tip: 0,
+        }
+    }
+
+    #[test]
+    fn test_normalize_contact() {
+        let mut body = contact_request(Some("8 (999) 123-45-67"), Some("  "));
+        normalize_contact(&mut body).unwrap();
+        assert_eq!(body.phone.as_deref(), Some("79991234567"));
+        assert_eq!(body.email, None);
+
+        let mut body = contact_request(None, Some("Anya@Mail.ru"));
+        normalize_contact(&mut body).unwrap();
+        assert_eq!(body.email.as_deref(), Some("anya@mail.ru"));
+
+        assert!(normalize_contact(&mut contact_request(Some("123"), None)).is_err());
+        assert!(normalize_contact(&mut contact_request(None, Some("anya"))).is_err());
+    }
+
+    // ── PaymentPlan / Charge ──
+
+    #[test]
+    fn test_payment_plan_amount() {
+        assert_eq!(PaymentPlan::Deposit.amount(2500), 500);
+        assert_eq!(PaymentPlan::Deposit.amount(300), 300);
+        assert_eq!(PaymentPlan::Full.amount(2500), 2500);
+    }
+
+    #[test]
+    fn test_charge_label() {
+        let charge = |prepaid, tip| Charge {
+            amount_due: 2500,
+            prepaid,
+            tip,
+        };
+        assert_eq!(charge(500, 0).label(), "Предоплата");
+        assert_eq!(charge(700, 200).label(), "Предоплата");
+        assert_eq!(charge(2800, 300).label(), "Оплата");
+    }
+
     // ── slots_needed_for_duration ──
 
     #[test]
     fn test_slots_needed_exact_hour() {
-        assert_eq!(slots_needed_for_duration(60), 1);
+        assert_eq!(slots_needed_for_duration(60, 60), 1);
     }
 
     #[test]
     fn test_slots_needed_two_hours() {
-        assert_eq!(slots_needed_for_duration(120), 2);
+        assert_eq!(slots_needed_for_duration(120, 60), 2);
     }
 
     #[test]
     fn test_slots_needed_round_up() {
-        assert_eq!(slots_needed_for_duration(90), 2);
+        assert_eq!(slots_needed_for_duration(90, 60), 2);
     }
 
     #[test]
     fn test_slots_needed_small() {
-        assert_eq!(slots_needed_for_duration(30), 1);
+        assert_eq!(slots_needed_for_duration(30, 60), 1);
     }
 
     #[test]
     fn test_slots_needed_three_hours() {
-        assert_eq!(slots_needed_for_duration(180), 3);
+        assert_eq!(slots_needed_for_duration(180, 60), 3);
     }
 
     #[test]
     fn test_slots_needed_just_over() {
-        assert_eq!(slots_needed_for_duration(61), 2);
+        assert_eq!(slots_needed_for_duration(61, 60), 2);
     }
 
     // ── days_between ──
@@ -1044,13 +1632,13 @@ mod tests {
     #[test]
     fn test_consecutive_single_free() {
         let slots = vec![make_slot(1, "2026-03-01", "10:00", "11:00", false)];
-        assert!(has_consecutive_free_slots(&slots, 1));
+        assert!(has_consecutive_free_slots(&slots, 60));
     }
 
     #[test]
     fn test_consecutive_empty() {
         let slots: Vec<AvailableSlot> = vec![];
-        assert!(!has_consecutive_free_slots(&slots, 1));
+        assert!(!has_consecutive_free_slots(&slots, 60));
     }
 
     #[test]
@@ -1059,7 +1647,7 @@ mod tests {
             make_slot(1, "2026-03-01", "10:00", "11:00", true),
             make_slot(2, "2026-03-01", "11:00", "12:00", true),
         ];
-        assert!(!has_consecutive_free_slots(&slots, 1));
+        assert!(!has_consecutive_free_slots(&slots, 60));
     }
 
     #[test]
@@ -1068,7 +1656,7 @@ mod tests {
             make_slot(1, "2026-03-01", "10:00", "11:00", false),
             make_slot(2, "2026-03-01", "11:00", "12:00", false),
         ];
-        assert!(has_consecutive_free_slots(&slots, 2));
+        assert!(has_consecutive_free_slots(&slots, 120));
     }
 
     #[test]
@@ -1077,7 +1665,7 @@ mod tests {
             make_slot(1, "2026-03-01", "10:00", "11:00", false),
             make_slot(2, "2026-03-01", "12:00", "13:00", false), // gap
         ];
-        assert!(!has_consecutive_free_slots(&slots, 2));
+        assert!(!has_consecutive_free_slots(&slots, 120));
     }
 
     #[test]
@@ -1087,13 +1675,13 @@ mod tests {
             make_slot(2, "2026-03-01", "11:00", "12:00", false),
             make_slot(3, "2026-03-01", "12:00", "13:00", false),
         ];
-        assert!(has_consecutive_free_slots(&slots, 2));
+        assert!(has_consecutive_free_slots(&slots, 120));
     }
 
     #[test]
     fn test_consecutive_not_enough() {
         let slots = vec![make_slot(1, "2026-03-01", "10:00", "11:00", false)];
-        assert!(!has_consecutive_free_slots(&slots, 2));
+        assert!(!has_consecutive_free_slots(&slots, 120));
     }
 
     #[test]
@@ -1103,7 +1691,7 @@ mod tests {
             make_slot(2, "2026-03-01", "11:00", "12:00", true),
             make_slot(3, "2026-03-01", "12:00", "13:00", false),
         ];
-        assert!(!has_consecutive_free_slots(&slots, 2));
+        assert!(!has_consecutive_free_slots(&slots, 120));
     }
 
     #[test]
@@ -1113,7 +1701,7 @@ mod tests {
             make_slot(2, "2026-03-01", "11:00", "12:00", false),
             make_slot(3, "2026-03-01", "12:00", "13:00", false),
         ];
-        assert!(has_consecutive_free_slots(&slots, 3));
+        assert!(has_consecutive_free_slots(&slots, 180));
     }
 
     // ── is_adjacent_to_booked ──
@@ -1157,7 +1745,7 @@ mod tests {
             make_slot(2, "2026-03-01", "11:00", "12:00", false),
             make_slot(3, "2026-03-01", "12:00", "13:00", false),
         ];
-        let blocks = find_bookable_blocks(&slots, 1, false);
+        let blocks = find_bookable_blocks(&slots, shape(60, 60), false);
         assert_eq!(blocks.len(), 3);
         assert_eq!(blocks[0].start_time, "10:00");
         assert_eq!(blocks[2].end_time, "13:00");
@@ -1170,7 +1758,7 @@ mod tests {
             make_slot(2, "2026-03-01", "11:00", "12:00", false),
             make_slot(3, "2026-03-01", "12:00", "13:00", false),
         ];
-        let blocks = find_bookable_blocks(&slots, 2, false);
+        let blocks = find_bookable_blocks(&slots, shape(120, 120), false);
         assert_eq!(blocks.len(), 2); // 10-12, 11-13
         assert_eq!(blocks[0].start_time, "10:00");
         assert_eq!(blocks[0].end_time, "12:00");
@@ -1185,7 +1773,7 @@ mod tests {
             make_slot(2, "2026-03-01", "11:00", "12:00", true),
             make_slot(3, "2026-03-01", "12:00", "13:00", false),
         ];
-        let blocks = find_bookable_blocks(&slots, 1, false);
+        let blocks = find_bookable_blocks(&slots, shape(60, 60), false);
         assert_eq!(blocks.len(), 2); // 10-11, 12-13
     }
 
@@ -1196,7 +1784,7 @@ mod tests {
             make_slot(1, "2026-03-01", "10:00", "11:00", false),
             make_slot(2, "2026-03-01", "11:00", "12:00", false),
         ];
-        let blocks = find_bookable_blocks(&slots, 1, true);
+        let blocks = find_bookable_blocks(&slots, shape(60, 60), true);
         assert_eq!(blocks.len(), 2); // All shown (has_bookings=false)
     }
 
@@ -1214,7 +1802,7 @@ mod tests {
         // 12-13: start_time 12:00 == booked.end_time 12:00 → adjacent ✓
         // 13-14: start 13:00 ≠ 12:00, end 14:00 ≠ 11:00 → NOT adjacent
         // 14-15: NOT adjacent
-        let blocks = find_bookable_blocks(&slots, 1, true);
+        let blocks = find_bookable_blocks(&slots, shape(60, 60), true);
         assert_eq!(blocks.len(), 2);
         assert_eq!(blocks[0].start_time, "10:00");
         assert_eq!(blocks[1].start_time, "12:00");
@@ -1223,7 +1811,7 @@ mod tests {
     #[test]
     fn test_bookable_empty() {
         let slots: Vec<AvailableSlot> = vec![];
-        let blocks = find_bookable_blocks(&slots, 1, false);
+        let blocks = find_bookable_blocks(&slots, shape(60, 60), false);
         assert_eq!(blocks.len(), 0);
     }
 
@@ -1240,11 +1828,398 @@ mod tests {
         // 2-slot blocks: 10-12 (end 12:00 == booked.start 12:00 → adj ✓),
         //                11-13 → slot 12-13 is booked → invalid
         //                13-15 (start 13:00 == booked.end 13:00 → adj ✓)
-        let blocks = find_bookable_blocks(&slots, 2, true);
+        let blocks = find_bookable_blocks(&slots, shape(120, 120), true);
         assert_eq!(blocks.len(), 2);
         assert_eq!(blocks[0].start_time, "10:00");
         assert_eq!(blocks[0].end_time, "12:00");
         assert_eq!(blocks[1].start_time, "13:00");
         assert_eq!(blocks[1].end_time, "15:00");
     }
+
+    // ── slot granularity ──
+
+    #[test]
+    fn test_slots_needed_half_hour_granularity() {
+        assert_eq!(slots_needed_for_duration(90, 30), 3);
+    }
+
+    #[test]
+    fn test_slots_needed_quarter_granularity() {
+        assert_eq!(slots_needed_for_duration(20, 15), 2);
+    }
+
+    #[test]
+    fn test_block_minutes_rounds_to_granularity() {
+        assert_eq!(block_minutes(20, 15), 30);
+        assert_eq!(block_minutes(20, 60), 60);
+        assert_eq!(block_minutes(90, 30), 90);
+    }
+
+    #[test]
+    fn test_block_end_index_mixed_lengths() {
+        // Legacy 1-hour slot followed by 30-minute slots
+        let slots = vec![
+            make_slot(1, "2026-03-01", "10:00", "11:00", false),
+            make_slot(2, "2026-03-01", "11:00", "11:30", false),
+            make_slot(3, "2026-03-01", "11:30", "12:00", false),
+        ];
+        assert_eq!(block_end_index(&slots, 0, 90), Some(1));
+        assert_eq!(block_end_index(&slots, 1, 60), Some(2));
+        assert_eq!(block_end_index(&slots, 1, 90), None);
+    }
+
+    #[test]
+    fn test_consecutive_half_hour_slots() {
+        let slots = vec![
+            make_slot(1, "2026-03-01", "10:00", "10:30", false),
+            make_slot(2, "2026-03-01", "10:30", "11:00", false),
+            make_slot(3, "2026-03-01", "11:00", "11:30", true),
+        ];
+        assert!(has_consecutive_free_slots(&slots, 60));
+        assert!(!has_consecutive_free_slots(&slots, 90));
+    }
+
+    #[test]
+    fn test_bookable_half_hour_granularity() {
+        // 90-minute correction on 30-minute slots fits exactly, no wasted hour
+        let slots = vec![
+            make_slot(1, "2026-03-01", "10:00", "10:30", false),
+            make_slot(2, "2026-03-01", "10:30", "11:00", false),
+            make_slot(3, "2026-03-01", "11:00", "11:30", false),
+            make_slot(4, "2026-03-01", "11:30", "12:00", false),
+        ];
+        let blocks = find_bookable_blocks(&slots, shape(90, 90), false);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].start_time, "10:00");
+        assert_eq!(blocks[0].end_time, "11:30");
+        assert_eq!(blocks[1].start_time, "10:30");
+        assert_eq!(blocks[1].end_time, "12:00");
+    }
+
+    #[test]
+    fn test_bookable_end_time_is_appointment_end() {
+        // 20-minute addon on 15-minute slots occupies 30 minutes but ends at +20
+        let slots = vec![
+            make_slot(1, "2026-03-01", "10:00", "10:15", false),
+            make_slot(2, "2026-03-01", "10:15", "10:30", false),
+        ];
+        let blocks = find_bookable_blocks(&slots, shape(30, 20), false);
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].end_time, "10:20");
+    }
+
+    // ── BlockShape (buffers) ──
+
+    #[test]
+    fn test_shape_without_buffers() {
+        let shape = BlockShape::for_service(&make_service(120, 0, 0), 60);
+        assert_eq!(shape, BlockShape { lead_min: 0, total_min: 120, duration_min: 120 });
+    }
+
+    #[test]
+    fn test_shape_buffer_after_rounds_with_duration() {
+        // 120 + 15 cleanup on 15-minute slots → 135, on hourly slots → 180
+        assert_eq!(BlockShape::new(120, 0, 15, 15).total_min, 135);
+        assert_eq!(BlockShape::new(120, 0, 15, 60).total_min, 180);
+    }
+
+    #[test]
+    fn test_shape_buffer_before_is_slot_aligned() {
+        let shape = BlockShape::new(60, 10, 0, 30);
+        assert_eq!(shape.lead_min, 30);
+        assert_eq!(shape.total_min, 90);
+    }
+
+    #[test]
+    fn test_shape_negative_buffers_ignored() {
+        assert_eq!(BlockShape::new(60, -15, -15, 30), shape(60, 60));
+    }
+
+    #[test]
+    fn test_bookable_buffer_after_not_back_to_back() {
+        // 60-min service + 15-min cleanup on 15-min slots: 10:00 booking blocks until 11:15
+        let mut slots = Vec::new();
+        for (i, m) in (600..720).step_by(15).enumerate() {
+            let booked = m < 675; // 10:00–11:15 taken by a previous booking + its buffer
+            slots.push(make_slot(
+                i as i64,
+                "2026-03-01",
+                &minutes_to_time(m),
+                &minutes_to_time(m + 15),
+                booked,
+            ));
+        }
+        let blocks = find_bookable_blocks(&slots, BlockShape::new(30, 0, 15, 15), false);
+        let starts: Vec<&str> = blocks.iter().map(|b| b.start_time.as_str()).collect();
+        assert_eq!(starts, vec!["11:15"]);
+        assert_eq!(blocks[0].end_time, "11:45");
+    }
+
+    #[test]
+    fn test_bookable_buffer_before_shifts_start() {
+        let slots = vec![
+            make_slot(1, "2026-03-01", "10:00", "10:30", false),
+            make_slot(2, "2026-03-01", "10:30", "11:00", false),
+            make_slot(3, "2026-03-01", "11:00", "11:30", false),
+        ];
+        // 30-min prep, 60-min appointment: only 10:30 fits, prep uses 10:00–10:30
+        let blocks = find_bookable_blocks(&slots, BlockShape::new(60, 30, 0, 30), false);
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].start_time, "10:30");
+        assert_eq!(blocks[0].end_time, "11:30");
+    }
+
+    // ── Reschedule policy ──
+
+    #[test]
+    fn test_reschedule_policy_allows_within_limits() {
+        let policy = ReschedulePolicy {
+            max_reschedules: 2,
+            cutoff_hours: 24,
+        };
+        assert!(policy.check(0, 48).is_ok());
+        assert!(policy.check(1, 24).is_ok());
+    }
+
+    #[test]
+    fn test_reschedule_policy_limits_count() {
+        let policy = ReschedulePolicy {
+            max_reschedules: 2,
+            cutoff_hours: 24,
+        };
+        assert!(policy.check(2, 100).is_err());
+    }
+
+    #[test]
+    fn test_reschedule_policy_cutoff() {
+        let policy = ReschedulePolicy {
+            max_reschedules: 2,
+            cutoff_hours: 24,
+        };
+        assert!(policy.check(0, 23).is_err());
+        assert!(policy.check(0, -1).is_err());
+    }
+
+    #[test]
+    fn test_resolve_block_conflict_vs_missing() {
+        let slots = vec![
+            make_slot(1, "2026-03-01", "10:00", "11:00", false),
+            make_slot(2, "2026-03-01", "11:00", "12:00", true),
+        ];
+        let taken = resolve_block(&slots, 600, shape(120, 120)).unwrap_err();
+        assert_eq!(taken.0, StatusCode::CONFLICT);
+        let missing = resolve_block(&slots[..1], 600, shape(120, 120)).unwrap_err();
+        assert_eq!(missing.0, StatusCode::NOT_FOUND);
+        let ok = resolve_block(&slots, 600, shape(60, 60)).unwrap();
+        assert_eq!(ok.len(), 1);
+    }
+
+    // ── Concurrent booking ──
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_bookings_single_winner() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite://{}?mode=rwc", dir.path().join("test.db").display());
+        let db = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(8)
+            .connect(&url)
+            .await
+            .unwrap();
+        bimbo_lashes_core::migrations::run(&db).await.unwrap();
+
+        testing::open_slots(
+            &db,
+            "2030-01-01",
+            &[("10:00", "11:00"), ("11:00", "12:00"), ("12:00", "13:00")],
+        )
+        .await;
+        let service_id: i64 = sqlx::query_scalar("SELECT MIN(id) FROM services")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+
+        let attempts = (0..20).map(|i| {
+            let db = db.clone();
+            tokio::spawn(async move {
+                let body = CreateBookingRequest {
+                    service_id,
+                    date: "2030-01-01".into(),
+                    start_time: "10:00".into(),
+                    with_lower_lashes: false,
+                    payment_method: None,
+                    phone: None,
+                    email: None,
+                    payment_plan: PaymentPlan::Deposit,
+                    tip: 0,
+                };
+                let user = TelegramUser {
+                    id: 1000 + i,
+                    first_name: format!("Client {}", i),
+                    last_name: None,
+                    username: None,
+                };
+                let created_at = "2030-01-01 09:00:00";
+                let charge = Charge {
+                    amount_due: 2500,
+                    prepaid: 500,
+                    tip: 0,
+                };
+                reserve_booking(&db, &body, &user, shape(120, 120), 600, charge, false, created_at)
+                    .await
+                    .is_ok()
+            })
+        });
+        let mut winners = 0;
+        for attempt in attempts.collect::<Vec<_>>() {
+            if attempt.await.unwrap() {
+                winners += 1;
+            }
+        }
+        assert_eq!(winners, 1);
+
+        let bookings: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM bookings")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(bookings, 1);
+        let locked: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM available_slots WHERE is_booked = 1 AND booking_id IS NOT NULL",
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        assert_eq!(locked, 2);
+    }
+
+    #[tokio::test]
+    async fn test_reserve_without_prepayment_confirms() {
+        let db = testing::memory_db().await;
+        testing::open_slots(&db, "2030-01-01", &[("10:00", "11:00")]).await;
+        let service_id: i64 = sqlx::query_scalar("SELECT MIN(id) FROM services")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+
+        let mut body = contact_request(None, None);
+        body.service_id = service_id;
+        body.date = "2030-01-01".into();
+        let user = TelegramUser {
+            id: 42,
+            first_name: "Аня".into(),
+            last_name: None,
+            username: None,
+        };
+        let charge = Charge {
+            amount_due: 2500,
+            prepaid: 0,
+            tip: 0,
+        };
+        let created_at = "2030-01-01 09:00:00";
+        let (booking_id, _) =
+            reserve_booking(&db, &body, &user, shape(60, 60), 600, charge, false, created_at)
+                .await
+                .unwrap();
+
+        let (status, payment_status, payment_method): (String, String, String) = sqlx::query_as(
+            "SELECT status, payment_status, payment_method FROM bookings WHERE id = ?",
+        )
+        .bind(booking_id)
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        assert_eq!(status, "confirmed");
+        assert_eq!(payment_status, "none");
+        assert_eq!(payment_method, "none");
+    }
+
+    #[tokio::test]
+    async fn test_offered_block_is_taken_for_others() {
+        let db = testing::memory_db().await;
+        testing::open_slots(&db, "2030-01-01", &[("10:00", "11:00"), ("11:00", "12:00")]).await;
+        sqlx::query(
+            "INSERT INTO waitlist (client_tg_id, client_first_name, service_id, date_from, date_to,
+                                   status, offered_date, offered_time, hold_start, hold_end, hold_until)
+             SELECT 7, 'Оля', MIN(id), '2030-01-01', '2030-01-01', 'offered', '2030-01-01', '10:00',
+                    '10:00', '11:00', datetime('now', '+3 hours', '+10 minutes') FROM services
+             UNION ALL
+             SELECT 8, 'Ира', MIN(id), '2030-01-01', '2030-01-01', 'offered', '2030-01-01', '11:00',
+                    '11:00', '12:00', datetime('now', '+3 hours', '-1 minutes') FROM services",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        let booked = |slots: Vec<AvailableSlot>| slots.iter().map(|s| s.is_booked).collect::<Vec<_>>();
+        // The client offered the block sees it free; the lapsed offer counts for nobody
+        assert_eq!(booked(day_slots(&db, "2030-01-01", 7).await.unwrap()), [false, false]);
+        assert_eq!(booked(day_slots(&db, "2030-01-01", 42).await.unwrap()), [true, false]);
+        assert_eq!(
+            booked(day_slots(&db, "2030-01-01", NO_TELEGRAM).await.unwrap()),
+            [true, false]
+        );
+
+        let mut body = contact_request(None, None);
+        body.service_id = sqlx::query_scalar("SELECT MIN(id) FROM services")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        body.date = "2030-01-01".into();
+        let user = TelegramUser {
+            id: 42,
+            first_name: "Аня".into(),
+            last_name: None,
+            username: None,
+        };
+        let charge = Charge {
+            amount_due: 2500,
+            prepaid: 0,
+            tip: 0,
+        };
+        let err = reserve_booking(&db, &body, &user, shape(60, 60), 600, charge, false, "x")
+            .await
+            .unwrap_err();
+        assert_eq!(err.0, StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_booking_held_block_drops_hold() {
+        let db = testing::memory_db().await;
+        testing::open_slots(&db, "2030-01-01", &[("10:00", "11:00"), ("11:00", "12:00")]).await;
+        sqlx::query(
+            "INSERT INTO slot_holds (client_tg_id, date, start_time, end_time, expires_at)
+             VALUES (42, '2030-01-01', '10:00', '11:00', datetime('now', '+3 hours', '+10 minutes')),
+                    (7, '2030-01-01', '11:00', '12:00', datetime('now', '+3 hours', '+10 minutes'))",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        let mut body = contact_request(None, None);
+        body.service_id = sqlx::query_scalar("SELECT MIN(id) FROM services")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        body.date = "2030-01-01".into();
+        let user = TelegramUser {
+            id: 42,
+            first_name: "Аня".into(),
+            last_name: None,
+            username: None,
+        };
+        let charge = Charge {
+            amount_due: 2500,
+            prepaid: 0,
+            tip: 0,
+        };
+        reserve_booking(&db, &body, &user, shape(60, 60), 600, charge, false, "x")
+            .await
+            .unwrap();
+
+        // The holder's own hold became the booking; someone else's stays
+        let holders: Vec<i64> = sqlx::query_scalar("SELECT client_tg_id FROM slot_holds")
+            .fetch_all(&db)
+            .await
+            .unwrap();
+        assert_eq!(holders, [7]);
+    }
 }
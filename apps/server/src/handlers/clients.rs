@@ -0,0 +1,173 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    Json,
+};
+use bimbo_lashes_core::{receipts, repo};
+use std::sync::Arc;
+
+use crate::{models::*, AppState};
+
+/// Search results are capped at this many clients.
+const SEARCH_LIMIT: i64 = 100;
+
+/// GET /api/admin/clients?q= — clients by name, username, phone, tag or note; recent first.
+pub async fn list_clients(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Query(query): Query<ClientsQuery>,
+) -> Result<Json<ApiResponse<Vec<Client>>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    let pattern = format!("%{}%", query.q.as_deref().unwrap_or("").trim());
+    let clients = sqlx::query_as::<_, Client>(&format!(
+        "{} WHERE c.first_name LIKE ?1 OR c.last_name LIKE ?1 OR c.username LIKE ?1
+         OR c.phone LIKE ?1 OR c.tags LIKE ?1 OR c.notes LIKE ?1
+         ORDER BY c.last_seen_at DESC LIMIT ?2",
+        repo::CLIENT_SELECT
+    ))
+    .bind(&pattern)
+    .bind(SEARCH_LIMIT)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("list_clients: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+
+    Ok(Json(ApiResponse::success(clients)))
+}
+
+/// GET /api/admin/clients/:tg_id — profile, standing and every booking, latest first.
+pub async fn get_client(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(tg_id): Path<i64>,
+) -> Result<Json<ApiResponse<ClientProfile>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("get_client: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    };
+
+    let client = repo::client(&state.db, tg_id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(ApiResponse::error("Клиент не найден"))))?;
+    let standing = super::standing::load_standing(&state, tg_id)
+        .await
+        .map_err(db_error)?;
+    let bookings = sqlx::query_as::<_, BookingDetail>(&format!(
+        "{} WHERE b.client_tg_id = ?
+         ORDER BY COALESCE(b.date, sl.date) DESC, COALESCE(b.start_time, sl.start_time) DESC",
+        repo::BOOKING_DETAIL_SELECT
+    ))
+    .bind(tg_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(ApiResponse::success(ClientProfile {
+        client,
+        standing,
+        bookings,
+    })))
+}
+
+/// PUT /api/admin/clients/:tg_id — edit the master's notes; omitted fields stay, an empty phone clears it.
+pub async fn update_client(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(tg_id): Path<i64>,
+    Json(body): Json<UpdateClientRequest>,
+) -> Result<Json<ApiResponse<Client>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("update_client: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    };
+
+    // Some("") clears the phone
+    let phone = match body.phone.as_deref().map(str::trim) {
+        Some("") => Some(String::new()),
+        Some(phone) => Some(receipts::normalize_phone(phone).ok_or_else(|| {
+            (StatusCode::BAD_REQUEST, Json(ApiResponse::error("Неверный номер телефона")))
+        })?),
+        None => None,
+    };
+    let tags = body.tags.as_deref().map(join_tags);
+
+    let updated = sqlx::query(
+        "UPDATE clients SET
+         phone = CASE WHEN ?1 IS NULL THEN phone ELSE NULLIF(?1, '') END,
+         lash_style = COALESCE(?2, lash_style),
+         curl = COALESCE(?3, curl),
+         length = COALESCE(?4, length),
+         allergies = COALESCE(?5, allergies),
+         notes = COALESCE(?6, notes),
+         tags = COALESCE(?7, tags)
+         WHERE tg_id = ?8",
+    )
+    .bind(phone)
+    .bind(&body.lash_style)
+    .bind(&body.curl)
+    .bind(&body.length)
+    .bind(&body.allergies)
+    .bind(&body.notes)
+    .bind(tags)
+    .bind(tg_id)
+    .execute(&state.db)
+    .await
+    .map_err(db_error)?
+    .rows_affected();
+    if updated == 0 {
+        return Err((StatusCode::NOT_FOUND, Json(ApiResponse::error("Клиент не найден"))));
+    }
+
+    let client = repo::client(&state.db, tg_id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(ApiResponse::error("Клиент не найден"))))?;
+    Ok(Json(ApiResponse::success(client)))
+}
+
+/// Keep the phone from the client's latest booking on their profile.
+pub async fn remember_phone(db: &sqlx::SqlitePool, tg_id: i64, phone: &str) {
+    if let Err(e) = sqlx::query("UPDATE clients SET phone = ? WHERE tg_id = ?")
+        .bind(phone)
+        .bind(tg_id)
+        .execute(db)
+        .await
+    {
+        tracing::error!(tg_id, error = %e, "Failed to save client phone");
+    }
+}
+
+/// Trimmed, de-duplicated tags as stored: comma-separated, commas inside a tag dropped.
+fn join_tags(tags: &[String]) -> String {
+    let mut unique: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.replace(',', " ").trim().to_string();
+        if !tag.is_empty() && !unique.contains(&tag) {
+            unique.push(tag);
+        }
+    }
+    unique.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_tags() {
+        let tags = ["vip".to_string(), " лиса ".into(), "vip".into(), "".into(), "a,b".into()];
+        assert_eq!(join_tags(&tags), "vip,лиса,a b");
+        assert_eq!(join_tags(&[]), "");
+    }
+}
@@ -1,5 +1,6 @@
 use axum::{extract::State, Json};
 use serde::Serialize;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use crate::AppState;
@@ -10,6 +11,8 @@ pub struct HealthResponse {
     pub version: &'static str,
     pub uptime_secs: u64,
     pub db_ok: bool,
+    /// Payment webhooks refused since start.
+    pub webhook_rejections: u64,
 }
 
 pub async fn health(State(state): State<Arc<AppState>>) -> Json<HealthResponse> {
@@ -25,5 +28,6 @@ pub async fn health(State(state): State<Arc<AppState>>) -> Json<HealthResponse>
         version: env!("CARGO_PKG_VERSION"),
         uptime_secs,
         db_ok,
+        webhook_rejections: state.webhook_rejections.load(Ordering::Relaxed),
     })
 }
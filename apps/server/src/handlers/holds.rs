@@ -0,0 +1,356 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    Json,
+};
+use bimbo_lashes_core::time::minutes_until;
+use std::sync::Arc;
+
+use crate::{models::*, AppState};
+
+/// POST /api/holds — keep a block for the client for a few minutes while they
+/// confirm and pay. Replaces the client's previous hold.
+pub async fn create_hold(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Json(body): Json<CreateHoldRequest>,
+) -> Result<Json<ApiResponse<SlotHold>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    let user = super::client::extract_user(auth_header, &state.bot_token)?;
+
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("create_hold: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    };
+
+    if chrono::NaiveDate::parse_from_str(&body.date, "%Y-%m-%d").is_err() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error("Неверный формат даты")),
+        ));
+    }
+    if minutes_until(&body.date, &body.start_time).is_some_and(|m| m <= 0) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error("Это время уже прошло")),
+        ));
+    }
+
+    if super::blocklist::is_blocked(&state.db, &user, None)
+        .await
+        .map_err(db_error)?
+    {
+        return Err((StatusCode::FORBIDDEN, Json(ApiResponse::error(BLOCKED_NOTICE))));
+    }
+    if super::standing::client_restriction(&state, user.id)
+        .await
+        .map_err(db_error)?
+        == Restriction::Blocked
+    {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ApiResponse::error(super::standing::BLOCKED_ONLINE)),
+        ));
+    }
+
+    let service = sqlx::query_as::<_, Service>(
+        "SELECT id, name, description, price, duration_min, is_active, sort_order, service_type,
+         buffer_before_min, buffer_after_min, prepayment_required
+         FROM services WHERE id = ? AND is_active = 1",
+    )
+    .bind(body.service_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error)?
+    .ok_or_else(|| (StatusCode::NOT_FOUND, Json(ApiResponse::error("Услуга не найдена"))))?;
+
+    let (hold, replaced) = place_hold(
+        &state.db,
+        user.id,
+        &service,
+        state.slot_granularity_min,
+        state.slot_hold_min,
+        &body.date,
+        &body.start_time,
+    )
+    .await?;
+    if replaced {
+        state.waitlist.wake();
+    }
+
+    Ok(Json(ApiResponse::success(hold)))
+}
+
+/// Hold the block at `start_time` for `client_tg_id`, dropping their previous hold.
+/// Returns the hold and whether an earlier one was replaced.
+async fn place_hold(
+    db: &sqlx::SqlitePool,
+    client_tg_id: i64,
+    service: &Service,
+    granularity_min: u32,
+    hold_min: i64,
+    date: &str,
+    start_time: &str,
+) -> Result<(SlotHold, bool), (StatusCode, Json<ApiResponse<()>>)> {
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("create_hold: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    };
+
+    // IMMEDIATE: no booking or other hold can take the block between the check and the INSERT
+    let mut tx = db.begin_with("BEGIN IMMEDIATE").await.map_err(db_error)?;
+
+    let replaced = sqlx::query("DELETE FROM slot_holds WHERE client_tg_id = ?")
+        .bind(client_tg_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?
+        .rows_affected();
+
+    let day_slots = super::client::day_slots(&mut *tx, date, client_tg_id)
+        .await
+        .map_err(db_error)?;
+    let slots = super::client::block_slots(&day_slots, service, granularity_min, start_time)?;
+    let (Some(first), Some(last)) = (slots.first(), slots.last()) else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ApiResponse::error("Недостаточно слотов для записи")),
+        ));
+    };
+
+    let (id, expires_at): (i64, String) = sqlx::query_as(&format!(
+        "INSERT INTO slot_holds (client_tg_id, date, start_time, end_time, expires_at)
+         VALUES (?, ?, ?, ?, datetime('now', '+3 hours', '+{} minutes'))
+         RETURNING id, expires_at",
+        hold_min
+    ))
+    .bind(client_tg_id)
+    .bind(date)
+    .bind(&first.start_time)
+    .bind(&last.end_time)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(db_error)?;
+
+    tx.commit().await.map_err(db_error)?;
+
+    let hold = SlotHold {
+        id,
+        date: date.to_string(),
+        start_time: first.start_time.clone(),
+        end_time: last.end_time.clone(),
+        expires_at,
+    };
+    Ok((hold, replaced > 0))
+}
+
+/// DELETE /api/holds/:id — give the held block back before it expires.
+pub async fn release_hold(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<Json<ApiResponse<String>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    let user = super::client::extract_user(auth_header, &state.bot_token)?;
+
+    drop_hold(&state.db, id, user.id).await?;
+    state.waitlist.wake();
+
+    Ok(Json(ApiResponse::success("Время освобождено".into())))
+}
+
+/// Delete hold `id` if it belongs to `client_tg_id`; 404 otherwise.
+async fn drop_hold(
+    db: &sqlx::SqlitePool,
+    id: i64,
+    client_tg_id: i64,
+) -> Result<(), (StatusCode, Json<ApiResponse<()>>)> {
+    let released = sqlx::query("DELETE FROM slot_holds WHERE id = ? AND client_tg_id = ?")
+        .bind(id)
+        .bind(client_tg_id)
+        .execute(db)
+        .await
+        .map_err(|e| {
+            tracing::error!("release_hold: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+        })?
+        .rows_affected();
+
+    if released == 0 {
+        return Err((StatusCode::NOT_FOUND, Json(ApiResponse::error("Бронь времени не найдена"))));
+    }
+    Ok(())
+}
+
+/// Drop the client's holds overlapping the slots they just booked.
+pub(super) async fn release_booked(
+    conn: &mut sqlx::SqliteConnection,
+    client_tg_id: i64,
+    slots: &[AvailableSlot],
+) -> Result<(), sqlx::Error> {
+    let (Some(first), Some(last)) = (slots.first(), slots.last()) else {
+        return Ok(());
+    };
+    sqlx::query(
+        "DELETE FROM slot_holds WHERE client_tg_id = ? AND date = ?
+         AND start_time < ? AND end_time > ?",
+    )
+    .bind(client_tg_id)
+    .bind(&first.date)
+    .bind(&last.end_time)
+    .bind(&first.start_time)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Delete expired holds; their blocks go to the waitlist. Called periodically from `main.rs`.
+pub async fn release_expired_holds(state: &AppState) {
+    match delete_expired_holds(&state.db, &state.waitlist).await {
+        Ok(0) => {}
+        Ok(count) => tracing::info!(count, "Released expired slot holds"),
+        Err(e) => tracing::error!("release_expired_holds failed: {}", e),
+    }
+}
+
+/// Delete expired holds and wake `waitlist` if any went; returns how many.
+async fn delete_expired_holds(
+    db: &sqlx::SqlitePool,
+    waitlist: &super::waitlist::Waitlist,
+) -> Result<u64, sqlx::Error> {
+    let count = sqlx::query("DELETE FROM slot_holds WHERE expires_at <= datetime('now', '+3 hours')")
+        .execute(db)
+        .await?
+        .rows_affected();
+    if count > 0 {
+        waitlist.wake();
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bimbo_lashes_core::testing;
+    use crate::handlers::waitlist::Waitlist;
+
+    const DATE: &str = "2030-01-01";
+
+    /// A fresh database with four hourly slots from 10:00 and a two-hour service.
+    async fn setup() -> (sqlx::SqlitePool, Service) {
+        let db = testing::memory_db().await;
+        testing::open_slots(
+            &db,
+            DATE,
+            &[("10:00", "11:00"), ("11:00", "12:00"), ("12:00", "13:00"), ("13:00", "14:00")],
+        )
+        .await;
+        let service = Service {
+            id: 1,
+            name: "Наращивание".into(),
+            description: String::new(),
+            price: 2500,
+            duration_min: 120,
+            is_active: true,
+            sort_order: 1,
+            service_type: "main".into(),
+            buffer_before_min: 0,
+            buffer_after_min: 0,
+            prepayment_required: true,
+        };
+        (db, service)
+    }
+
+    async fn hold(
+        db: &sqlx::SqlitePool,
+        service: &Service,
+        client_tg_id: i64,
+        start_time: &str,
+    ) -> Result<(SlotHold, bool), (StatusCode, Json<ApiResponse<()>>)> {
+        place_hold(db, client_tg_id, service, 60, 10, DATE, start_time).await
+    }
+
+    async fn taken(db: &sqlx::SqlitePool, client_tg_id: i64) -> Vec<bool> {
+        super::super::client::day_slots(db, DATE, client_tg_id)
+            .await
+            .unwrap()
+            .iter()
+            .map(|s| s.is_booked)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_hold_hides_block_from_others_only() {
+        let (db, service) = setup().await;
+
+        let (held, replaced) = hold(&db, &service, 7, "10:00").await.unwrap();
+        assert!(!replaced);
+        assert_eq!((held.start_time.as_str(), held.end_time.as_str()), ("10:00", "12:00"));
+        let stored: (String, String) =
+            sqlx::query_as("SELECT start_time, end_time FROM slot_holds WHERE id = ?")
+                .bind(held.id)
+                .fetch_one(&db)
+                .await
+                .unwrap();
+        assert_eq!(stored, (held.start_time, held.end_time));
+
+        assert_eq!(taken(&db, 7).await, [false, false, false, false]);
+        assert_eq!(taken(&db, 42).await, [true, true, false, false]);
+        let err = hold(&db, &service, 42, "11:00").await.unwrap_err();
+        assert_eq!(err.0, StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_second_hold_replaces_first() {
+        let (db, service) = setup().await;
+
+        hold(&db, &service, 7, "10:00").await.unwrap();
+        let (_, replaced) = hold(&db, &service, 7, "12:00").await.unwrap();
+        assert!(replaced);
+
+        let holds: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM slot_holds WHERE client_tg_id = 7")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(holds, 1);
+        assert_eq!(taken(&db, 42).await, [false, false, true, true]);
+    }
+
+    #[tokio::test]
+    async fn test_release_only_own_hold() {
+        let (db, service) = setup().await;
+        let (held, _) = hold(&db, &service, 7, "10:00").await.unwrap();
+
+        let err = drop_hold(&db, held.id, 42).await.unwrap_err();
+        assert_eq!(err.0, StatusCode::NOT_FOUND);
+        assert_eq!(taken(&db, 42).await, [true, true, false, false]);
+
+        drop_hold(&db, held.id, 7).await.unwrap();
+        assert_eq!(taken(&db, 42).await, [false, false, false, false]);
+        let err = drop_hold(&db, held.id, 7).await.unwrap_err();
+        assert_eq!(err.0, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_expired_holds_wake_waitlist() {
+        let (db, _) = setup().await;
+        sqlx::query(
+            "INSERT INTO slot_holds (client_tg_id, date, start_time, end_time, expires_at)
+             VALUES (7, '2030-01-01', '10:00', '12:00', datetime('now', '+3 hours', '-1 minutes')),
+                    (8, '2030-01-01', '12:00', '14:00', datetime('now', '+3 hours', '+10 minutes'))",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        let waitlist = Waitlist::new(15);
+        let woken = || tokio::time::timeout(std::time::Duration::from_millis(50), waitlist.woken());
+
+        assert_eq!(delete_expired_holds(&db, &waitlist).await.unwrap(), 1);
+        assert!(woken().await.is_ok());
+        assert_eq!(taken(&db, 42).await, [false, false, true, true]);
+
+        // Nothing else expired: no wake-up
+        assert_eq!(delete_expired_holds(&db, &waitlist).await.unwrap(), 0);
+        assert!(woken().await.is_err());
+    }
+}
@@ -1,4 +1,16 @@
 pub mod admin;
+pub mod approval;
+pub mod blocklist;
 pub mod client;
+pub mod clients;
 pub mod health;
+pub mod holds;
 pub mod payment;
+pub mod policy;
+pub mod reconcile;
+pub mod refunds;
+pub mod schedule;
+pub mod standing;
+pub mod trusted;
+pub mod visits;
+pub mod waitlist;
@@ -1,321 +1,337 @@
 use axum::{
-    extract::State,
-    http::StatusCode,
-    Json,
+    body::Bytes,
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, StatusCode},
 };
+use bimbo_lashes_core::{
+    approval,
+    payments::{PaymentInfo, WebhookEvent},
+    refunds, repo,
+};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
-use crate::{models::*, AppState};
+use crate::{client_ip, models::*, AppState};
 
 /// Payment expiry timeout (minutes).
 const PAYMENT_EXPIRY_MINUTES: i32 = 15;
+/// While the provider can't be asked, expiry waits this many timeouts.
+const PROVIDER_GRACE_FACTOR: i32 = 4;
+
+/// Prepayment methods offered by this deployment
+/// (env PREPAYMENT_REQUIRED / PREPAYMENT_METHOD / TELEGRAM_PAYMENT_TOKEN).
+#[derive(Debug, Clone)]
+pub struct PrepaymentMethods {
+    /// `false`: every booking is confirmed without prepayment.
+    pub required: bool,
+    /// Used when the booking request doesn't choose.
+    pub default: PaymentMethod,
+    /// Bot API payment provider token; `None` disables Telegram invoices.
+    pub telegram_token: Option<String>,
+}
 
-/// YooKassa allowed IP prefixes (for future webhook validation).
-///
-/// Ranges: 185.71.76.0/27, 185.71.77.0/27, 77.75.153.0/25, 77.75.154.128/25, 77.75.156.35
-#[allow(dead_code)]
-const YOOKASSA_IP_PREFIXES: &[&str] = &[
-    "185.71.76.",
-    "185.71.77.",
-    "77.75.153.",
-    "77.75.154.",
-    "77.75.156.35",
-];
-
-/// Validate that a request comes from YooKassa IP range.
-#[allow(dead_code)]
-fn is_yookassa_ip(ip: &str) -> bool {
-    for prefix in YOOKASSA_IP_PREFIXES {
-        if ip.starts_with(prefix) {
-            return true;
+impl PrepaymentMethods {
+    /// Method for a new booking. `Err` carries the user-facing reason.
+    pub fn resolve(&self, requested: Option<PaymentMethod>) -> Result<PaymentMethod, &'static str> {
+        let method = requested.unwrap_or(self.default);
+        if method == PaymentMethod::Telegram && self.telegram_token.is_none() {
+            return Err("Оплата через Telegram недоступна");
         }
+        Ok(method)
     }
-    ip == "127.0.0.1" || ip == "::1"
 }
 
-/// Create a payment in YooKassa.
+/// POST /api/payments/webhook — payment provider notifications.
 ///
-/// Returns `(payment_id, confirmation_url)` on success.
-pub async fn create_yookassa_payment(
-    shop_id: &str,
-    secret_key: &str,
-    booking_id: i64,
-    amount: i64,
-    description: &str,
-    return_url: &str,
-) -> anyhow::Result<(String, String)> {
-    let client = reqwest::Client::new();
-
-    let idempotence_key = format!(
-        "booking-{}-{}",
-        booking_id,
-        chrono::Utc::now().timestamp_millis()
-    );
+/// Accepted only from the provider's networks; payment events are re-checked
+/// against the provider API before the booking is touched.
+pub async fn payment_webhook(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    let ip = client_ip::client_ip(peer.ip(), &headers, &state.trusted_proxies);
 
-    let body = serde_json::json!({
-        "amount": {
-            "value": format!("{}.00", amount),
-            "currency": "RUB"
-        },
-        "capture": true,
-        "confirmation": {
-            "type": "redirect",
-            "return_url": return_url
-        },
-        "description": description,
-        "metadata": {
-            "booking_id": booking_id.to_string()
+    if let Some(sources) = &state.webhook_sources {
+        if !sources.contains(ip) {
+            return reject_webhook(&state, ip, "source outside provider networks", StatusCode::FORBIDDEN);
         }
-    });
-
-    let resp = client
-        .post("https://api.yookassa.ru/v3/payments")
-        .basic_auth(shop_id, Some(secret_key))
-        .header("Idempotence-Key", &idempotence_key)
-        .header("Content-Type", "application/json")
-        .json(&body)
-        .send()
-        .await?;
-
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let text = resp.text().await.unwrap_or_default();
-        tracing::error!("YooKassa payment creation failed: {} - {}", status, text);
-        anyhow::bail!("YooKassa API error: {}", status);
     }
 
-    let json: serde_json::Value = resp.json().await?;
-
-    let payment_id = json["id"]
-        .as_str()
-        .ok_or_else(|| anyhow::anyhow!("Missing payment id in YooKassa response"))?
-        .to_string();
+    let event = match state.payments.parse_webhook(&body) {
+        Ok(event) => event,
+        Err(e) => {
+            tracing::debug!(error = %e, "Webhook body did not parse");
+            return reject_webhook(&state, ip, "malformed body", StatusCode::BAD_REQUEST);
+        }
+    };
 
-    let confirmation_url = json["confirmation"]["confirmation_url"]
-        .as_str()
-        .ok_or_else(|| anyhow::anyhow!("Missing confirmation URL in YooKassa response"))?
-        .to_string();
+    tracing::info!(provider = state.payments.name(), ?event, %ip, "Payment webhook received");
 
-    tracing::info!(
-        booking_id,
-        payment_id = %payment_id,
-        "YooKassa payment created"
-    );
+    match event {
+        WebhookEvent::PaymentSucceeded { payment_id, booking_id } => {
+            let Some(booking_id) = booking_id else {
+                tracing::warn!(payment_id = %payment_id, "Webhook missing booking_id in metadata");
+                return StatusCode::OK;
+            };
+            if let Err(status) = verify_payment(&state, ip, &payment_id, booking_id, "succeeded").await {
+                return status;
+            }
+            if let Err(e) = mark_paid(&state, booking_id).await {
+                tracing::error!(booking_id, error = %e, "Failed to update booking");
+                return StatusCode::INTERNAL_SERVER_ERROR;
+            }
+        }
 
-    Ok((payment_id, confirmation_url))
-}
+        WebhookEvent::PaymentCanceled { payment_id, booking_id } => {
+            let Some(booking_id) = booking_id else {
+                tracing::warn!(payment_id = %payment_id, "Webhook missing booking_id in metadata");
+                return StatusCode::OK;
+            };
+            if let Err(status) = verify_payment(&state, ip, &payment_id, booking_id, "canceled").await {
+                return status;
+            }
+            tracing::info!(booking_id, "Payment canceled");
+            expire_booking(&state.db, booking_id).await;
+        }
 
-/// Create a refund in YooKassa.
-pub async fn create_yookassa_refund(
-    shop_id: &str,
-    secret_key: &str,
-    payment_id: &str,
-    amount: i64,
-) -> anyhow::Result<()> {
-    let client = reqwest::Client::new();
-
-    let idempotence_key = format!(
-        "refund-{}-{}",
-        payment_id,
-        chrono::Utc::now().timestamp_millis()
-    );
+        WebhookEvent::Refund { refund_id, status } => {
+            match refunds::update_refund_status(&state.db, &refund_id, &status).await {
+                Ok(Some(booking_id)) => {
+                    tracing::info!(booking_id, refund_id = %refund_id, status = %status, "Refund updated");
+                }
+                Ok(None) => {
+                    tracing::warn!(refund_id = %refund_id, "Webhook for unknown refund");
+                }
+                Err(e) => {
+                    tracing::error!(refund_id = %refund_id, error = %e, "Failed to update refund");
+                    return StatusCode::INTERNAL_SERVER_ERROR;
+                }
+            }
+        }
 
-    let body = serde_json::json!({
-        "payment_id": payment_id,
-        "amount": {
-            "value": format!("{}.00", amount),
-            "currency": "RUB"
+        WebhookEvent::Other(event) => {
+            tracing::debug!(event = %event, "Ignoring webhook event");
         }
-    });
-
-    let resp = client
-        .post("https://api.yookassa.ru/v3/refunds")
-        .basic_auth(shop_id, Some(secret_key))
-        .header("Idempotence-Key", &idempotence_key)
-        .header("Content-Type", "application/json")
-        .json(&body)
-        .send()
-        .await?;
-
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let text = resp.text().await.unwrap_or_default();
-        tracing::error!("YooKassa refund failed: {} - {}", status, text);
-        anyhow::bail!("YooKassa refund error: {}", status);
     }
 
-    tracing::info!(payment_id, "YooKassa refund created");
-    Ok(())
+    StatusCode::OK
 }
 
-/// POST /api/payments/webhook — handle YooKassa webhook notifications.
-pub async fn payment_webhook(
-    State(state): State<Arc<AppState>>,
-    _headers: axum::http::HeaderMap,
-    Json(event): Json<YooKassaWebhookEvent>,
-) -> StatusCode {
-    tracing::info!(
-        event = %event.event,
-        payment_id = %event.object.id,
-        status = %event.object.status,
-        "YooKassa webhook received"
-    );
+/// Log and count a webhook we refuse to act on.
+fn reject_webhook(state: &AppState, ip: IpAddr, reason: &str, status: StatusCode) -> StatusCode {
+    let total = state.webhook_rejections.fetch_add(1, Ordering::Relaxed) + 1;
+    tracing::warn!(%ip, reason, total, "Payment webhook rejected");
+    status
+}
 
-    // Extract booking_id from metadata
-    let booking_id: i64 = match event
-        .object
-        .metadata
-        .as_ref()
-        .and_then(|m| m.get("booking_id"))
-        .and_then(|v| v.as_str())
-        .and_then(|s| s.parse().ok())
-    {
-        Some(id) => id,
-        None => {
-            tracing::warn!("Webhook missing booking_id in metadata");
-            return StatusCode::OK;
-        }
+/// Re-fetch the payment from the provider and check it against the booking.
+///
+/// `Err` carries the response: a rejection, or 500 when the provider can't be
+/// reached (the provider retries the notification later).
+async fn verify_payment(
+    state: &AppState,
+    ip: IpAddr,
+    payment_id: &str,
+    booking_id: i64,
+    expected_status: &str,
+) -> Result<(), StatusCode> {
+    let Some(booking) = fetch_booking(&state.db, booking_id).await else {
+        return Err(reject_webhook(state, ip, "unknown booking", StatusCode::BAD_REQUEST));
     };
 
-    match event.event.as_str() {
-        "payment.succeeded" => {
-            tracing::info!(booking_id, "Payment succeeded");
+    let info = state.payments.fetch_payment(payment_id).await.map_err(|e| {
+        tracing::error!(booking_id, payment_id, error = %e, "Failed to re-fetch payment");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
 
-            let result = sqlx::query(
-                "UPDATE bookings SET status = 'confirmed', payment_status = 'paid'
-                 WHERE id = ? AND status = 'pending_payment'",
-            )
-            .bind(booking_id)
-            .execute(&state.db)
-            .await;
+    check_payment(&info, &booking, expected_status)
+        .map_err(|reason| reject_webhook(state, ip, reason, StatusCode::BAD_REQUEST))
+}
 
-            if let Err(e) = result {
-                tracing::error!(booking_id, error = %e, "Failed to update booking");
-                return StatusCode::INTERNAL_SERVER_ERROR;
-            }
+/// Does the provider's view of a payment back up the notification?
+pub fn check_payment(info: &PaymentInfo, booking: &Booking, expected_status: &str) -> Result<(), &'static str> {
+    if booking.yookassa_payment_id.as_deref() != Some(info.id.as_str()) {
+        return Err("payment does not belong to booking");
+    }
+    if info.booking_id != Some(booking.id) {
+        return Err("payment metadata names another booking");
+    }
+    if info.status != expected_status {
+        return Err("payment status differs from notification");
+    }
+    if expected_status == "succeeded" && info.amount != booking.prepaid_amount {
+        return Err("paid amount differs from prepayment");
+    }
+    Ok(())
+}
 
-            // Notify admin about successful payment
-            if let Some(booking) = fetch_booking(&state.db, booking_id).await {
-                let mention = booking
-                    .client_username
-                    .as_ref()
-                    .map(|u| format!("@{}", u))
-                    .unwrap_or_else(|| booking.client_first_name.clone());
-
-                let service_name: String =
-                    sqlx::query_scalar("SELECT name FROM services WHERE id = ?")
-                        .bind(booking.service_id)
-                        .fetch_optional(&state.db)
-                        .await
-                        .ok()
-                        .flatten()
-                        .unwrap_or_else(|| "?".into());
-
-                let b_date = booking.date.as_deref().unwrap_or("?");
-                let b_start = booking.start_time.as_deref().unwrap_or("?");
-                let b_end = booking.end_time.as_deref().unwrap_or("?");
-                let addon_text = if booking.with_lower_lashes {
-                    "\n   + нижние ресницы"
-                } else {
-                    ""
-                };
-
-                let message = format!(
-                    "📋 Новая запись! 💳 Оплачено\n\n\
-                     👤 {}\n\
-                     💅 {}{}\n\
-                     📅 {} в {} — {}\n\
-                     💰 Предоплата {} ₽",
-                    mention, service_name, addon_text, b_date, b_start, b_end,
-                    booking.prepaid_amount
-                );
-
-                super::client::notify_admin(&state.bot_token, state.admin_tg_id, &message).await;
-            }
-        }
+/// Confirm a paid booking (or pass it on for approval) and notify the admin
+/// (webhook or auto-confirmed mock payment).
+pub async fn mark_paid(state: &AppState, booking_id: i64) -> Result<(), sqlx::Error> {
+    tracing::info!(booking_id, "Payment succeeded");
 
-        "payment.canceled" => {
-            tracing::info!(booking_id, "Payment canceled");
+    // Repeated notification: already confirmed and announced
+    if !repo::confirm_payment(&state.db, booking_id, None, None).await? {
+        return Ok(());
+    }
 
-            if let Err(e) = sqlx::query(
-                "UPDATE bookings SET status = 'expired', payment_status = 'none'
-                 WHERE id = ? AND status = 'pending_payment'",
-            )
-            .bind(booking_id)
-            .execute(&state.db)
-            .await
-            {
-                tracing::error!(booking_id, error = %e, "Failed to expire booking");
-            }
+    notify_new_booking(state, booking_id).await;
+    Ok(())
+}
 
-            if let Err(e) = sqlx::query(
-                "UPDATE available_slots SET is_booked = 0, booking_id = NULL
-                 WHERE booking_id = ?",
-            )
-            .bind(booking_id)
-            .execute(&state.db)
+/// Tell the admin about a new booking: once paid, or right away without prepayment.
+/// Bookings awaiting approval come with Approve/Decline buttons.
+pub async fn notify_new_booking(state: &AppState, booking_id: i64) {
+    let Some(booking) = fetch_booking(&state.db, booking_id).await else {
+        return;
+    };
+
+    let mention = booking
+        .client_username
+        .as_ref()
+        .map(|u| format!("@{}", u))
+        .unwrap_or_else(|| booking.client_first_name.clone());
+
+    let service_name: String =
+        sqlx::query_scalar("SELECT name FROM services WHERE id = ?")
+            .bind(booking.service_id)
+            .fetch_optional(&state.db)
             .await
-            {
-                tracing::error!(booking_id, error = %e, "Failed to free slots");
-            }
-        }
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| "?".into());
+
+    let b_date = booking.date.as_deref().unwrap_or("?");
+    let b_start = booking.start_time.as_deref().unwrap_or("?");
+    let b_end = booking.end_time.as_deref().unwrap_or("?");
+    let addon_text = if booking.with_lower_lashes {
+        "\n   + нижние ресницы"
+    } else {
+        ""
+    };
+    let (badge, amount_line) = if booking.payment_status == "paid" {
+        ("💳 Оплачено", format!("Оплачено онлайн {} ₽", booking.prepaid_amount))
+    } else {
+        ("🤝 Без предоплаты", format!("Оплата на месте {} ₽", booking.amount_due))
+    };
 
-        other => {
-            tracing::debug!(event = other, "Ignoring webhook event");
-        }
-    }
+    let awaiting_approval = booking.status == "awaiting_approval";
+    let title = if awaiting_approval {
+        "🙋 Новая заявка — нужно подтверждение!"
+    } else {
+        "📋 Новая запись!"
+    };
 
-    StatusCode::OK
+    let message = format!(
+        "{} {}\n\n\
+         👤 {}\n\
+         💅 {}{}\n\
+         📅 {} в {} — {}\n\
+         💰 {}",
+        title, badge, mention, service_name, addon_text, b_date, b_start, b_end, amount_line
+    );
+
+    let keyboard = awaiting_approval.then(|| approval::keyboard_json(booking.id));
+    super::client::send_message(&state.bot_token, state.admin_tg_id, &message, keyboard).await;
 }
 
 /// Expire pending_payment bookings older than the timeout.
-pub async fn expire_pending_payments(db: &sqlx::SqlitePool) {
-    let expired_ids: Vec<i64> = match sqlx::query_scalar(&format!(
-        "SELECT id FROM bookings
+///
+/// Each provider payment is re-checked first, so a booking whose `payment.succeeded`
+/// webhook was lost gets confirmed instead of expired.
+pub async fn expire_pending_payments(state: &AppState) {
+    let candidates: Vec<(i64, Option<String>, bool)> = match sqlx::query_as(&format!(
+        "SELECT id, yookassa_payment_id,
+                datetime(created_at, '+{} minutes') < datetime('now', '+3 hours') AS overdue
+         FROM bookings
          WHERE status = 'pending_payment'
          AND datetime(created_at, '+{} minutes') < datetime('now', '+3 hours')",
+        PAYMENT_EXPIRY_MINUTES * PROVIDER_GRACE_FACTOR,
         PAYMENT_EXPIRY_MINUTES
     ))
-    .fetch_all(db)
+    .fetch_all(&state.db)
     .await
     {
-        Ok(ids) => ids,
+        Ok(rows) => rows,
         Err(e) => {
             tracing::error!("expire_pending_payments query failed: {}", e);
             return;
         }
     };
 
-    if expired_ids.is_empty() {
+    if candidates.is_empty() {
         return;
     }
 
-    tracing::info!(count = expired_ids.len(), "Expiring unpaid bookings");
+    tracing::info!(count = candidates.len(), "Checking unpaid bookings before expiry");
+
+    for (booking_id, payment_id, overdue) in candidates {
+        if let Some(payment_id) = payment_id {
+            match state.payments.fetch_payment(&payment_id).await {
+                Ok(info) if info.status == "succeeded" => {
+                    let paid = fetch_booking(&state.db, booking_id)
+                        .await
+                        .is_some_and(|b| check_payment(&info, &b, "succeeded").is_ok());
+                    if paid {
+                        tracing::warn!(booking_id, payment_id = %payment_id, "Recovered missed payment webhook");
+                        if let Err(e) = mark_paid(state, booking_id).await {
+                            tracing::error!(booking_id, error = %e, "Failed to confirm recovered payment");
+                        }
+                        continue;
+                    }
+                    // Amount or booking mismatch: expire, the sweep reports it
+                }
+                Ok(_) => {}
+                Err(e) if !overdue => {
+                    tracing::warn!(booking_id, error = %e, "Payment status unavailable, expiry postponed");
+                    continue;
+                }
+                Err(e) => {
+                    tracing::error!(booking_id, error = %e, "Payment status unavailable, expiring anyway");
+                }
+            }
+        }
 
-    for booking_id in expired_ids {
         tracing::info!(booking_id, "Expiring unpaid booking");
+        expire_booking(&state.db, booking_id).await;
+        state.waitlist.wake();
+    }
+}
 
-        if let Err(e) = sqlx::query(
-            "UPDATE bookings SET status = 'expired', payment_status = 'none'
-             WHERE id = ? AND status = 'pending_payment'",
-        )
-        .bind(booking_id)
-        .execute(db)
-        .await
-        {
+/// Mark an unpaid booking expired and free its slots.
+async fn expire_booking(db: &sqlx::SqlitePool, booking_id: i64) {
+    let expired = match sqlx::query(
+        "UPDATE bookings SET status = 'expired', payment_status = 'none'
+         WHERE id = ? AND status = 'pending_payment'",
+    )
+    .bind(booking_id)
+    .execute(db)
+    .await
+    {
+        Ok(result) => result.rows_affected() > 0,
+        Err(e) => {
             tracing::error!(booking_id, error = %e, "Failed to expire booking");
+            return;
         }
+    };
 
-        if let Err(e) = sqlx::query(
-            "UPDATE available_slots SET is_booked = 0, booking_id = NULL
-             WHERE booking_id = ?",
-        )
-        .bind(booking_id)
-        .execute(db)
-        .await
-        {
-            tracing::error!(booking_id, error = %e, "Failed to free slots");
-        }
+    // Paid in the meantime: the slots belong to a confirmed booking now
+    if !expired {
+        return;
+    }
+
+    if let Err(e) = sqlx::query(
+        "UPDATE available_slots SET is_booked = 0, booking_id = NULL
+         WHERE booking_id = ?",
+    )
+    .bind(booking_id)
+    .execute(db)
+    .await
+    {
+        tracing::error!(booking_id, error = %e, "Failed to free slots");
     }
 }
 
@@ -328,3 +344,95 @@ async fn fetch_booking(db: &sqlx::SqlitePool, booking_id: i64) -> Option<Booking
         .ok()
         .flatten()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn methods(default: PaymentMethod, token: Option<&str>) -> PrepaymentMethods {
+        PrepaymentMethods {
+            required: true,
+            default,
+            telegram_token: token.map(String::from),
+        }
+    }
+
+    fn booking() -> Booking {
+        Booking {
+            id: 5,
+            service_id: 1,
+            slot_id: 1,
+            client_tg_id: 42,
+            client_username: None,
+            client_first_name: "Аня".into(),
+            status: "pending_payment".into(),
+            reminder_sent: false,
+            created_at: "2026-03-01 10:00:00".into(),
+            cancelled_at: None,
+            date: Some("2026-03-02".into()),
+            start_time: Some("10:00".into()),
+            end_time: Some("12:00".into()),
+            with_lower_lashes: false,
+            payment_status: "pending".into(),
+            yookassa_payment_id: Some("pay-5".into()),
+            prepaid_amount: 500,
+            reschedule_count: 0,
+            payment_method: "yookassa".into(),
+            telegram_charge_id: None,
+            client_phone: None,
+            client_email: None,
+            amount_due: 2500,
+            tip_amount: 0,
+            needs_approval: false,
+            amount_collected: None,
+            closed_at: None,
+            outcome_prompts: 0,
+            cancelled_by: None,
+        }
+    }
+
+    fn payment(status: &str, amount: i64) -> PaymentInfo {
+        PaymentInfo {
+            id: "pay-5".into(),
+            status: status.into(),
+            booking_id: Some(5),
+            amount,
+            refunded: 0,
+        }
+    }
+
+    #[test]
+    fn test_resolve_uses_deployment_default() {
+        let m = methods(PaymentMethod::Telegram, Some("tok"));
+        assert_eq!(m.resolve(None), Ok(PaymentMethod::Telegram));
+        assert_eq!(m.resolve(Some(PaymentMethod::Yookassa)), Ok(PaymentMethod::Yookassa));
+    }
+
+    #[test]
+    fn test_resolve_telegram_requires_token() {
+        let m = methods(PaymentMethod::Yookassa, None);
+        assert_eq!(m.resolve(None), Ok(PaymentMethod::Yookassa));
+        assert!(m.resolve(Some(PaymentMethod::Telegram)).is_err());
+    }
+
+    #[test]
+    fn test_check_payment_accepts_matching() {
+        assert!(check_payment(&payment("succeeded", 500), &booking(), "succeeded").is_ok());
+        assert!(check_payment(&payment("canceled", 0), &booking(), "canceled").is_ok());
+    }
+
+    #[test]
+    fn test_check_payment_rejects_forged_notification() {
+        // Notification claims success, provider says pending
+        assert!(check_payment(&payment("pending", 500), &booking(), "succeeded").is_err());
+        assert!(check_payment(&payment("succeeded", 1), &booking(), "succeeded").is_err());
+
+        let mut other = payment("succeeded", 500);
+        other.id = "pay-9".into();
+        assert!(check_payment(&other, &booking(), "succeeded").is_err());
+
+        let mut moved = payment("succeeded", 500);
+        moved.booking_id = Some(6);
+        assert!(check_payment(&moved, &booking(), "succeeded").is_err());
+    }
+}
@@ -0,0 +1,161 @@
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    Json,
+};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::{models::*, AppState};
+
+/// GET /api/admin/cancellation-policy — default tiers and per-service overrides.
+pub async fn get_policy(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+) -> Result<Json<ApiResponse<Vec<CancellationTier>>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    let tiers = load_tiers(&state.db, None, true).await.map_err(|e| {
+        tracing::error!("get_policy: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+
+    Ok(Json(ApiResponse::success(tiers)))
+}
+
+/// PUT /api/admin/cancellation-policy — replace the default tiers or one service's override.
+pub async fn update_policy(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Json(body): Json<UpdateCancellationPolicyRequest>,
+) -> Result<Json<ApiResponse<Vec<CancellationTier>>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    validate_tiers(&body.tiers)
+        .map_err(|msg| (StatusCode::BAD_REQUEST, Json(ApiResponse::error(msg))))?;
+
+    if let Some(service_id) = body.service_id {
+        let exists: bool = sqlx::query_scalar("SELECT COUNT(*) > 0 FROM services WHERE id = ?")
+            .bind(service_id)
+            .fetch_one(&state.db)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error"))))?;
+        if !exists {
+            return Err((StatusCode::NOT_FOUND, Json(ApiResponse::error("Услуга не найдена"))));
+        }
+    }
+
+    let mut tx = state.db.begin().await.map_err(|e| {
+        tracing::error!("update_policy begin: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+
+    let result: Result<(), sqlx::Error> = async {
+        sqlx::query("DELETE FROM cancellation_tiers WHERE service_id IS ?")
+            .bind(body.service_id)
+            .execute(&mut *tx)
+            .await?;
+        for tier in &body.tiers {
+            sqlx::query(
+                "INSERT INTO cancellation_tiers (service_id, more_than_hours, refund_percent)
+                 VALUES (?, ?, ?)",
+            )
+            .bind(body.service_id)
+            .bind(tier.more_than_hours)
+            .bind(tier.refund_percent)
+            .execute(&mut *tx)
+            .await?;
+        }
+        Ok(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        tracing::error!("update_policy: {}", e);
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error"))));
+    }
+
+    tx.commit().await.map_err(|e| {
+        tracing::error!("update_policy commit: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+
+    let tiers = load_tiers(&state.db, body.service_id, false).await.map_err(|e| {
+        tracing::error!("update_policy fetch: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+
+    Ok(Json(ApiResponse::success(tiers)))
+}
+
+// ── Private helpers ──
+
+/// Tiers of one policy (`all` = every policy, default first).
+async fn load_tiers(
+    db: &sqlx::SqlitePool,
+    service_id: Option<i64>,
+    all: bool,
+) -> Result<Vec<CancellationTier>, sqlx::Error> {
+    sqlx::query_as::<_, CancellationTier>(
+        "SELECT id, service_id, more_than_hours, refund_percent FROM cancellation_tiers
+         WHERE ? OR service_id IS ?
+         ORDER BY service_id IS NOT NULL, service_id, more_than_hours DESC",
+    )
+    .bind(all)
+    .bind(service_id)
+    .fetch_all(db)
+    .await
+}
+
+/// Tiers need non-negative hours, a 0–100 percent and distinct thresholds.
+fn validate_tiers(tiers: &[CancellationTierInput]) -> Result<(), &'static str> {
+    let mut seen = HashSet::new();
+    for tier in tiers {
+        if tier.more_than_hours < 0 {
+            return Err("more_than_hours не может быть отрицательным");
+        }
+        if !(0..=100).contains(&tier.refund_percent) {
+            return Err("refund_percent должен быть от 0 до 100");
+        }
+        if !seen.insert(tier.more_than_hours) {
+            return Err("Пороги more_than_hours не должны повторяться");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tier(more_than_hours: i64, refund_percent: i64) -> CancellationTierInput {
+        CancellationTierInput {
+            more_than_hours,
+            refund_percent,
+        }
+    }
+
+    #[test]
+    fn test_validate_tiers_ok() {
+        assert!(validate_tiers(&[tier(48, 100), tier(24, 50)]).is_ok());
+        assert!(validate_tiers(&[]).is_ok());
+    }
+
+    #[test]
+    fn test_validate_tiers_bad_percent() {
+        assert!(validate_tiers(&[tier(24, 101)]).is_err());
+        assert!(validate_tiers(&[tier(24, -1)]).is_err());
+    }
+
+    #[test]
+    fn test_validate_tiers_negative_hours() {
+        assert!(validate_tiers(&[tier(-1, 100)]).is_err());
+    }
+
+    #[test]
+    fn test_validate_tiers_duplicate_threshold() {
+        assert!(validate_tiers(&[tier(24, 100), tier(24, 50)]).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use bimbo_lashes_core::payments::PaymentInfo;
+
+use crate::{models::*, AppState};
+
+/// How far back the sweep re-checks provider payments.
+const RECONCILE_WINDOW_DAYS: i64 = 7;
+
+/// Compare recent provider payments with our bookings and report new mismatches
+/// to the admin. Called periodically from `main.rs`.
+pub async fn reconcile_payments(state: &AppState) {
+    let records = match sqlx::query_as::<_, PaymentRecord>(&format!(
+        "SELECT b.id AS booking_id, b.yookassa_payment_id AS payment_id, b.status,
+                b.payment_status, b.prepaid_amount,
+                COALESCE((SELECT SUM(r.amount) FROM refunds r
+                          WHERE r.booking_id = b.id AND r.status = 'succeeded'), 0) AS refunded
+         FROM bookings b
+         WHERE b.yookassa_payment_id IS NOT NULL
+         AND b.status != 'pending_payment'
+         AND b.created_at >= datetime('now', '+3 hours', '-{} days')",
+        RECONCILE_WINDOW_DAYS
+    ))
+    .fetch_all(&state.db)
+    .await
+    {
+        Ok(records) => records,
+        Err(e) => {
+            tracing::error!("reconcile_payments query failed: {}", e);
+            return;
+        }
+    };
+
+    let mut report = Vec::new();
+
+    for record in records {
+        let info = match state.payments.fetch_payment(&record.payment_id).await {
+            Ok(info) => info,
+            Err(e) => {
+                tracing::warn!(booking_id = record.booking_id, error = %e, "Reconciliation fetch failed");
+                continue;
+            }
+        };
+
+        for detail in discrepancies(&record, &info) {
+            // Reported once: the row is only new the first time
+            let inserted = sqlx::query(
+                "INSERT OR IGNORE INTO payment_discrepancies (booking_id, payment_id, detail)
+                 VALUES (?, ?, ?)",
+            )
+            .bind(record.booking_id)
+            .bind(&record.payment_id)
+            .bind(&detail)
+            .execute(&state.db)
+            .await;
+
+            match inserted {
+                Ok(r) if r.rows_affected() > 0 => {
+                    tracing::warn!(booking_id = record.booking_id, detail = %detail, "Payment discrepancy");
+                    report.push(format!("• #{} ({}): {}", record.booking_id, record.payment_id, detail));
+                }
+                Ok(_) => {}
+                Err(e) => tracing::error!("reconcile_payments insert failed: {}", e),
+            }
+        }
+    }
+
+    if !report.is_empty() {
+        let message = format!(
+            "⚠️ Расхождения с ЮKassa ({}):\n\n{}",
+            report.len(),
+            report.join("\n")
+        );
+        super::client::notify_admin(&state.bot_token, state.admin_tg_id, &message).await;
+    }
+}
+
+/// Problems with one payment, described for the admin. Empty when both sides agree.
+fn discrepancies(record: &PaymentRecord, info: &PaymentInfo) -> Vec<String> {
+    let locally_paid = matches!(
+        record.payment_status.as_str(),
+        "paid" | "partially_refunded" | "refunded"
+    );
+    let mut problems = Vec::new();
+
+    match (info.status == "succeeded", locally_paid) {
+        (true, false) => problems.push(format!(
+            "оплата {} ₽ прошла, но запись {} без оплаты",
+            info.amount, record.status
+        )),
+        (false, true) => problems.push(format!(
+            "в ЮKassa платёж {}, а запись считается оплаченной",
+            info.status
+        )),
+        _ => {}
+    }
+
+    if info.status == "succeeded" {
+        if info.amount != record.prepaid_amount {
+            problems.push(format!(
+                "оплачено {} ₽, ожидалось {} ₽",
+                info.amount, record.prepaid_amount
+            ));
+        }
+        if info.refunded != record.refunded {
+            problems.push(format!(
+                "возвращено {} ₽, у нас учтено {} ₽",
+                info.refunded, record.refunded
+            ));
+        }
+    }
+
+    problems
+}
+
+// ── Tests ──
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(status: &str, payment_status: &str, refunded: i64) -> PaymentRecord {
+        PaymentRecord {
+            booking_id: 1,
+            payment_id: "pay-1".into(),
+            status: status.into(),
+            payment_status: payment_status.into(),
+            prepaid_amount: 500,
+            refunded,
+        }
+    }
+
+    fn remote(status: &str, amount: i64, refunded: i64) -> PaymentInfo {
+        PaymentInfo {
+            id: "pay-1".into(),
+            status: status.into(),
+            booking_id: Some(1),
+            amount,
+            refunded,
+        }
+    }
+
+    #[test]
+    fn test_matching_payment_has_no_discrepancies() {
+        assert!(discrepancies(&record("confirmed", "paid", 0), &remote("succeeded", 500, 0)).is_empty());
+        assert!(discrepancies(&record("cancelled", "refunded", 500), &remote("succeeded", 500, 500)).is_empty());
+        assert!(discrepancies(&record("expired", "none", 0), &remote("canceled", 500, 0)).is_empty());
+    }
+
+    #[test]
+    fn test_paid_but_expired() {
+        let problems = discrepancies(&record("expired", "none", 0), &remote("succeeded", 500, 0));
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("expired"));
+    }
+
+    #[test]
+    fn test_paid_locally_but_not_at_provider() {
+        let problems = discrepancies(&record("confirmed", "paid", 0), &remote("canceled", 500, 0));
+        assert_eq!(problems.len(), 1);
+    }
+
+    #[test]
+    fn test_amount_and_refund_mismatch() {
+        let problems =
+            discrepancies(&record("cancelled", "partially_refunded", 200), &remote("succeeded", 400, 300));
+        assert_eq!(problems.len(), 2);
+    }
+}
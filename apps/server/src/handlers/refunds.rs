@@ -0,0 +1,136 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    Json,
+};
+use bimbo_lashes_core::refunds;
+use std::sync::Arc;
+
+use crate::{models::*, AppState};
+
+/// GET /api/admin/refunds?booking_id=&status= — refunds, newest first.
+pub async fn list_refunds(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Query(query): Query<RefundsQuery>,
+) -> Result<Json<ApiResponse<Vec<RefundDetail>>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    let refunds = sqlx::query_as::<_, RefundDetail>(
+        "SELECT r.id, r.booking_id, r.yookassa_refund_id, r.amount, r.status, r.reason,
+                r.created_at, r.updated_at, b.client_first_name, b.client_username,
+                b.date, b.start_time
+         FROM refunds r
+         JOIN bookings b ON b.id = r.booking_id
+         WHERE (? IS NULL OR r.booking_id = ?)
+           AND (? IS NULL OR r.status = ?)
+         ORDER BY r.id DESC",
+    )
+    .bind(query.booking_id)
+    .bind(query.booking_id)
+    .bind(&query.status)
+    .bind(&query.status)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("list_refunds: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+
+    Ok(Json(ApiResponse::success(refunds)))
+}
+
+/// POST /api/admin/bookings/:id/refund — refund part (or the rest) of a prepayment.
+pub async fn create_refund(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(id): Path<i64>,
+    Json(body): Json<CreateRefundRequest>,
+) -> Result<Json<ApiResponse<Refund>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    let booking = sqlx::query_as::<_, Booking>("SELECT * FROM bookings WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|e| {
+            tracing::error!("create_refund fetch: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+        })?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(ApiResponse::error("Запись не найдена"))))?;
+
+    // Telegram Payments charges are recorded for the master to return by hand
+    if !refunds::has_refundable_payment(&booking) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error("По этой записи нет оплаты для возврата")),
+        ));
+    }
+
+    let remaining = refunds::refundable_amount(&state.db, &booking)
+        .await
+        .map_err(|e| {
+            tracing::error!("create_refund remaining: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+        })?;
+    if body.amount <= 0 || body.amount > remaining {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!(
+                "Сумма возврата должна быть от 1 до {} ₽",
+                remaining
+            ))),
+        ));
+    }
+
+    let reason = body.reason.as_deref().unwrap_or("admin");
+    let refund = refunds::issue_refund(
+        &state.db,
+        state.payments.as_ref(),
+        &booking,
+        body.amount,
+        reason,
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!(booking_id = id, error = %e, "create_refund failed");
+        (
+            StatusCode::BAD_GATEWAY,
+            Json(ApiResponse::error("Не удалось создать возврат в ЮKassa")),
+        )
+    })?;
+
+    tracing::info!(booking_id = id, amount = body.amount, "Admin refund issued");
+
+    Ok(Json(ApiResponse::success(refund)))
+}
+
+/// POST /api/admin/refunds/:id/complete — the master returned a Telegram Payments
+/// charge by hand; marks the `pending_manual` refund succeeded.
+pub async fn complete_refund(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<Json<ApiResponse<Refund>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    let refund = refunds::complete_manual_refund(&state.db, id)
+        .await
+        .map_err(|e| {
+            tracing::error!("complete_refund: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("Ручной возврат не найден или уже выполнен")),
+            )
+        })?;
+
+    tracing::info!(refund_id = id, booking_id = refund.booking_id, "Manual refund completed");
+
+    Ok(Json(ApiResponse::success(refund)))
+}
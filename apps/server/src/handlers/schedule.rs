@@ -0,0 +1,704 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    Json,
+};
+use bimbo_lashes_core::{repo, slots, time::moscow_now};
+use chrono::{Datelike, NaiveDate};
+use std::sync::Arc;
+
+use crate::{models::*, AppState};
+
+/// Upper bound for `?weeks=` in the preview endpoint.
+const MAX_PREVIEW_WEEKS: u32 = 12;
+
+/// GET /api/admin/schedule — weekly template + upcoming exceptions.
+pub async fn get_schedule(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+) -> Result<Json<ApiResponse<ScheduleResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    let (days, exceptions) = load_schedule(&state.db).await.map_err(|e| {
+        tracing::error!("get_schedule: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+
+    Ok(Json(ApiResponse::success(ScheduleResponse {
+        days,
+        exceptions,
+        weeks_ahead: state.schedule_weeks_ahead,
+    })))
+}
+
+/// PUT /api/admin/schedule — replace the weekly template (missing weekdays = days off).
+pub async fn update_schedule(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Json(body): Json<UpdateScheduleRequest>,
+) -> Result<Json<ApiResponse<Vec<ScheduleDay>>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    for day in &body.days {
+        if !(0..=6).contains(&day.weekday) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::error("weekday должен быть от 0 (пн) до 6 (вс)")),
+            ));
+        }
+        if !valid_hours(day.start_hour, day.end_hour) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::error("start_hour должен быть меньше end_hour")),
+            ));
+        }
+    }
+
+    let mut tx = state.db.begin().await.map_err(|e| {
+        tracing::error!("update_schedule begin: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+
+    let result: Result<(), sqlx::Error> = async {
+        sqlx::query("DELETE FROM schedule_template")
+            .execute(&mut *tx)
+            .await?;
+        for day in &body.days {
+            sqlx::query(
+                "INSERT OR REPLACE INTO schedule_template (weekday, start_hour, end_hour)
+                 VALUES (?, ?, ?)",
+            )
+            .bind(day.weekday)
+            .bind(day.start_hour)
+            .bind(day.end_hour)
+            .execute(&mut *tx)
+            .await?;
+        }
+        Ok(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        tracing::error!("update_schedule: {}", e);
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error"))));
+    }
+
+    tx.commit().await.map_err(|e| {
+        tracing::error!("update_schedule commit: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+
+    let (days, _) = load_schedule(&state.db).await.map_err(|e| {
+        tracing::error!("update_schedule fetch: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+
+    Ok(Json(ApiResponse::success(days)))
+}
+
+/// POST /api/admin/schedule/exceptions — add or replace an exception for a date.
+///
+/// Free slots outside the new hours are closed right away; bookings there are
+/// returned for the master to deal with.
+pub async fn upsert_exception(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Json(body): Json<ScheduleException>,
+) -> Result<Json<ApiResponse<ScheduleExceptionResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    if NaiveDate::parse_from_str(&body.date, "%Y-%m-%d").is_err() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error("Неверный формат даты")),
+        ));
+    }
+
+    let hours = match (body.start_hour, body.end_hour) {
+        _ if body.is_day_off => None,
+        (Some(start), Some(end)) if valid_hours(start, end) => Some((start, end)),
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::error("Укажите start_hour < end_hour или is_day_off")),
+            ));
+        }
+    };
+
+    let mut tx = state.db.begin().await.map_err(|e| {
+        tracing::error!("upsert_exception begin: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+
+    let result: Result<(), sqlx::Error> = async {
+        sqlx::query(
+            "INSERT OR REPLACE INTO schedule_exceptions (date, is_day_off, start_hour, end_hour, note)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&body.date)
+        .bind(body.is_day_off)
+        .bind(body.start_hour)
+        .bind(body.end_hour)
+        .bind(&body.note)
+        .execute(&mut *tx)
+        .await?;
+        close_free_slots_outside(&mut tx, &body.date, hours).await
+    }
+    .await;
+
+    if let Err(e) = result {
+        tracing::error!("upsert_exception: {}", e);
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error"))));
+    }
+
+    tx.commit().await.map_err(|e| {
+        tracing::error!("upsert_exception commit: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+
+    let bookings = bookings_outside(&state.db, &body.date, hours).await.map_err(|e| {
+        tracing::error!("upsert_exception bookings: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+
+    Ok(Json(ApiResponse::success(ScheduleExceptionResponse {
+        exception: body,
+        bookings,
+    })))
+}
+
+/// DELETE /api/admin/schedule/exceptions/:date — remove an exception.
+pub async fn delete_exception(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(date): Path<String>,
+) -> Result<Json<ApiResponse<&'static str>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    let deleted = sqlx::query("DELETE FROM schedule_exceptions WHERE date = ?")
+        .bind(&date)
+        .execute(&state.db)
+        .await
+        .map_err(|e| {
+            tracing::error!("delete_exception: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+        })?
+        .rows_affected();
+
+    if deleted == 0 {
+        return Err((StatusCode::NOT_FOUND, Json(ApiResponse::error("Исключение не найдено"))));
+    }
+
+    Ok(Json(ApiResponse::success("Исключение удалено")))
+}
+
+/// GET /api/admin/schedule/preview?weeks=N — slots the template would generate (dry run).
+pub async fn preview_schedule(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Query(query): Query<SchedulePreviewQuery>,
+) -> Result<Json<ApiResponse<Vec<SchedulePreviewDay>>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    let weeks = query
+        .weeks
+        .unwrap_or(state.schedule_weeks_ahead)
+        .clamp(1, MAX_PREVIEW_WEEKS);
+
+    let (days, exceptions) = load_schedule(&state.db).await.map_err(|e| {
+        tracing::error!("preview_schedule: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+
+    let today = moscow_now().date_naive();
+    let mut preview = Vec::new();
+
+    for (date, start_h, end_h) in working_days(today, weeks, &days, &exceptions) {
+        let existing = sqlx::query_as::<_, (String, String)>(
+            "SELECT start_time, end_time FROM available_slots WHERE date = ?",
+        )
+        .bind(&date)
+        .fetch_all(&state.db)
+        .await
+        .map_err(|e| {
+            tracing::error!("preview_schedule slots: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+        })?;
+
+        let slots: Vec<TimeBlock> =
+            slots::day_slot_times(start_h, end_h, state.slot_granularity_min)
+                .into_iter()
+                .map(|(start_time, end_time)| TimeBlock {
+                    start_time,
+                    end_time,
+                })
+                .collect();
+        // Same rules as materialize_day: an opened day stays as the master left it
+        let opened: bool = sqlx::query_scalar(
+            "SELECT COUNT(*) > 0 FROM materialized_days
+             WHERE date = ? AND start_hour = ? AND end_hour = ?",
+        )
+        .bind(&date)
+        .bind(start_h)
+        .bind(end_h)
+        .fetch_one(&state.db)
+        .await
+        .map_err(|e| {
+            tracing::error!("preview_schedule materialized: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+        })?;
+        let new_slots = slots
+            .iter()
+            .filter(|_| !opened)
+            .filter(|s| {
+                !existing
+                    .iter()
+                    .any(|(start, end)| *start < s.end_time && *end > s.start_time)
+            })
+            .count() as i64;
+
+        preview.push(SchedulePreviewDay {
+            date,
+            start_hour: start_h as i64,
+            end_hour: end_h as i64,
+            slots,
+            new_slots,
+        });
+    }
+
+    Ok(Json(ApiResponse::success(preview)))
+}
+
+// ── Background materialization ──
+
+/// Open slots for every working day in the next `weeks` weeks (idempotent).
+///
+/// Called periodically from `main.rs`; existing slots are left untouched, and a day
+/// already opened with the same hours is skipped.
+pub async fn materialize_schedule(db: &sqlx::SqlitePool, weeks: u32, granularity_min: u32) {
+    let (days, exceptions) = match load_schedule(db).await {
+        Ok(schedule) => schedule,
+        Err(e) => {
+            tracing::error!("materialize_schedule load failed: {}", e);
+            return;
+        }
+    };
+
+    let today = moscow_now().date_naive();
+    let mut added = 0;
+
+    for (date, start_h, end_h) in working_days(today, weeks, &days, &exceptions) {
+        match materialize_day(db, &date, start_h, end_h, granularity_min).await {
+            Ok(n) => added += n,
+            Err(e) => tracing::error!(date = %date, error = %e, "Failed to materialize day"),
+        }
+    }
+
+    if added > 0 {
+        tracing::info!(added, weeks, "Materialized schedule slots");
+    }
+}
+
+/// Open a working day unless it was already opened with these hours.
+///
+/// Recorded in `materialized_days`, so slots the master deletes afterwards stay deleted.
+async fn materialize_day(
+    db: &sqlx::SqlitePool,
+    date: &str,
+    start_h: u32,
+    end_h: u32,
+    granularity_min: u32,
+) -> Result<u32, sqlx::Error> {
+    let done: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) > 0 FROM materialized_days WHERE date = ? AND start_hour = ? AND end_hour = ?",
+    )
+    .bind(date)
+    .bind(start_h)
+    .bind(end_h)
+    .fetch_one(db)
+    .await?;
+    if done {
+        return Ok(0);
+    }
+
+    let added = slots::open_day_slots(db, date, start_h, end_h, granularity_min).await?;
+    sqlx::query(
+        "INSERT OR REPLACE INTO materialized_days (date, start_hour, end_hour) VALUES (?, ?, ?)",
+    )
+    .bind(date)
+    .bind(start_h)
+    .bind(end_h)
+    .execute(db)
+    .await?;
+    Ok(added)
+}
+
+// ── Helpers ──
+
+/// `(start, end)` of working hours as slot times; `None` (day off) maps to an empty range.
+fn hour_bounds(hours: Option<(i64, i64)>) -> (String, String) {
+    let (start, end) = hours.unwrap_or((0, 0));
+    (format!("{:02}:00", start), format!("{:02}:00", end))
+}
+
+/// Remove the free schedule slots on `date` outside `hours` (`None` = day off).
+///
+/// Slots a cancelled booking still references can't be deleted; they are marked
+/// ad hoc, which keeps free ones out of every listing.
+async fn close_free_slots_outside(
+    conn: &mut sqlx::SqliteConnection,
+    date: &str,
+    hours: Option<(i64, i64)>,
+) -> Result<(), sqlx::Error> {
+    let (start, end) = hour_bounds(hours);
+    sqlx::query(
+        "DELETE FROM available_slots
+         WHERE date = ? AND is_booked = 0 AND ad_hoc = 0 AND (start_time < ? OR end_time > ?)
+         AND id NOT IN (SELECT slot_id FROM bookings)",
+    )
+    .bind(date)
+    .bind(&start)
+    .bind(&end)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        "UPDATE available_slots SET ad_hoc = 1
+         WHERE date = ? AND is_booked = 0 AND ad_hoc = 0 AND (start_time < ? OR end_time > ?)",
+    )
+    .bind(date)
+    .bind(&start)
+    .bind(&end)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Active bookings on `date` starting or ending outside `hours` (`None` = day off).
+async fn bookings_outside(
+    db: &sqlx::SqlitePool,
+    date: &str,
+    hours: Option<(i64, i64)>,
+) -> Result<Vec<BookingDetail>, sqlx::Error> {
+    let (start, end) = hour_bounds(hours);
+    let sql = format!(
+        "{} WHERE COALESCE(b.date, sl.date) = ?
+         AND b.status IN ('confirmed', 'pending_payment', 'awaiting_approval')
+         AND (COALESCE(b.start_time, sl.start_time) < ? OR COALESCE(b.end_time, sl.end_time) > ?)
+         ORDER BY COALESCE(b.start_time, sl.start_time) ASC",
+        repo::BOOKING_DETAIL_SELECT
+    );
+    sqlx::query_as::<_, BookingDetail>(&sql)
+        .bind(date)
+        .bind(&start)
+        .bind(&end)
+        .fetch_all(db)
+        .await
+}
+
+/// Load the weekly template and all exceptions from today onwards.
+async fn load_schedule(
+    db: &sqlx::SqlitePool,
+) -> Result<(Vec<ScheduleDay>, Vec<ScheduleException>), sqlx::Error> {
+    let days = sqlx::query_as::<_, ScheduleDay>(
+        "SELECT weekday, start_hour, end_hour FROM schedule_template ORDER BY weekday ASC",
+    )
+    .fetch_all(db)
+    .await?;
+
+    let exceptions = sqlx::query_as::<_, ScheduleException>(
+        "SELECT date, is_day_off, start_hour, end_hour, note FROM schedule_exceptions
+         WHERE date >= date('now', '+3 hours')
+         ORDER BY date ASC",
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok((days, exceptions))
+}
+
+fn valid_hours(start_hour: i64, end_hour: i64) -> bool {
+    (0..24).contains(&start_hour) && start_hour < end_hour && end_hour <= 24
+}
+
+/// Working hours for a date: exception first, then the weekday template.
+fn hours_for_date(
+    date: NaiveDate,
+    days: &[ScheduleDay],
+    exceptions: &[ScheduleException],
+) -> Option<(u32, u32)> {
+    let date_str = date.format("%Y-%m-%d").to_string();
+
+    if let Some(ex) = exceptions.iter().find(|e| e.date == date_str) {
+        if ex.is_day_off {
+            return None;
+        }
+        return match (ex.start_hour, ex.end_hour) {
+            (Some(start), Some(end)) if valid_hours(start, end) => Some((start as u32, end as u32)),
+            _ => None,
+        };
+    }
+
+    let weekday = date.weekday().num_days_from_monday() as i64;
+    days.iter()
+        .find(|d| d.weekday == weekday)
+        .filter(|d| valid_hours(d.start_hour, d.end_hour))
+        .map(|d| (d.start_hour as u32, d.end_hour as u32))
+}
+
+/// All working days in `[today, today + weeks)` as `(date, start_hour, end_hour)`.
+fn working_days(
+    today: NaiveDate,
+    weeks: u32,
+    days: &[ScheduleDay],
+    exceptions: &[ScheduleException],
+) -> Vec<(String, u32, u32)> {
+    today
+        .iter_days()
+        .take(weeks as usize * 7)
+        .filter_map(|date| {
+            hours_for_date(date, days, exceptions)
+                .map(|(start, end)| (date.format("%Y-%m-%d").to_string(), start, end))
+        })
+        .collect()
+}
+
+// ── Tests ──
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bimbo_lashes_core::testing;
+
+    fn day(weekday: i64, start: i64, end: i64) -> ScheduleDay {
+        ScheduleDay {
+            weekday,
+            start_hour: start,
+            end_hour: end,
+        }
+    }
+
+    fn day_off(date: &str) -> ScheduleException {
+        ScheduleException {
+            date: date.to_string(),
+            is_day_off: true,
+            start_hour: None,
+            end_hour: None,
+            note: String::new(),
+        }
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    // ── valid_hours ──
+
+    #[test]
+    fn test_valid_hours_basic() {
+        assert!(valid_hours(12, 20));
+    }
+
+    #[test]
+    fn test_valid_hours_full_day() {
+        assert!(valid_hours(0, 24));
+    }
+
+    #[test]
+    fn test_valid_hours_inverted() {
+        assert!(!valid_hours(20, 12));
+    }
+
+    #[test]
+    fn test_valid_hours_past_midnight() {
+        assert!(!valid_hours(12, 25));
+    }
+
+    // ── hours_for_date ──
+
+    #[test]
+    fn test_hours_from_template() {
+        // 2026-03-02 is a Monday
+        let days = vec![day(0, 10, 18)];
+        assert_eq!(hours_for_date(date("2026-03-02"), &days, &[]), Some((10, 18)));
+    }
+
+    #[test]
+    fn test_hours_weekday_not_in_template() {
+        // 2026-03-03 is a Tuesday
+        let days = vec![day(0, 10, 18)];
+        assert_eq!(hours_for_date(date("2026-03-03"), &days, &[]), None);
+    }
+
+    #[test]
+    fn test_hours_exception_day_off() {
+        let days = vec![day(0, 10, 18)];
+        let exceptions = vec![day_off("2026-03-02")];
+        assert_eq!(hours_for_date(date("2026-03-02"), &days, &exceptions), None);
+    }
+
+    #[test]
+    fn test_hours_exception_custom_hours() {
+        // Exception opens a Sunday that the template keeps closed
+        let exceptions = vec![ScheduleException {
+            date: "2026-03-08".into(),
+            is_day_off: false,
+            start_hour: Some(11),
+            end_hour: Some(15),
+            note: "Праздник".into(),
+        }];
+        assert_eq!(hours_for_date(date("2026-03-08"), &[], &exceptions), Some((11, 15)));
+    }
+
+    #[test]
+    fn test_hours_exception_without_hours_is_closed() {
+        let exceptions = vec![ScheduleException {
+            date: "2026-03-02".into(),
+            is_day_off: false,
+            start_hour: None,
+            end_hour: None,
+            note: String::new(),
+        }];
+        let days = vec![day(0, 10, 18)];
+        assert_eq!(hours_for_date(date("2026-03-02"), &days, &exceptions), None);
+    }
+
+    // ── working_days ──
+
+    #[test]
+    fn test_working_days_one_week() {
+        // Mon + Wed + Fri
+        let days = vec![day(0, 12, 20), day(2, 12, 20), day(4, 10, 16)];
+        let result = working_days(date("2026-03-02"), 1, &days, &[]);
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[0], ("2026-03-02".to_string(), 12, 20));
+        assert_eq!(result[1].0, "2026-03-04");
+        assert_eq!(result[2], ("2026-03-06".to_string(), 10, 16));
+    }
+
+    #[test]
+    fn test_working_days_respects_exceptions() {
+        let days = vec![day(0, 12, 20)];
+        let exceptions = vec![day_off("2026-03-09")];
+        let result = working_days(date("2026-03-02"), 3, &days, &exceptions);
+        let dates: Vec<&str> = result.iter().map(|(d, _, _)| d.as_str()).collect();
+        assert_eq!(dates, vec!["2026-03-02", "2026-03-16"]);
+    }
+
+    #[test]
+    fn test_working_days_empty_template() {
+        assert!(working_days(date("2026-03-02"), 4, &[], &[]).is_empty());
+    }
+
+    // ── materialization and exceptions ──
+
+    async fn free_slot_times(db: &sqlx::SqlitePool, date: &str) -> Vec<String> {
+        sqlx::query_scalar(
+            "SELECT start_time FROM available_slots
+             WHERE date = ? AND is_booked = 0 AND ad_hoc = 0 ORDER BY start_time",
+        )
+        .bind(date)
+        .fetch_all(db)
+        .await
+        .unwrap()
+    }
+
+    async fn slot_count(db: &sqlx::SqlitePool) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM available_slots")
+            .fetch_one(db)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_materialize_keeps_deleted_slots_deleted() {
+        let db = testing::memory_db().await;
+        for weekday in 0..7 {
+            sqlx::query("INSERT INTO schedule_template (weekday, start_hour, end_hour) VALUES (?, 10, 12)")
+                .bind(weekday)
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+
+        materialize_schedule(&db, 1, 60).await;
+        assert_eq!(slot_count(&db).await, 14);
+
+        // The master deletes a slot; the next run doesn't bring it back
+        sqlx::query("DELETE FROM available_slots WHERE id = (SELECT MIN(id) FROM available_slots)")
+            .execute(&db)
+            .await
+            .unwrap();
+        materialize_schedule(&db, 1, 60).await;
+        assert_eq!(slot_count(&db).await, 13);
+
+        // New hours open the days again
+        sqlx::query("UPDATE schedule_template SET end_hour = 13")
+            .execute(&db)
+            .await
+            .unwrap();
+        materialize_schedule(&db, 1, 60).await;
+        assert_eq!(slot_count(&db).await, 21);
+    }
+
+    #[tokio::test]
+    async fn test_exception_hours_without_template() {
+        let db = testing::memory_db().await;
+        let tomorrow = (moscow_now().date_naive() + chrono::Duration::days(1))
+            .format("%Y-%m-%d")
+            .to_string();
+        sqlx::query(
+            "INSERT INTO schedule_exceptions (date, is_day_off, start_hour, end_hour)
+             VALUES (?, 0, 11, 13)",
+        )
+        .bind(&tomorrow)
+        .execute(&db)
+        .await
+        .unwrap();
+
+        materialize_schedule(&db, 1, 60).await;
+        assert_eq!(free_slot_times(&db, &tomorrow).await, vec!["11:00", "12:00"]);
+    }
+
+    #[tokio::test]
+    async fn test_exception_closes_free_slots_outside_hours() {
+        let db = testing::memory_db().await;
+        let date = "2099-01-01";
+        // A cancelled booking still references the 10:00 slot
+        testing::paid_booking(&db, date, "10:00", "11:00", 500).await;
+        sqlx::query("UPDATE bookings SET status = 'cancelled'")
+            .execute(&db)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE available_slots SET is_booked = 0, booking_id = NULL")
+            .execute(&db)
+            .await
+            .unwrap();
+        testing::open_slots(
+            &db,
+            date,
+            &[("11:00", "12:00"), ("12:00", "13:00"), ("13:00", "14:00"), ("14:00", "15:00")],
+        )
+        .await;
+        let booked = testing::paid_booking(&db, date, "15:00", "16:00", 500).await;
+
+        let mut conn = db.acquire().await.unwrap();
+        close_free_slots_outside(&mut conn, date, Some((11, 14))).await.unwrap();
+        drop(conn);
+        assert_eq!(free_slot_times(&db, date).await, vec!["11:00", "12:00", "13:00"]);
+        let outside = bookings_outside(&db, date, Some((11, 14))).await.unwrap();
+        assert_eq!(outside.iter().map(|b| b.id).collect::<Vec<_>>(), vec![booked.id]);
+        assert!(bookings_outside(&db, date, Some((11, 16))).await.unwrap().is_empty());
+
+        let mut conn = db.acquire().await.unwrap();
+        close_free_slots_outside(&mut conn, date, None).await.unwrap();
+        drop(conn);
+        assert!(free_slot_times(&db, date).await.is_empty());
+        assert_eq!(bookings_outside(&db, date, None).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,88 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    Json,
+};
+use bimbo_lashes_core::{
+    repo,
+    standing::{Restriction, Rule},
+};
+use std::sync::Arc;
+
+use crate::{models::*, AppState};
+
+/// What a client under [`Restriction::Blocked`] is told when booking online.
+pub const BLOCKED_ONLINE: &str = "Онлайн-запись недоступна. Напиши мастеру — она запишет тебя сама";
+
+/// Restrictions for clients who skip or cancel late (env CLIENT_RESTRICTIONS /
+/// LATE_CANCEL_HOURS), enforced when a client books online.
+#[derive(Debug, Clone)]
+pub struct RestrictionPolicy {
+    pub rules: Vec<Rule>,
+    /// A client cancellation closer to the appointment than this counts as late.
+    pub late_cancel_hours: i64,
+}
+
+/// GET /api/admin/clients/:tg_id/standing — no-shows, late cancellations and the resulting restriction.
+pub async fn get_standing(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(tg_id): Path<i64>,
+) -> Result<Json<ApiResponse<ClientStanding>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    let standing = load_standing(&state, tg_id).await.map_err(|e| {
+        tracing::error!("get_standing: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+    Ok(Json(ApiResponse::success(standing)))
+}
+
+/// DELETE /api/admin/clients/:tg_id/standing — forgive past no-shows and late cancellations.
+pub async fn reset_standing(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(tg_id): Path<i64>,
+) -> Result<Json<ApiResponse<ClientStanding>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("reset_standing: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    };
+    repo::reset_client_standing(&state.db, tg_id)
+        .await
+        .map_err(db_error)?;
+    let standing = load_standing(&state, tg_id).await.map_err(db_error)?;
+    Ok(Json(ApiResponse::success(standing)))
+}
+
+/// What the client may do online under the configured rules.
+pub async fn client_restriction(state: &AppState, tg_id: i64) -> Result<Restriction, sqlx::Error> {
+    if state.restrictions.rules.is_empty() {
+        return Ok(Restriction::None);
+    }
+    let standing =
+        repo::client_standing(&state.db, tg_id, state.restrictions.late_cancel_hours).await?;
+    Ok(standing.restriction(&state.restrictions.rules))
+}
+
+/// The client's counters, restriction and last reset.
+pub async fn load_standing(state: &AppState, tg_id: i64) -> Result<ClientStanding, sqlx::Error> {
+    let standing =
+        repo::client_standing(&state.db, tg_id, state.restrictions.late_cancel_hours).await?;
+    let reset_at: Option<String> =
+        sqlx::query_scalar("SELECT reset_at FROM client_standing_resets WHERE client_tg_id = ?")
+            .bind(tg_id)
+            .fetch_optional(&state.db)
+            .await?;
+    Ok(ClientStanding {
+        client_tg_id: tg_id,
+        no_shows: standing.no_shows,
+        late_cancellations: standing.late_cancellations,
+        restriction: standing.restriction(&state.restrictions.rules),
+        reset_at,
+    })
+}
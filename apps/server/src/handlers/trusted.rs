@@ -0,0 +1,93 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    Json,
+};
+use std::sync::Arc;
+
+use crate::{models::*, AppState};
+
+/// GET /api/admin/trusted-clients — clients who book without prepayment.
+pub async fn list_trusted_clients(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+) -> Result<Json<ApiResponse<Vec<TrustedClient>>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    let clients = sqlx::query_as::<_, TrustedClient>(
+        "SELECT t.client_tg_id, t.note, t.created_at,
+                (SELECT b.client_first_name FROM bookings b
+                 WHERE b.client_tg_id = t.client_tg_id
+                 ORDER BY b.id DESC LIMIT 1) AS client_first_name
+         FROM trusted_clients t ORDER BY t.created_at DESC",
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("list_trusted_clients: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+
+    Ok(Json(ApiResponse::success(clients)))
+}
+
+/// PUT /api/admin/trusted-clients/:tg_id — let a client book without prepayment.
+pub async fn trust_client(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(tg_id): Path<i64>,
+    Json(body): Json<TrustClientRequest>,
+) -> Result<Json<ApiResponse<String>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    sqlx::query(
+        "INSERT INTO trusted_clients (client_tg_id, note) VALUES (?, ?)
+         ON CONFLICT(client_tg_id) DO UPDATE SET note = excluded.note",
+    )
+    .bind(tg_id)
+    .bind(body.note.as_deref().unwrap_or(""))
+    .execute(&state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("trust_client: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+
+    Ok(Json(ApiResponse::success("Клиент добавлен в доверенные".into())))
+}
+
+/// DELETE /api/admin/trusted-clients/:tg_id — require prepayment from the client again.
+pub async fn untrust_client(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(tg_id): Path<i64>,
+) -> Result<Json<ApiResponse<String>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    let removed = sqlx::query("DELETE FROM trusted_clients WHERE client_tg_id = ?")
+        .bind(tg_id)
+        .execute(&state.db)
+        .await
+        .map_err(|e| {
+            tracing::error!("untrust_client: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+        })?
+        .rows_affected();
+
+    if removed == 0 {
+        return Err((StatusCode::NOT_FOUND, Json(ApiResponse::error("Клиент не найден"))));
+    }
+
+    Ok(Json(ApiResponse::success("Клиент удалён из доверенных".into())))
+}
+
+/// Whether the client may book without prepayment.
+pub async fn is_trusted(db: &sqlx::SqlitePool, tg_id: i64) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar("SELECT COUNT(*) > 0 FROM trusted_clients WHERE client_tg_id = ?")
+        .bind(tg_id)
+        .fetch_one(db)
+        .await
+}
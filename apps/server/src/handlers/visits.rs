@@ -0,0 +1,181 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    Json,
+};
+use bimbo_lashes_core::{
+    repo,
+    time::moscow_now,
+    visits::{self, Outcome},
+};
+use chrono::Timelike;
+use std::sync::Arc;
+
+use crate::{models::*, AppState};
+
+/// Morning hour (Moscow) after which the master is reminded of yesterday's open visits.
+const NUDGE_HOUR: u32 = 10;
+
+/// POST /api/admin/bookings/:id/outcome — close a past visit as completed or no-show.
+///
+/// `amount_collected` defaults to what was left to pay (0 for a no-show). Sending it
+/// again for a completed visit corrects the amount.
+pub async fn close_visit(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(id): Path<i64>,
+    Json(body): Json<CloseVisitRequest>,
+) -> Result<Json<ApiResponse<BookingDetail>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("close_visit: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    };
+
+    if body.amount_collected.is_some_and(|a| a < 0) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error("Сумма не может быть отрицательной")),
+        ));
+    }
+
+    let detail = repo::booking_detail(&state.db, id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(ApiResponse::error("Запись не найдена"))))?;
+
+    let closed = match (detail.status.as_str(), body.outcome, body.amount_collected) {
+        ("completed", Outcome::Completed, Some(amount)) => {
+            repo::set_amount_collected(&state.db, id, amount).await.map_err(db_error)?
+        }
+        ("confirmed", outcome, amount) => {
+            let amount = amount.unwrap_or(match outcome {
+                Outcome::Completed => detail.amount_to_collect(),
+                Outcome::NoShow => 0,
+            });
+            if !repo::close_visit(&state.db, id, outcome, amount).await.map_err(db_error)? {
+                return Err((
+                    StatusCode::CONFLICT,
+                    Json(ApiResponse::error("Визит ещё не начался")),
+                ));
+            }
+            true
+        }
+        _ => false,
+    };
+    if !closed {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error("Визит уже закрыт или запись не подтверждена")),
+        ));
+    }
+
+    let detail = repo::booking_detail(&state.db, id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(ApiResponse::error("Запись не найдена"))))?;
+    Ok(Json(ApiResponse::success(detail)))
+}
+
+/// Ask the master how visits went: right after today's appointments end, and
+/// once more the next morning for those still open. Called periodically from `main.rs`.
+pub async fn ask_visit_outcomes(state: &AppState) {
+    let now = moscow_now();
+    let today = now.format("%Y-%m-%d").to_string();
+
+    let finished = open_visits(
+        &state.db,
+        &format!(
+            "b.date = '{}' AND b.end_time <= '{}' AND b.outcome_prompts = 0",
+            today,
+            now.format("%H:%M")
+        ),
+    )
+    .await;
+    for visit in finished {
+        let text = format!(
+            "🕐 Как прошёл визит?\n\n👤 {}\n💅 {}\n📅 {} в {}\n💵 Получить на месте: {} ₽",
+            visit.client_mention(),
+            visit.service_name,
+            visit.date,
+            visit.start_time,
+            visit.amount_to_collect()
+        );
+        let keyboard = visits::keyboard_json(&[(visit.id, "Пришла".into())]);
+        super::client::send_message(&state.bot_token, state.admin_tg_id, &text, Some(keyboard))
+            .await;
+        mark_prompted(&state.db, &[visit.id], 1).await;
+    }
+
+    if now.hour() < NUDGE_HOUR {
+        return;
+    }
+    let yesterday = (now - chrono::TimeDelta::days(1)).format("%Y-%m-%d").to_string();
+    let unresolved = open_visits(
+        &state.db,
+        &format!("b.date = '{}' AND b.outcome_prompts < 2", yesterday),
+    )
+    .await;
+    if unresolved.is_empty() {
+        return;
+    }
+
+    let mut text = format!(
+        "📝 Вчера ({}) остались незакрытые визиты: {}\nОтметь, кто пришёл:\n",
+        yesterday,
+        unresolved.len()
+    );
+    for visit in &unresolved {
+        text.push_str(&format!(
+            "\n• {} {} · {} · {} ₽",
+            visit.start_time,
+            visit.client_mention(),
+            visit.service_name,
+            visit.amount_to_collect()
+        ));
+    }
+    let rows: Vec<(i64, String)> = unresolved
+        .iter()
+        .map(|v| (v.id, format!("{} {}", v.start_time, v.client_first_name)))
+        .collect();
+    super::client::send_message(
+        &state.bot_token,
+        state.admin_tg_id,
+        &text,
+        Some(visits::keyboard_json(&rows)),
+    )
+    .await;
+    let ids: Vec<i64> = unresolved.iter().map(|v| v.id).collect();
+    mark_prompted(&state.db, &ids, 2).await;
+}
+
+/// Confirmed bookings matching `filter` (SQL over `b`), in time order.
+async fn open_visits(db: &sqlx::SqlitePool, filter: &str) -> Vec<BookingDetail> {
+    let sql = format!(
+        "{} WHERE b.status = 'confirmed' AND {} ORDER BY b.start_time ASC",
+        repo::BOOKING_DETAIL_SELECT,
+        filter
+    );
+    sqlx::query_as::<_, BookingDetail>(&sql)
+        .fetch_all(db)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("open_visits query failed: {}", e);
+            vec![]
+        })
+}
+
+async fn mark_prompted(db: &sqlx::SqlitePool, ids: &[i64], prompts: i64) {
+    for id in ids {
+        if let Err(e) = sqlx::query("UPDATE bookings SET outcome_prompts = ? WHERE id = ?")
+            .bind(prompts)
+            .bind(id)
+            .execute(db)
+            .await
+        {
+            tracing::error!(booking_id = id, error = %e, "Failed to record visit prompt");
+        }
+    }
+}
@@ -0,0 +1,573 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    Json,
+};
+use bimbo_lashes_core::time::{moscow_now, moscow_today};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+use crate::{models::*, AppState};
+
+/// Longest date range one waitlist entry may cover, in days.
+const MAX_RANGE_DAYS: i64 = 31;
+
+/// Waiting or offered entries one client may have at a time.
+const MAX_ACTIVE_ENTRIES: i64 = 5;
+
+const ENTRY_SELECT: &str =
+    "SELECT w.id, w.client_tg_id, w.client_first_name, w.service_id, s.name AS service_name,
+            w.date_from, w.date_to, w.status, w.offered_date, w.offered_time, w.hold_until,
+            w.created_at
+     FROM waitlist w JOIN services s ON s.id = w.service_id";
+
+/// Waitlist offers (env WAITLIST_HOLD_MIN) and the signal that slots were freed.
+pub struct Waitlist {
+    /// How long an offered block is kept for the client.
+    pub hold_min: i64,
+    freed: Notify,
+}
+
+impl Waitlist {
+    pub fn new(hold_min: i64) -> Self {
+        Self {
+            hold_min,
+            freed: Notify::new(),
+        }
+    }
+
+    /// Slots were freed or opened: offer them now instead of on the next tick.
+    pub fn wake(&self) {
+        self.freed.notify_one();
+    }
+
+    pub async fn woken(&self) {
+        self.freed.notified().await;
+    }
+}
+
+/// POST /api/waitlist — wait for a service on a date or date range.
+pub async fn join_waitlist(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Json(body): Json<JoinWaitlistRequest>,
+) -> Result<Json<ApiResponse<WaitlistEntry>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    let user = super::client::extract_user(auth_header, &state.bot_token)?;
+
+    let bad_request = |msg: &str| (StatusCode::BAD_REQUEST, Json(ApiResponse::error(msg)));
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("join_waitlist: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    };
+
+    let date_to = body.date_to.as_deref().unwrap_or(&body.date_from);
+    let parse = |d: &str| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d");
+    let (Ok(from), Ok(to)) = (parse(&body.date_from), parse(date_to)) else {
+        return Err(bad_request("Неверный формат даты"));
+    };
+    if body.date_from < moscow_today() {
+        return Err(bad_request("Нельзя встать в очередь на прошедшую дату"));
+    }
+    if to < from || (to - from).num_days() >= MAX_RANGE_DAYS {
+        return Err(bad_request("Выбери период не длиннее месяца"));
+    }
+
+    if super::blocklist::is_blocked(&state.db, &user, None)
+        .await
+        .map_err(db_error)?
+    {
+        return Err((StatusCode::FORBIDDEN, Json(ApiResponse::error(BLOCKED_NOTICE))));
+    }
+    if super::standing::client_restriction(&state, user.id)
+        .await
+        .map_err(db_error)?
+        == Restriction::Blocked
+    {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ApiResponse::error(super::standing::BLOCKED_ONLINE)),
+        ));
+    }
+
+    let service_exists: bool =
+        sqlx::query_scalar("SELECT COUNT(*) > 0 FROM services WHERE id = ? AND is_active = 1")
+            .bind(body.service_id)
+            .fetch_one(&state.db)
+            .await
+            .map_err(db_error)?;
+    if !service_exists {
+        return Err((StatusCode::NOT_FOUND, Json(ApiResponse::error("Услуга не найдена"))));
+    }
+
+    let active: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM waitlist
+         WHERE client_tg_id = ? AND status IN ('waiting', 'offered')",
+    )
+    .bind(user.id)
+    .fetch_one(&state.db)
+    .await
+    .map_err(db_error)?;
+    if active >= MAX_ACTIVE_ENTRIES {
+        return Err(bad_request("Слишком много заявок в листе ожидания"));
+    }
+
+    let id = sqlx::query(
+        "INSERT INTO waitlist (client_tg_id, client_first_name, service_id, date_from, date_to)
+         VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(user.id)
+    .bind(&user.first_name)
+    .bind(body.service_id)
+    .bind(&body.date_from)
+    .bind(date_to)
+    .execute(&state.db)
+    .await
+    .map_err(db_error)?
+    .last_insert_rowid();
+
+    let entry = sqlx::query_as::<_, WaitlistEntry>(&format!("{} WHERE w.id = ?", ENTRY_SELECT))
+        .bind(id)
+        .fetch_one(&state.db)
+        .await
+        .map_err(db_error)?;
+
+    // Something may already be free in the range
+    state.waitlist.wake();
+
+    Ok(Json(ApiResponse::success(entry)))
+}
+
+/// GET /api/waitlist/my — the client's waiting and offered entries.
+pub async fn my_waitlist(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+) -> Result<Json<ApiResponse<Vec<WaitlistEntry>>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    let user = super::client::extract_user(auth_header, &state.bot_token)?;
+
+    let entries = sqlx::query_as::<_, WaitlistEntry>(&format!(
+        "{} WHERE w.client_tg_id = ? AND w.status IN ('waiting', 'offered')
+         ORDER BY w.date_from ASC, w.id ASC",
+        ENTRY_SELECT
+    ))
+    .bind(user.id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("my_waitlist: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+
+    Ok(Json(ApiResponse::success(entries)))
+}
+
+/// DELETE /api/waitlist/:id — leave the waitlist; an offered block goes to the next client.
+pub async fn leave_waitlist(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<Json<ApiResponse<String>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    let user = super::client::extract_user(auth_header, &state.bot_token)?;
+
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("leave_waitlist: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    };
+
+    // Only offered entries have a date; theirs is free again
+    let left: Option<Option<String>> = sqlx::query_scalar(
+        "UPDATE waitlist SET status = 'cancelled'
+         WHERE id = ? AND client_tg_id = ? AND status IN ('waiting', 'offered')
+         RETURNING offered_date",
+    )
+    .bind(id)
+    .bind(user.id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error)?;
+    match left {
+        None => {
+            return Err((StatusCode::NOT_FOUND, Json(ApiResponse::error("Заявка не найдена"))));
+        }
+        Some(Some(_)) => state.waitlist.wake(),
+        Some(None) => {}
+    }
+
+    Ok(Json(ApiResponse::success("Ты больше не в листе ожидания".into())))
+}
+
+/// GET /api/admin/waitlist — everyone waiting or holding an offer, in queue order.
+pub async fn list_waitlist(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+) -> Result<Json<ApiResponse<Vec<WaitlistEntry>>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth_header = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    super::admin::extract_admin(auth_header, &state)?;
+
+    let entries = sqlx::query_as::<_, WaitlistEntry>(&format!(
+        "{} WHERE w.status IN ('waiting', 'offered') ORDER BY w.created_at ASC, w.id ASC",
+        ENTRY_SELECT
+    ))
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("list_waitlist: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error("DB error")))
+    })?;
+
+    Ok(Json(ApiResponse::success(entries)))
+}
+
+/// The client booked: close their matching entries, which also ends their offer's hold.
+pub async fn booked(db: &sqlx::SqlitePool, client_tg_id: i64, service_id: i64, date: &str) {
+    let result = sqlx::query(
+        "UPDATE waitlist SET status = 'booked'
+         WHERE client_tg_id = ? AND service_id = ? AND ? BETWEEN date_from AND date_to
+         AND status IN ('waiting', 'offered')",
+    )
+    .bind(client_tg_id)
+    .bind(service_id)
+    .bind(date)
+    .execute(db)
+    .await;
+    if let Err(e) = result {
+        tracing::error!(client_tg_id, error = %e, "Failed to close waitlist entries");
+    }
+}
+
+/// Offer free blocks to waiting clients, oldest entry first, one offer per client at a time.
+///
+/// Each offer holds the block for `hold_min` minutes; unanswered offers expire and the
+/// block goes to the next client. Runs periodically and whenever slots are freed.
+pub async fn offer_free_slots(state: &AppState) {
+    let offers = match claim_offers(
+        &state.db,
+        state.slot_granularity_min,
+        state.waitlist.hold_min,
+        &moscow_today(),
+        &moscow_now().format("%H:%M").to_string(),
+    )
+    .await
+    {
+        Ok(offers) => offers,
+        Err(e) => {
+            tracing::error!("offer_free_slots failed: {}", e);
+            return;
+        }
+    };
+
+    for (entry, block) in offers {
+        tracing::info!(entry = entry.id, date = %block.date, time = %block.time.start_time, "Offered a freed slot");
+        send_offer(state, &entry, &block).await;
+    }
+}
+
+// ── Private helpers ──
+
+/// A block to offer and the slots held for it, buffers included.
+struct FreeBlock {
+    date: String,
+    time: TimeBlock,
+    hold_start: String,
+    hold_end: String,
+}
+
+/// Expire stale offers, then hold a free block for each waiting client in queue order;
+/// returns the offers to send. `today` and `now` are the Moscow date and `HH:MM`.
+async fn claim_offers(
+    db: &sqlx::SqlitePool,
+    granularity_min: u32,
+    hold_min: i64,
+    today: &str,
+    now: &str,
+) -> Result<Vec<(WaitlistEntry, FreeBlock)>, sqlx::Error> {
+    expire_offers(db).await?;
+
+    let entries = sqlx::query_as::<_, WaitlistEntry>(&format!(
+        "{} WHERE w.status IN ('waiting', 'offered') ORDER BY w.created_at ASC, w.id ASC",
+        ENTRY_SELECT
+    ))
+    .fetch_all(db)
+    .await?;
+
+    let mut busy: HashSet<i64> = entries
+        .iter()
+        .filter(|e| e.status == "offered")
+        .map(|e| e.client_tg_id)
+        .collect();
+    let mut offers = Vec::new();
+    for entry in entries.into_iter().filter(|e| e.status == "waiting") {
+        if busy.contains(&entry.client_tg_id) {
+            continue;
+        }
+        let held = match next_free_block(db, granularity_min, &entry, today, now).await {
+            Ok(Some(block)) => hold(db, hold_min, &entry, &block).await.map(|ok| ok.then_some(block)),
+            Ok(None) => Ok(None),
+            Err(e) => Err(e),
+        };
+        match held {
+            Ok(Some(block)) => {
+                busy.insert(entry.client_tg_id);
+                offers.push((entry, block));
+            }
+            Ok(None) => {}
+            Err(e) => tracing::error!(entry = entry.id, error = %e, "Failed to offer a freed slot"),
+        }
+    }
+    Ok(offers)
+}
+
+/// Close offers whose hold ran out and entries whose dates passed.
+async fn expire_offers(db: &sqlx::SqlitePool) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE waitlist SET status = 'expired'
+         WHERE (status = 'offered' AND hold_until <= datetime('now', '+3 hours'))
+         OR (status = 'waiting' AND date_to < date('now', '+3 hours'))",
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// The earliest block in the entry's range the client could book at `now` on `today`.
+async fn next_free_block(
+    db: &sqlx::SqlitePool,
+    granularity_min: u32,
+    entry: &WaitlistEntry,
+    today: &str,
+    now: &str,
+) -> Result<Option<FreeBlock>, sqlx::Error> {
+    let service = sqlx::query_as::<_, Service>(
+        "SELECT id, name, description, price, duration_min, is_active, sort_order, service_type,
+         buffer_before_min, buffer_after_min, prepayment_required
+         FROM services WHERE id = ? AND is_active = 1",
+    )
+    .bind(entry.service_id)
+    .fetch_optional(db)
+    .await?;
+    let Some(service) = service else {
+        return Ok(None);
+    };
+
+    let dates: Vec<String> = sqlx::query_scalar(
+        "SELECT DISTINCT date FROM available_slots
+         WHERE is_booked = 0 AND ad_hoc = 0 AND date BETWEEN ? AND ?
+         ORDER BY date ASC",
+    )
+    .bind(entry.date_from.as_str().max(today))
+    .bind(&entry.date_to)
+    .fetch_all(db)
+    .await?;
+
+    for date in dates {
+        let times = super::client::bookable_times(
+            db,
+            granularity_min,
+            &service,
+            &date,
+            entry.client_tg_id,
+        )
+        .await?;
+        let Some(time) = times.into_iter().find(|b| date != today || b.start_time.as_str() > now)
+        else {
+            continue;
+        };
+        let span = super::client::locked_span(&service, granularity_min, &time.start_time);
+        if let Some((hold_start, hold_end)) = span {
+            return Ok(Some(FreeBlock {
+                date,
+                time,
+                hold_start,
+                hold_end,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+/// Hold the block for the entry's client for `hold_min` minutes.
+///
+/// Returns `false` if the entry stopped waiting in the meantime.
+async fn hold(
+    db: &sqlx::SqlitePool,
+    hold_min: i64,
+    entry: &WaitlistEntry,
+    block: &FreeBlock,
+) -> Result<bool, sqlx::Error> {
+    let updated = sqlx::query(&format!(
+        "UPDATE waitlist SET status = 'offered', offered_date = ?, offered_time = ?,
+         hold_start = ?, hold_end = ?, hold_until = datetime('now', '+3 hours', '+{} minutes')
+         WHERE id = ? AND status = 'waiting'",
+        hold_min
+    ))
+    .bind(&block.date)
+    .bind(&block.time.start_time)
+    .bind(&block.hold_start)
+    .bind(&block.hold_end)
+    .bind(entry.id)
+    .execute(db)
+    .await?
+    .rows_affected();
+    Ok(updated > 0)
+}
+
+/// Send the client the offer of a held block.
+async fn send_offer(state: &AppState, entry: &WaitlistEntry, block: &FreeBlock) {
+    let text = format!(
+        "🔔 Освободилось время!\n\n\
+         💅 {}\n\
+         📅 {} в {}\n\n\
+         Держим его для тебя {} мин — если не успеешь, время уйдёт следующему в очереди.",
+        entry.service_name, block.date, block.time.start_time, state.waitlist.hold_min
+    );
+    let keyboard = booking_link(&state.webapp_url, entry.service_id, &block.date).map(|url| {
+        serde_json::json!({
+            "inline_keyboard": [[{ "text": "💅 Записаться", "web_app": { "url": url } }]]
+        })
+    });
+    super::client::send_message(&state.bot_token, entry.client_tg_id, &text, keyboard).await;
+}
+
+/// Mini App link that opens the booking page on `date`.
+fn booking_link(webapp_url: &str, service_id: i64, date: &str) -> Option<String> {
+    let mut url = url::Url::parse(webapp_url).ok()?;
+    url.query_pairs_mut()
+        .append_pair("service_id", &service_id.to_string())
+        .append_pair("date", date);
+    Some(url.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bimbo_lashes_core::testing;
+
+    const DATE: &str = "2030-01-01";
+
+    /// A fresh database with hourly slots on [`DATE`] and a one-hour service.
+    async fn setup(times: &[(&str, &str)]) -> (sqlx::SqlitePool, i64) {
+        let db = testing::memory_db().await;
+        testing::open_slots(&db, DATE, times).await;
+        let service_id = sqlx::query_scalar(
+            "INSERT INTO services (name, price, duration_min, is_active, buffer_before_min, buffer_after_min)
+             VALUES ('Коррекция', 1500, 60, 1, 0, 0) RETURNING id",
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        (db, service_id)
+    }
+
+    async fn wait(db: &sqlx::SqlitePool, client_tg_id: i64, service_id: i64, to: &str) -> i64 {
+        sqlx::query_scalar(
+            "INSERT INTO waitlist (client_tg_id, client_first_name, service_id, date_from, date_to)
+             VALUES (?, 'Аня', ?, ?, ?) RETURNING id",
+        )
+        .bind(client_tg_id)
+        .bind(service_id)
+        .bind(DATE)
+        .bind(to)
+        .fetch_one(db)
+        .await
+        .unwrap()
+    }
+
+    async fn entry(db: &sqlx::SqlitePool, id: i64) -> WaitlistEntry {
+        sqlx::query_as::<_, WaitlistEntry>(&format!("{} WHERE w.id = ?", ENTRY_SELECT))
+            .bind(id)
+            .fetch_one(db)
+            .await
+            .unwrap()
+    }
+
+    /// Offers made now, as `(entry id, offered time)`.
+    async fn claim(db: &sqlx::SqlitePool) -> Vec<(i64, String)> {
+        claim_offers(db, 60, 15, "2029-12-31", "12:00")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(entry, block)| (entry.id, block.time.start_time))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_offers_first_come_first_served_one_per_client() {
+        let (db, service_id) = setup(&[("10:00", "11:00"), ("11:00", "12:00")]).await;
+        let first = wait(&db, 7, service_id, DATE).await;
+        let second = wait(&db, 8, service_id, DATE).await;
+        let again = wait(&db, 7, service_id, "2030-01-02").await;
+
+        assert_eq!(
+            claim(&db).await,
+            vec![(first, "10:00".to_string()), (second, "11:00".to_string())]
+        );
+        assert_eq!(entry(&db, first).await.status, "offered");
+        // Client 7 already holds an offer, and nothing is left anyway
+        assert_eq!(entry(&db, again).await.status, "waiting");
+        assert!(claim(&db).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_next_free_block_skips_past_times_today() {
+        let (db, service_id) = setup(&[("10:00", "11:00"), ("11:00", "12:00")]).await;
+        let waiting = entry(&db, wait(&db, 7, service_id, DATE).await).await;
+
+        let block = next_free_block(&db, 60, &waiting, DATE, "10:30").await.unwrap().unwrap();
+        assert_eq!(block.time.start_time, "11:00");
+        assert!(next_free_block(&db, 60, &waiting, DATE, "11:00").await.unwrap().is_none());
+        // Times on later days are all open
+        let block = next_free_block(&db, 60, &waiting, "2029-12-31", "23:00").await.unwrap().unwrap();
+        assert_eq!(block.time.start_time, "10:00");
+    }
+
+    #[tokio::test]
+    async fn test_expired_offer_goes_to_next_client() {
+        let (db, service_id) = setup(&[("10:00", "11:00")]).await;
+        let first = wait(&db, 7, service_id, DATE).await;
+        let second = wait(&db, 8, service_id, DATE).await;
+
+        assert_eq!(claim(&db).await, vec![(first, "10:00".to_string())]);
+        assert!(claim(&db).await.is_empty());
+
+        sqlx::query("UPDATE waitlist SET hold_until = datetime('now', '+3 hours', '-1 minutes') WHERE id = ?")
+            .bind(first)
+            .execute(&db)
+            .await
+            .unwrap();
+        assert_eq!(claim(&db).await, vec![(second, "10:00".to_string())]);
+        assert_eq!(entry(&db, first).await.status, "expired");
+    }
+
+    #[tokio::test]
+    async fn test_booking_closes_matching_entries() {
+        let (db, service_id) = setup(&[("10:00", "11:00")]).await;
+        let offered = wait(&db, 7, service_id, DATE).await;
+        claim(&db).await;
+        let waiting = wait(&db, 7, service_id, "2030-01-05").await;
+        let other_client = wait(&db, 8, service_id, DATE).await;
+        let other_service = wait(&db, 7, service_id - 1, DATE).await;
+
+        booked(&db, 7, service_id, DATE).await;
+
+        assert_eq!(entry(&db, offered).await.status, "booked");
+        assert_eq!(entry(&db, waiting).await.status, "booked");
+        assert_eq!(entry(&db, other_client).await.status, "waiting");
+        assert_eq!(entry(&db, other_service).await.status, "waiting");
+    }
+
+    #[test]
+    fn test_booking_link() {
+        assert_eq!(
+            booking_link("https://lashes.example/", 3, "2026-05-01").as_deref(),
+            Some("https://lashes.example/?service_id=3&date=2026-05-01")
+        );
+        assert_eq!(
+            booking_link("https://lashes.example/app?v=2", 3, "2026-05-01").as_deref(),
+            Some("https://lashes.example/app?v=2&service_id=3&date=2026-05-01")
+        );
+        assert_eq!(booking_link("not a url", 3, "2026-05-01"), None);
+    }
+}
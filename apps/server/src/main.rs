@@ -1,5 +1,5 @@
 mod auth;
-mod db;
+mod client_ip;
 mod handlers;
 mod models;
 mod rate_limit;
@@ -10,8 +10,11 @@ use axum::{
     routing::{delete, get, post, put},
     Router,
 };
+use bimbo_lashes_core::{approval::ApprovalMode, standing};
+use bimbo_lashes_core::payments::{self, PaymentProvider};
 use sqlx::sqlite::SqlitePoolOptions;
 use std::net::SocketAddr;
+use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tower_http::cors::{AllowOrigin, Any, CorsLayer};
@@ -31,15 +34,49 @@ pub struct AppState {
     pub bot_token: String,
     pub admin_tg_id: i64,
     pub started_at: Instant,
-    pub yookassa_shop_id: String,
-    pub yookassa_secret_key: String,
+    /// YooKassa, or the in-process mock (`PAYMENT_PROVIDER=mock`).
+    pub payments: Arc<dyn PaymentProvider>,
+    /// YooKassa redirect and/or Telegram invoices.
+    pub prepayment: handlers::payment::PrepaymentMethods,
+    /// Proxies whose `X-Forwarded-For` is believed (env TRUSTED_PROXIES).
+    pub trusted_proxies: client_ip::IpNets,
+    /// Networks payment webhooks must come from; `None` for the mock provider.
+    pub webhook_sources: Option<client_ip::IpNets>,
+    /// Webhooks refused since start (bad source, forged or mismatched payment).
+    pub webhook_rejections: AtomicU64,
     pub webapp_url: String,
+    /// How many weeks ahead the weekly template opens slots.
+    pub schedule_weeks_ahead: u32,
+    /// Base slot length in minutes (15, 30 or 60).
+    pub slot_granularity_min: u32,
+    /// Limits on client-initiated rescheduling.
+    pub reschedule_policy: handlers::client::ReschedulePolicy,
+    /// Which new bookings wait for the master's approval.
+    pub approval: handlers::approval::ApprovalPolicy,
+    /// What clients who skip or cancel late may still do online.
+    pub restrictions: handlers::standing::RestrictionPolicy,
+    /// Offers freed slots to waiting clients.
+    pub waitlist: handlers::waitlist::Waitlist,
+    /// How long `POST /api/holds` keeps a block for the client (minutes).
+    pub slot_hold_min: i64,
 }
 
 /// Payment expiry check interval (seconds).
 const PAYMENT_EXPIRY_INTERVAL_SECS: u64 = 300;
 /// Rate limit cleanup interval (seconds).
 const RATE_LIMIT_CLEANUP_SECS: u64 = 300;
+/// Weekly schedule materialization interval (seconds).
+const SCHEDULE_MATERIALIZE_SECS: u64 = 3600;
+/// Payment reconciliation sweep interval (seconds).
+const RECONCILE_INTERVAL_SECS: u64 = 3600;
+/// Unanswered approval requests check interval (seconds).
+const APPROVAL_EXPIRY_INTERVAL_SECS: u64 = 600;
+/// Finished and unresolved visits check interval (seconds).
+const VISIT_OUTCOME_INTERVAL_SECS: u64 = 600;
+/// Waitlist offers check interval (seconds); freed slots also trigger it at once.
+const WAITLIST_INTERVAL_SECS: u64 = 60;
+/// Expired slot holds cleanup interval (seconds).
+const HOLD_CLEANUP_INTERVAL_SECS: u64 = 60;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -71,14 +108,81 @@ async fn main() -> anyhow::Result<()> {
     let port = std::env::var("PORT").unwrap_or_else(|_| "3000".into());
 
     // ── Optional env vars ──
-    let yookassa_shop_id = std::env::var("YOOKASSA_SHOP_ID").unwrap_or_default();
-    let yookassa_secret_key = std::env::var("YOOKASSA_SECRET_KEY").unwrap_or_default();
+    let payments = payments::from_env()?;
+    let prepayment_required = !std::env::var("PREPAYMENT_REQUIRED")
+        .is_ok_and(|v| v == "false" || v == "0");
+    if !prepayment_required {
+        tracing::info!("PREPAYMENT_REQUIRED=false — bookings are confirmed without prepayment");
+    }
+    let telegram_payment_token = std::env::var("TELEGRAM_PAYMENT_TOKEN")
+        .ok()
+        .filter(|t| !t.is_empty());
+    let default_payment_method = match std::env::var("PREPAYMENT_METHOD").as_deref() {
+        Ok("telegram") => models::PaymentMethod::Telegram,
+        Ok("yookassa") | Err(_) => models::PaymentMethod::Yookassa,
+        Ok(other) => anyhow::bail!("PREPAYMENT_METHOD must be yookassa or telegram, got {}", other),
+    };
+    if default_payment_method == models::PaymentMethod::Telegram && telegram_payment_token.is_none() {
+        anyhow::bail!("PREPAYMENT_METHOD=telegram requires TELEGRAM_PAYMENT_TOKEN");
+    }
+    let trusted_proxies = client_ip::IpNets::parse_list(
+        &std::env::var("TRUSTED_PROXIES")
+            .unwrap_or_else(|_| client_ip::DEFAULT_TRUSTED_PROXIES.into()),
+    )?;
+    let webhook_sources = payments
+        .webhook_sources()
+        .map(|nets| client_ip::IpNets::parse(nets.iter().copied()))
+        .transpose()?;
     let webapp_url =
         std::env::var("WEBAPP_URL").unwrap_or_else(|_| "https://example.com".into());
+    let schedule_weeks_ahead: u32 = std::env::var("SCHEDULE_WEEKS_AHEAD")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(4);
+    let slot_granularity_min = bimbo_lashes_core::slots::granularity_from_env()?;
+    let reschedule_policy = handlers::client::ReschedulePolicy {
+        max_reschedules: std::env::var("RESCHEDULE_MAX")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(2),
+        cutoff_hours: std::env::var("RESCHEDULE_CUTOFF_HOURS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(24),
+    };
 
-    if yookassa_shop_id.is_empty() {
-        tracing::warn!("YOOKASSA_SHOP_ID not set — payments will fail");
-    }
+    let approval = handlers::approval::ApprovalPolicy {
+        mode: match std::env::var("BOOKING_APPROVAL") {
+            Ok(v) => ApprovalMode::parse(&v).ok_or_else(|| {
+                anyhow::anyhow!("BOOKING_APPROVAL must be off, new_clients or all, got {}", v)
+            })?,
+            Err(_) => ApprovalMode::Off,
+        },
+        timeout_hours: std::env::var("APPROVAL_TIMEOUT_HOURS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(12),
+    };
+
+    let restrictions = handlers::standing::RestrictionPolicy {
+        rules: standing::parse_rules(&std::env::var("CLIENT_RESTRICTIONS").unwrap_or_default())
+            .map_err(|e| anyhow::anyhow!("CLIENT_RESTRICTIONS: {}", e))?,
+        late_cancel_hours: std::env::var("LATE_CANCEL_HOURS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(24),
+    };
+
+    let waitlist_hold_min: i64 = std::env::var("WAITLIST_HOLD_MIN")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&m| m > 0)
+        .unwrap_or(15);
+    let slot_hold_min: i64 = std::env::var("SLOT_HOLD_MIN")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&m| m > 0)
+        .unwrap_or(10);
 
     // ── Database ──
     let pool = SqlitePoolOptions::new()
@@ -86,27 +190,123 @@ async fn main() -> anyhow::Result<()> {
         .connect(&database_url)
         .await?;
 
-    db::run_migrations(&pool).await?;
+    bimbo_lashes_core::migrations::run(&pool).await?;
+
+    // Re-cut free slots when the granularity was lowered (e.g. legacy 1-hour slots)
+    let split = handlers::admin::split_free_slots(&pool, slot_granularity_min).await?;
+    if split > 0 {
+        tracing::info!(split, slot_granularity_min, "Split free slots to new granularity");
+    }
 
     let state = Arc::new(AppState {
         db: pool,
         bot_token,
         admin_tg_id,
         started_at: Instant::now(),
-        yookassa_shop_id,
-        yookassa_secret_key,
+        payments,
+        prepayment: handlers::payment::PrepaymentMethods {
+            required: prepayment_required,
+            default: default_payment_method,
+            telegram_token: telegram_payment_token,
+        },
+        trusted_proxies,
+        webhook_sources,
+        webhook_rejections: AtomicU64::new(0),
         webapp_url: webapp_url.clone(),
+        schedule_weeks_ahead,
+        slot_granularity_min,
+        reschedule_policy,
+        approval,
+        restrictions,
+        waitlist: handlers::waitlist::Waitlist::new(waitlist_hold_min),
+        slot_hold_min,
     });
 
     // ── Background task: expire unpaid bookings ──
-    let expire_db = state.db.clone();
+    let expire_state = state.clone();
     tokio::spawn(async move {
         let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
             PAYMENT_EXPIRY_INTERVAL_SECS,
         ));
         loop {
             interval.tick().await;
-            handlers::payment::expire_pending_payments(&expire_db).await;
+            handlers::payment::expire_pending_payments(&expire_state).await;
+        }
+    });
+
+    // ── Background task: expire unanswered approval requests ──
+    let approval_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+            APPROVAL_EXPIRY_INTERVAL_SECS,
+        ));
+        loop {
+            interval.tick().await;
+            handlers::approval::expire_unapproved_bookings(&approval_state).await;
+        }
+    });
+
+    // ── Background task: ask the master how finished visits went ──
+    let visits_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+            VISIT_OUTCOME_INTERVAL_SECS,
+        ));
+        loop {
+            interval.tick().await;
+            handlers::visits::ask_visit_outcomes(&visits_state).await;
+        }
+    });
+
+    // ── Background task: offer freed slots to the waitlist ──
+    let waitlist_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(tokio::time::Duration::from_secs(WAITLIST_INTERVAL_SECS));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = waitlist_state.waitlist.woken() => {}
+            }
+            handlers::waitlist::offer_free_slots(&waitlist_state).await;
+        }
+    });
+
+    // ── Background task: release expired slot holds ──
+    let holds_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(tokio::time::Duration::from_secs(HOLD_CLEANUP_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            handlers::holds::release_expired_holds(&holds_state).await;
+        }
+    });
+
+    // ── Background task: compare recent payments with the provider ──
+    let reconcile_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(tokio::time::Duration::from_secs(RECONCILE_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            handlers::reconcile::reconcile_payments(&reconcile_state).await;
+        }
+    });
+
+    // ── Background task: open slots from the weekly template ──
+    let schedule_db = state.db.clone();
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(tokio::time::Duration::from_secs(SCHEDULE_MATERIALIZE_SECS));
+        loop {
+            interval.tick().await;
+            handlers::schedule::materialize_schedule(
+                &schedule_db,
+                schedule_weeks_ahead,
+                slot_granularity_min,
+            )
+            .await;
         }
     });
 
@@ -201,6 +401,11 @@ async fn main() -> anyhow::Result<()> {
     // 3. Booking creation: strictest limit (5 req/5min)
     let booking_routes = Router::new()
         .route("/api/bookings", post(handlers::client::create_booking))
+        .route(
+            "/api/bookings/{id}/reschedule",
+            post(handlers::client::reschedule_booking),
+        )
+        .layer(from_fn_with_state(state.clone(), auth::track_client))
         .layer(from_fn_with_state(
             rate_limiter.clone(),
             rate_limit_booking,
@@ -217,6 +422,15 @@ async fn main() -> anyhow::Result<()> {
             "/api/bookings/{id}/status",
             get(handlers::client::booking_status),
         )
+        .route("/api/waitlist", post(handlers::waitlist::join_waitlist))
+        .route("/api/waitlist/my", get(handlers::waitlist::my_waitlist))
+        .route(
+            "/api/waitlist/{id}",
+            delete(handlers::waitlist::leave_waitlist),
+        )
+        .route("/api/holds", post(handlers::holds::create_hold))
+        .route("/api/holds/{id}", delete(handlers::holds::release_hold))
+        .layer(from_fn_with_state(state.clone(), auth::track_client))
         .layer(from_fn_with_state(rate_limiter.clone(), rate_limit_auth));
 
     // 5. Admin: all admin endpoints (120 req/min)
@@ -240,14 +454,85 @@ async fn main() -> anyhow::Result<()> {
             delete(handlers::admin::delete_slot),
         )
         .route("/api/admin/openday", post(handlers::admin::open_day))
+        .route(
+            "/api/admin/cancellation-policy",
+            get(handlers::policy::get_policy),
+        )
+        .route(
+            "/api/admin/cancellation-policy",
+            put(handlers::policy::update_policy),
+        )
+        .route(
+            "/api/admin/schedule",
+            get(handlers::schedule::get_schedule),
+        )
+        .route(
+            "/api/admin/schedule",
+            put(handlers::schedule::update_schedule),
+        )
+        .route(
+            "/api/admin/schedule/preview",
+            get(handlers::schedule::preview_schedule),
+        )
+        .route(
+            "/api/admin/schedule/exceptions",
+            post(handlers::schedule::upsert_exception),
+        )
+        .route(
+            "/api/admin/schedule/exceptions/{date}",
+            delete(handlers::schedule::delete_exception),
+        )
         .route(
             "/api/admin/bookings",
-            get(handlers::admin::list_bookings),
+            get(handlers::admin::list_bookings).post(handlers::admin::create_booking),
         )
         .route(
             "/api/admin/bookings/{id}/cancel",
             post(handlers::admin::cancel_booking),
         )
+        .route(
+            "/api/admin/bookings/{id}/outcome",
+            post(handlers::visits::close_visit),
+        )
+        .route(
+            "/api/admin/bookings/{id}/refund",
+            post(handlers::refunds::create_refund),
+        )
+        .route("/api/admin/refunds", get(handlers::refunds::list_refunds))
+        .route(
+            "/api/admin/refunds/{id}/complete",
+            post(handlers::refunds::complete_refund),
+        )
+        .route("/api/admin/clients", get(handlers::clients::list_clients))
+        .route(
+            "/api/admin/clients/{tg_id}",
+            get(handlers::clients::get_client).put(handlers::clients::update_client),
+        )
+        .route(
+            "/api/admin/clients/{tg_id}/standing",
+            get(handlers::standing::get_standing).delete(handlers::standing::reset_standing),
+        )
+        .route("/api/admin/waitlist", get(handlers::waitlist::list_waitlist))
+        .route(
+            "/api/admin/blocklist",
+            get(handlers::blocklist::list_blocked).post(handlers::blocklist::block_client),
+        )
+        .route(
+            "/api/admin/blocklist/{id}",
+            delete(handlers::blocklist::unblock_client),
+        )
+        .route(
+            "/api/admin/trusted-clients",
+            get(handlers::trusted::list_trusted_clients),
+        )
+        .route(
+            "/api/admin/trusted-clients/{tg_id}",
+            put(handlers::trusted::trust_client),
+        )
+        .route(
+            "/api/admin/trusted-clients/{tg_id}",
+            delete(handlers::trusted::untrust_client),
+        )
         .layer(from_fn_with_state(rate_limiter.clone(), rate_limit_admin));
 
     let app = Router::new()
@@ -1,60 +1,82 @@
+use bimbo_lashes_core::rules::PREPAID_AMOUNT;
+pub use bimbo_lashes_core::standing::Restriction;
+pub use bimbo_lashes_core::visits::Outcome;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 
 // ── Database models ──
 
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
-pub struct Service {
-    pub id: i64,
-    pub name: String,
-    pub description: String,
-    pub price: i64,
-    pub duration_min: i64,
-    pub is_active: bool,
-    pub sort_order: i64,
-    pub service_type: String,
-}
+pub use bimbo_lashes_core::models::{
+    AvailableSlot, BlockedClient, Booking, BookingDetail, CancellationTier, Client, Refund,
+    Service, BLOCKED_NOTICE,
+};
 
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
-pub struct AvailableSlot {
-    pub id: i64,
+// ── API request/response types ──
+
+#[derive(Debug, Deserialize)]
+pub struct CreateBookingRequest {
+    pub service_id: i64,
     pub date: String,
     pub start_time: String,
-    pub end_time: String,
-    pub is_booked: bool,
-    pub booking_id: Option<i64>,
+    #[serde(default)]
+    pub with_lower_lashes: bool,
+    /// Defaults to the deployment's `PREPAYMENT_METHOD`.
+    pub payment_method: Option<PaymentMethod>,
+    /// Contact for the fiscal receipt; one of phone/email is required when receipts are on.
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    /// Deposit (default) or the whole price up front.
+    #[serde(default)]
+    pub payment_plan: PaymentPlan,
+    /// Tip in RUB, charged together with the payment.
+    #[serde(default)]
+    pub tip: i64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
-pub struct Booking {
-    pub id: i64,
-    pub service_id: i64,
-    pub slot_id: i64,
-    pub client_tg_id: i64,
-    pub client_username: Option<String>,
-    pub client_first_name: String,
-    pub status: String,
-    pub reminder_sent: bool,
-    pub created_at: String,
-    pub cancelled_at: Option<String>,
-    pub date: Option<String>,
-    pub start_time: Option<String>,
-    pub end_time: Option<String>,
-    pub with_lower_lashes: bool,
-    pub payment_status: String,
-    pub yookassa_payment_id: Option<String>,
-    pub prepaid_amount: i64,
+/// How much of the price is paid online.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PaymentPlan {
+    /// Fixed `PREPAID_AMOUNT`, the rest at the visit.
+    #[default]
+    Deposit,
+    /// Service + addon price.
+    Full,
 }
 
-// ── API request/response types ──
+impl PaymentPlan {
+    /// Amount charged for a visit costing `total_price`, tip excluded.
+    pub fn amount(self, total_price: i64) -> i64 {
+        match self {
+            PaymentPlan::Deposit => PREPAID_AMOUNT.min(total_price),
+            PaymentPlan::Full => total_price,
+        }
+    }
+}
+
+/// How the prepayment is collected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PaymentMethod {
+    /// Redirect to the payment provider's page.
+    Yookassa,
+    /// Invoice in the bot chat (Telegram Payments).
+    Telegram,
+}
+
+impl PaymentMethod {
+    /// Value stored in `bookings.payment_method`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PaymentMethod::Yookassa => "yookassa",
+            PaymentMethod::Telegram => "telegram",
+        }
+    }
+}
 
 #[derive(Debug, Deserialize)]
-pub struct CreateBookingRequest {
-    pub service_id: i64,
+pub struct RescheduleBookingRequest {
     pub date: String,
     pub start_time: String,
-    #[serde(default)]
-    pub with_lower_lashes: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -121,6 +143,139 @@ pub struct BookingsQuery {
     pub to: Option<String>,
 }
 
+/// Booking the master enters for a phone or walk-in client.
+#[derive(Debug, Deserialize)]
+pub struct AdminCreateBookingRequest {
+    pub service_id: i64,
+    pub date: String,
+    /// Any HH:MM, open slot or not.
+    pub start_time: String,
+    /// Defaults to `start_time` plus the service duration.
+    pub end_time: Option<String>,
+    #[serde(default)]
+    pub with_lower_lashes: bool,
+    pub client_name: String,
+    /// Links the booking to the client's Telegram (reminders, cancellation in the app).
+    pub client_tg_id: Option<i64>,
+    pub phone: Option<String>,
+    #[serde(default)]
+    pub payment: OnSitePayment,
+}
+
+/// How an admin-created booking is paid; nothing is charged online.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnSitePayment {
+    /// Paid in cash at the visit.
+    Cash,
+    /// Not settled yet.
+    #[default]
+    None,
+}
+
+impl OnSitePayment {
+    /// Value stored in `bookings.payment_method`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OnSitePayment::Cash => "cash",
+            OnSitePayment::None => "none",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClientsQuery {
+    /// Substring of the name, username, phone, tags or notes.
+    pub q: Option<String>,
+}
+
+/// A client with everything the master knows about them.
+#[derive(Debug, Serialize)]
+pub struct ClientProfile {
+    pub client: Client,
+    pub standing: ClientStanding,
+    pub bookings: Vec<BookingDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateClientRequest {
+    /// Empty string clears it.
+    pub phone: Option<String>,
+    pub lash_style: Option<String>,
+    pub curl: Option<String>,
+    pub length: Option<String>,
+    pub allergies: Option<String>,
+    pub notes: Option<String>,
+    /// Replaces all tags.
+    pub tags: Option<Vec<String>>,
+}
+
+/// A client's record of no-shows and late cancellations.
+#[derive(Debug, Serialize)]
+pub struct ClientStanding {
+    pub client_tg_id: i64,
+    pub no_shows: i64,
+    pub late_cancellations: i64,
+    pub restriction: Restriction,
+    /// Counted only after this moment; `None` if never reset.
+    pub reset_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CloseVisitRequest {
+    pub outcome: Outcome,
+    /// RUB taken at the visit.
+    pub amount_collected: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AdminCancelQuery {
+    /// Refund this amount instead of the full prepayment.
+    pub refund_amount: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RefundsQuery {
+    pub booking_id: Option<i64>,
+    /// pending | succeeded | canceled | failed
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRefundRequest {
+    pub amount: i64,
+    pub reason: Option<String>,
+}
+
+/// Refund row with the booking it belongs to, for the admin list.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct RefundDetail {
+    pub id: i64,
+    pub booking_id: i64,
+    pub yookassa_refund_id: Option<String>,
+    pub amount: i64,
+    pub status: String,
+    pub reason: String,
+    pub created_at: String,
+    pub updated_at: String,
+    pub client_first_name: String,
+    pub client_username: Option<String>,
+    pub date: Option<String>,
+    pub start_time: Option<String>,
+}
+
+/// Our side of a provider payment, as compared by reconciliation.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct PaymentRecord {
+    pub booking_id: i64,
+    pub payment_id: String,
+    pub status: String,
+    pub payment_status: String,
+    pub prepaid_amount: i64,
+    /// Sum of succeeded refunds.
+    pub refunded: i64,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreateServiceRequest {
     pub name: String,
@@ -128,6 +283,9 @@ pub struct CreateServiceRequest {
     pub price: i64,
     pub duration_min: i64,
     pub sort_order: Option<i64>,
+    pub buffer_before_min: Option<i64>,
+    pub buffer_after_min: Option<i64>,
+    pub prepayment_required: Option<bool>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -138,41 +296,96 @@ pub struct UpdateServiceRequest {
     pub duration_min: Option<i64>,
     pub is_active: Option<bool>,
     pub sort_order: Option<i64>,
+    pub buffer_before_min: Option<i64>,
+    pub buffer_after_min: Option<i64>,
+    pub prepayment_required: Option<bool>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct CreateSlotsRequest {
-    pub date: String,
-    pub slots: Vec<SlotTime>,
+/// Client allowed to book without prepayment.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct TrustedClient {
+    pub client_tg_id: i64,
+    pub note: String,
+    pub created_at: String,
+    /// From the client's latest booking, if any.
+    pub client_first_name: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct SlotTime {
-    pub start_time: String,
-    pub end_time: String,
+pub struct TrustClientRequest {
+    pub note: Option<String>,
 }
 
+/// A client waiting for a service on a date range.
 #[derive(Debug, Serialize, sqlx::FromRow)]
-pub struct BookingDetail {
+pub struct WaitlistEntry {
     pub id: i64,
-    pub service_name: String,
-    pub service_price: i64,
-    pub date: String,
-    pub start_time: String,
-    pub end_time: String,
     pub client_tg_id: i64,
-    pub client_username: Option<String>,
     pub client_first_name: String,
+    pub service_id: i64,
+    pub service_name: String,
+    pub date_from: String,
+    pub date_to: String,
+    /// waiting | offered | booked | expired | cancelled
     pub status: String,
+    /// The block held for the client while `offered`.
+    pub offered_date: Option<String>,
+    pub offered_time: Option<String>,
+    pub hold_until: Option<String>,
     pub created_at: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub with_lower_lashes: Option<bool>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub total_price: Option<i64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub payment_status: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub prepaid_amount: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JoinWaitlistRequest {
+    pub service_id: i64,
+    pub date_from: String,
+    /// Defaults to `date_from`.
+    pub date_to: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateHoldRequest {
+    pub service_id: i64,
+    pub date: String,
+    pub start_time: String,
+}
+
+/// A block kept for the client; `end_time` is the end of the appointment itself.
+#[derive(Debug, Serialize)]
+pub struct SlotHold {
+    pub id: i64,
+    pub date: String,
+    pub start_time: String,
+    pub end_time: String,
+    pub expires_at: String,
+}
+
+/// At least one identifier is required.
+#[derive(Debug, Deserialize)]
+pub struct BlockClientRequest {
+    pub tg_id: Option<i64>,
+    pub username: Option<String>,
+    pub phone: Option<String>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlockClientResponse {
+    pub entry: BlockedClient,
+    /// Still active; cancel them with POST /api/admin/bookings/:id/cancel.
+    pub upcoming: Vec<BookingDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateSlotsRequest {
+    pub date: String,
+    pub slots: Vec<SlotTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SlotTime {
+    pub start_time: String,
+    pub end_time: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -206,6 +419,8 @@ impl<T: Serialize> ApiResponse<T> {
 pub struct CreateBookingResponse {
     pub booking: BookingDetail,
     pub payment_url: Option<String>,
+    /// `None`: confirmed without prepayment.
+    pub payment_method: Option<PaymentMethod>,
 }
 
 #[derive(Debug, Serialize)]
@@ -214,24 +429,80 @@ pub struct BookingStatusResponse {
     pub payment_status: String,
 }
 
+#[derive(Debug, Serialize)]
+pub struct CancelBookingResponse {
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refund_info: Option<String>,
+}
+
+// ── Cancellation policy types ──
+
 #[derive(Debug, Deserialize)]
-pub struct YooKassaWebhookEvent {
-    pub event: String,
-    pub object: YooKassaPaymentObject,
+pub struct CancellationTierInput {
+    pub more_than_hours: i64,
+    pub refund_percent: i64,
 }
 
+/// Replace the tiers of one policy: the default (`service_id` omitted) or a service override.
+/// An empty list for a service removes its override.
 #[derive(Debug, Deserialize)]
-pub struct YooKassaPaymentObject {
-    pub id: String,
-    pub status: String,
-    pub metadata: Option<Value>,
+pub struct UpdateCancellationPolicyRequest {
+    pub service_id: Option<i64>,
+    pub tiers: Vec<CancellationTierInput>,
+}
+
+// ── Schedule template types ──
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ScheduleDay {
+    /// 0 = Monday … 6 = Sunday.
+    pub weekday: i64,
+    pub start_hour: i64,
+    pub end_hour: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ScheduleException {
+    pub date: String,
+    pub is_day_off: bool,
+    pub start_hour: Option<i64>,
+    pub end_hour: Option<i64>,
+    #[serde(default)]
+    pub note: String,
 }
 
 #[derive(Debug, Serialize)]
-pub struct CancelBookingResponse {
-    pub message: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub refund_info: Option<String>,
+pub struct ScheduleExceptionResponse {
+    pub exception: ScheduleException,
+    /// Active bookings outside the new hours; cancel or move them by hand.
+    pub bookings: Vec<BookingDetail>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScheduleResponse {
+    pub days: Vec<ScheduleDay>,
+    pub exceptions: Vec<ScheduleException>,
+    pub weeks_ahead: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateScheduleRequest {
+    pub days: Vec<ScheduleDay>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SchedulePreviewQuery {
+    pub weeks: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SchedulePreviewDay {
+    pub date: String,
+    pub start_hour: i64,
+    pub end_hour: i64,
+    pub slots: Vec<TimeBlock>,
+    pub new_slots: i64,
 }
 
 // ── Telegram auth ──
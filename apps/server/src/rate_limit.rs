@@ -243,7 +243,7 @@ mod tests {
         let ip = test_ip(1);
         limiter.check("test", ip).unwrap();
         let retry_after = limiter.check("test", ip).unwrap_err();
-        assert!(retry_after >= 1 && retry_after <= 60);
+        assert!((1..=60).contains(&retry_after));
     }
 
     #[test]
@@ -285,15 +285,19 @@ mod tests {
 
     #[test]
     fn test_format_message_basic() {
-        let mut v = MessageVisitor::default();
-        v.message = "Something failed".into();
+        let v = MessageVisitor {
+            message: "Something failed".into(),
+            ..Default::default()
+        };
         assert_eq!(v.message(), "Something failed");
     }
 
     #[test]
     fn test_format_message_with_fields() {
-        let mut v = MessageVisitor::default();
-        v.message = "DB error".into();
+        let mut v = MessageVisitor {
+            message: "DB error".into(),
+            ..Default::default()
+        };
         v.fields
             .push(("booking_id".into(), "42".into()));
         assert_eq!(v.message(), "DB error (booking_id=42)");
@@ -1,6 +1,7 @@
 import { createEffect, Match, Switch, onMount } from "solid-js";
 import WebApp from "@twa-dev/sdk";
-import { route, goHome, goAdmin } from "./lib/router";
+import { route, goHome, goAdmin, goBooking } from "./lib/router";
+import { api } from "./lib/api";
 import HomePage from "./pages/HomePage";
 import BookingPage from "./pages/BookingPage";
 import MyBookingsPage from "./pages/MyBookingsPage";
@@ -10,6 +11,19 @@ import AdminServicesPage from "./pages/AdminServicesPage";
 
 export default function App() {
   onMount(() => {
+    // Waitlist offers link straight to a date: ?service_id=N&date=YYYY-MM-DD
+    const params = new URLSearchParams(window.location.search);
+    const serviceId = Number(params.get("service_id"));
+    const date = params.get("date");
+    if (serviceId && date) {
+      api.getServices().then((services) => {
+        const service = services.find((s) => s.id === serviceId);
+        if (service) {
+          goBooking(service.id, service.name, service.price, false, service.prepayment_required, date);
+        }
+      }).catch(() => {});
+    }
+
     // Handle Telegram back button
     WebApp.BackButton.onClick(() => {
       const r = route();
@@ -47,6 +61,8 @@ export default function App() {
                   serviceName={r.serviceName}
                   servicePrice={r.servicePrice}
                   withLowerLashes={r.withLowerLashes}
+                  prepaymentRequired={r.prepaymentRequired}
+                  date={r.date}
                 />
               );
             }
@@ -14,6 +14,7 @@ interface CalendarProps {
   onSelect: (date: string) => void;
   selectedDate?: string;
   adminMode?: boolean; // admin can click any future date
+  waitlistMode?: boolean; // fully booked days open too, to join the waitlist
 }
 
 export default function Calendar(props: CalendarProps) {
@@ -123,6 +124,7 @@ export default function Calendar(props: CalendarProps) {
   const isClickable = (cell: NonNullable<ReturnType<typeof gridCells>[0]>) => {
     if (cell.isPast) return false;
     if (props.adminMode) return true; // admin can click any future date
+    if (props.waitlistMode && (cell.info?.total ?? 0) > 0) return true;
     return cell.info?.bookable === true;
   };
 
@@ -39,6 +39,8 @@ export interface Service {
   is_active: boolean;
   sort_order: number;
   service_type: string;
+  /** false: booked without online payment, paid at the visit. */
+  prepayment_required: boolean;
 }
 
 export interface Slot {
@@ -89,11 +91,95 @@ export interface BookingDetail {
   total_price?: number;
   payment_status?: string;
   prepaid_amount?: number;
+  tip_amount?: number;
+  /** 7XXXXXXXXXX; set for phone bookings and receipt contacts. */
+  client_phone?: string;
+  /** RUB taken at a completed visit. */
+  amount_collected?: number;
 }
 
+/** "deposit": fixed prepayment, the rest at the visit; "full": the whole price online. */
+export type PaymentPlan = "deposit" | "full";
+
 export interface CreateBookingResponse {
   booking: BookingDetail;
   payment_url?: string;
+  /** "telegram": the invoice was sent to the bot chat instead of a payment page;
+   *  absent: confirmed without prepayment. */
+  payment_method?: "yookassa" | "telegram";
+}
+
+export interface ClientStanding {
+  client_tg_id: number;
+  no_shows: number;
+  late_cancellations: number;
+  restriction: "none" | "full_prepayment" | "blocked";
+  reset_at: string | null;
+}
+
+export interface Client {
+  tg_id: number;
+  username: string | null;
+  first_name: string;
+  last_name: string | null;
+  phone: string | null;
+  lash_style: string;
+  curl: string;
+  length: string;
+  allergies: string;
+  notes: string;
+  tags: string[];
+  created_at: string;
+  last_seen_at: string;
+  visit_count: number;
+  lifetime_spend: number;
+  last_visit: string | null;
+}
+
+export interface ClientProfile {
+  client: Client;
+  standing: ClientStanding;
+  bookings: BookingDetail[];
+}
+
+export interface WaitlistEntry {
+  id: number;
+  client_tg_id: number;
+  client_first_name: string;
+  service_id: number;
+  service_name: string;
+  date_from: string;
+  date_to: string;
+  status: "waiting" | "offered" | "booked" | "expired" | "cancelled";
+  /** The block held for the client while "offered". */
+  offered_date: string | null;
+  offered_time: string | null;
+  hold_until: string | null;
+  created_at: string;
+}
+
+/** A block kept for the client while they confirm; `end_time` ends the appointment. */
+export interface SlotHold {
+  id: number;
+  date: string;
+  start_time: string;
+  end_time: string;
+  expires_at: string;
+}
+
+export interface BlockedClient {
+  id: number;
+  tg_id: number | null;
+  username: string | null;
+  phone: string | null;
+  reason: string;
+  created_at: string;
+}
+
+export interface BlockClientResponse {
+  entry: BlockedClient;
+  /** Still active; offer to cancel them with adminApi.cancelBooking. */
+  upcoming: BookingDetail[];
 }
 
 export interface BookingStatusResponse {
@@ -125,19 +211,56 @@ export const api = {
   getAvailableTimes: (date: string, serviceId: number) =>
     request<AvailableTimes>(`/api/available-times?date=${date}&service_id=${serviceId}`),
 
-  createBooking: (serviceId: number, date: string, startTime: string, withLowerLashes: boolean = false) =>
-    request<CreateBookingResponse>("/api/bookings", {
+  createBooking: (
+    serviceId: number,
+    date: string,
+    startTime: string,
+    withLowerLashes: boolean = false,
+    contact: string = "",
+    paymentPlan: PaymentPlan = "deposit",
+    tip: number = 0
+  ) => {
+    // Receipt contact: an email if it has '@', otherwise a phone number
+    const trimmed = contact.trim();
+    const isEmail = trimmed.includes("@");
+    return request<CreateBookingResponse>("/api/bookings", {
       method: "POST",
       body: JSON.stringify({
         service_id: serviceId,
         date,
         start_time: startTime,
         with_lower_lashes: withLowerLashes,
+        phone: trimmed && !isEmail ? trimmed : undefined,
+        email: isEmail ? trimmed : undefined,
+        payment_plan: paymentPlan,
+        tip,
       }),
-    }),
+    });
+  },
 
   getMyBookings: () => request<BookingDetail[]>("/api/bookings/my"),
 
+  /** Get a bot message when a block frees up on the date (or up to dateTo). */
+  joinWaitlist: (serviceId: number, dateFrom: string, dateTo?: string) =>
+    request<WaitlistEntry>("/api/waitlist", {
+      method: "POST",
+      body: JSON.stringify({ service_id: serviceId, date_from: dateFrom, date_to: dateTo }),
+    }),
+
+  getMyWaitlist: () => request<WaitlistEntry[]>("/api/waitlist/my"),
+
+  leaveWaitlist: (id: number) =>
+    request<string>(`/api/waitlist/${id}`, { method: "DELETE" }),
+
+  createHold: (serviceId: number, date: string, startTime: string) =>
+    request<SlotHold>("/api/holds", {
+      method: "POST",
+      body: JSON.stringify({ service_id: serviceId, date, start_time: startTime }),
+    }),
+
+  releaseHold: (id: number) =>
+    request<string>(`/api/holds/${id}`, { method: "DELETE" }),
+
   cancelBooking: (id: number) =>
     request<CancelBookingResponse>(`/api/bookings/${id}`, { method: "DELETE" }),
 
@@ -194,6 +317,71 @@ export const adminApi = {
     return request<BookingDetail[]>(`/api/admin/bookings${qs ? `?${qs}` : ""}`);
   },
 
+  /** Phone or walk-in client; any time, no online payment. */
+  createBooking: (data: {
+    service_id: number;
+    date: string;
+    start_time: string;
+    end_time?: string;
+    with_lower_lashes?: boolean;
+    client_name: string;
+    client_tg_id?: number;
+    phone?: string;
+    payment?: "cash" | "none";
+  }) =>
+    request<BookingDetail>("/api/admin/bookings", {
+      method: "POST",
+      body: JSON.stringify(data),
+    }),
+
   cancelBooking: (id: number) =>
     request<string>(`/api/admin/bookings/${id}/cancel`, { method: "POST" }),
+
+  getClients: (q?: string) =>
+    request<Client[]>(`/api/admin/clients${q ? `?q=${encodeURIComponent(q)}` : ""}`),
+
+  getClient: (tgId: number) =>
+    request<ClientProfile>(`/api/admin/clients/${tgId}`),
+
+  /** Omitted fields stay as they are; an empty phone clears it; tags replace the list. */
+  updateClient: (tgId: number, data: {
+    phone?: string;
+    lash_style?: string;
+    curl?: string;
+    length?: string;
+    allergies?: string;
+    notes?: string;
+    tags?: string[];
+  }) =>
+    request<Client>(`/api/admin/clients/${tgId}`, {
+      method: "PUT",
+      body: JSON.stringify(data),
+    }),
+
+  getWaitlist: () => request<WaitlistEntry[]>("/api/admin/waitlist"),
+
+  getBlocklist: () => request<BlockedClient[]>("/api/admin/blocklist"),
+
+  /** At least one of tg_id, username or phone. */
+  blockClient: (data: { tg_id?: number; username?: string; phone?: string; reason?: string }) =>
+    request<BlockClientResponse>("/api/admin/blocklist", {
+      method: "POST",
+      body: JSON.stringify(data),
+    }),
+
+  unblockClient: (id: number) =>
+    request<string>(`/api/admin/blocklist/${id}`, { method: "DELETE" }),
+
+  getStanding: (tgId: number) =>
+    request<ClientStanding>(`/api/admin/clients/${tgId}/standing`),
+
+  resetStanding: (tgId: number) =>
+    request<ClientStanding>(`/api/admin/clients/${tgId}/standing`, { method: "DELETE" }),
+
+  /** Close a past visit; the amount defaults to what was left to pay. */
+  closeVisit: (id: number, outcome: "completed" | "no_show", amountCollected?: number) =>
+    request<BookingDetail>(`/api/admin/bookings/${id}/outcome`, {
+      method: "POST",
+      body: JSON.stringify({ outcome, amount_collected: amountCollected }),
+    }),
 };
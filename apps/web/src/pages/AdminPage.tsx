@@ -46,7 +46,9 @@ export default function AdminPage() {
   };
 
   const totalRevenue = () =>
-    (activeBookings() || []).reduce((sum, b) => sum + b.service_price, 0);
+    (activeBookings() || [])
+      .filter((b) => b.status !== "no_show")
+      .reduce((sum, b) => sum + b.service_price, 0);
 
   const handleCancel = (booking: BookingDetail) => {
     WebApp.showConfirm(
@@ -56,9 +58,7 @@ export default function AdminPage() {
         try {
           await adminApi.cancelBooking(booking.id);
           WebApp.HapticFeedback.notificationOccurred("success");
-          refetchToday();
-          refetchTomorrow();
-          refetchWeek();
+          refetchAll();
         } catch {
           WebApp.showAlert("Не удалось отменить");
         }
@@ -66,6 +66,29 @@ export default function AdminPage() {
     );
   };
 
+  const refetchAll = () => {
+    refetchToday();
+    refetchTomorrow();
+    refetchWeek();
+  };
+
+  // Local date/time strings compare lexicographically
+  const hasStarted = (b: BookingDetail) => {
+    const now = new Date();
+    const time = `${String(now.getHours()).padStart(2, "0")}:${String(now.getMinutes()).padStart(2, "0")}`;
+    return `${b.date} ${formatTime(b.start_time)}` <= `${today} ${time}`;
+  };
+
+  const handleOutcome = async (booking: BookingDetail, outcome: "completed" | "no_show") => {
+    try {
+      await adminApi.closeVisit(booking.id, outcome);
+      WebApp.HapticFeedback.notificationOccurred("success");
+      refetchAll();
+    } catch (e) {
+      WebApp.showAlert(e instanceof Error ? e.message : "Не удалось сохранить");
+    }
+  };
+
   const tabLabel = () => {
     switch (activeTab()) {
       case "today": return "Сегодня";
@@ -187,15 +210,60 @@ export default function AdminPage() {
                           @{b.client_username}
                         </div>
                       </Show>
+                      <Show when={!b.client_username && b.client_phone}>
+                        <a
+                          class="text-xs"
+                          style={{ color: "var(--link)" }}
+                          href={`tel:+${b.client_phone}`}
+                        >
+                          +{b.client_phone}
+                        </a>
+                      </Show>
                     </div>
                   </div>
-                  <button
-                    class="mt-2 text-xs px-3 py-1.5 rounded-xl w-full text-center"
-                    style={{ color: "#d32f2f", background: "#fce4e4" }}
-                    onClick={() => handleCancel(b)}
+                  <Show when={b.status === "completed"}>
+                    <div class="mt-2 text-xs" style={{ color: "#4caf50" }}>
+                      ✅ Визит закрыт · получено {formatPrice(b.amount_collected ?? 0)}
+                    </div>
+                  </Show>
+                  <Show when={b.status === "no_show"}>
+                    <div class="mt-2 text-xs" style={{ color: "#d32f2f" }}>
+                      🚫 Неявка
+                    </div>
+                  </Show>
+                  <Show when={b.status === "confirmed" && hasStarted(b)}>
+                    <div class="mt-2 flex gap-2">
+                      <button
+                        class="text-xs px-3 py-1.5 rounded-xl flex-1 text-center"
+                        style={{ color: "#2e7d32", background: "#e8f5e9" }}
+                        onClick={() => handleOutcome(b, "completed")}
+                      >
+                        ✅ Пришла
+                      </button>
+                      <button
+                        class="text-xs px-3 py-1.5 rounded-xl flex-1 text-center"
+                        style={{ color: "#d32f2f", background: "#fce4e4" }}
+                        onClick={() => handleOutcome(b, "no_show")}
+                      >
+                        🚫 Не пришла
+                      </button>
+                    </div>
+                  </Show>
+                  <Show
+                    when={
+                      b.status !== "completed" &&
+                      b.status !== "no_show" &&
+                      !(b.status === "confirmed" && hasStarted(b))
+                    }
                   >
-                    Отменить
-                  </button>
+                    <button
+                      class="mt-2 text-xs px-3 py-1.5 rounded-xl w-full text-center"
+                      style={{ color: "#d32f2f", background: "#fce4e4" }}
+                      onClick={() => handleCancel(b)}
+                    >
+                      Отменить
+                    </button>
+                  </Show>
                 </div>
               )}
             </For>
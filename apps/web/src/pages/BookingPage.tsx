@@ -1,6 +1,6 @@
 import { createResource, createSignal, For, Show, onCleanup } from "solid-js";
 import WebApp from "@twa-dev/sdk";
-import { api, type TimeBlock } from "../lib/api";
+import { api, type PaymentPlan, type SlotHold, type TimeBlock } from "../lib/api";
 import { goHome, goMyBookings } from "../lib/router";
 import { formatPrice, friendlyDate, formatTime } from "../lib/utils";
 import Loader from "../components/Loader";
@@ -11,26 +11,45 @@ interface Props {
   serviceName: string;
   servicePrice: number;
   withLowerLashes: boolean;
+  /** false: the booking is confirmed right away, paid at the visit. */
+  prepaymentRequired: boolean;
+  /** Open straight on this date (link from a waitlist offer). */
+  date?: string;
 }
 
 type Step = "date" | "time" | "confirm" | "paying" | "done";
 
 const PREPAID_AMOUNT = 500;
+/** Tip presets in RUB. */
+const TIP_OPTIONS = [0, 200, 300, 500];
 /** Max polling time before giving up (5 minutes). */
 const POLL_TIMEOUT_MS = 5 * 60 * 1000;
 
 export default function BookingPage(props: Props) {
-  const [step, setStep] = createSignal<Step>("date");
-  const [selectedDate, setSelectedDate] = createSignal<string>("");
+  const [step, setStep] = createSignal<Step>(props.date ? "time" : "date");
+  const [selectedDate, setSelectedDate] = createSignal<string>(props.date ?? "");
   const [selectedTime, setSelectedTime] = createSignal<TimeBlock | null>(null);
   const [loading, setLoading] = createSignal(false);
   const [error, setError] = createSignal("");
   const [paymentUrl, setPaymentUrl] = createSignal<string | null>(null);
+  const [contact, setContact] = createSignal("");
+  const [paymentPlan, setPaymentPlan] = createSignal<PaymentPlan>("deposit");
+  const [tip, setTip] = createSignal(0);
+  const [paidOnline, setPaidOnline] = createSignal(false);
+  const [awaitingApproval, setAwaitingApproval] = createSignal(false);
+  const [waitlisted, setWaitlisted] = createSignal(false);
+  const [hold, setHold] = createSignal<SlotHold | null>(null);
   let pollTimer: ReturnType<typeof setInterval> | undefined;
   let pollTimeout: ReturnType<typeof setTimeout> | undefined;
 
   onCleanup(() => stopPolling());
 
+  /** Charged online now: deposit or full price, plus the tip. */
+  const payNow = () =>
+    (paymentPlan() === "full"
+      ? props.servicePrice
+      : Math.min(PREPAID_AMOUNT, props.servicePrice)) + tip();
+
   // Fetch available times when date is selected
   const [timesData] = createResource(
     () => selectedDate(),
@@ -42,13 +61,47 @@ export default function BookingPage(props: Props) {
 
   const selectDate = (date: string) => {
     setSelectedDate(date);
+    setWaitlisted(false);
     setStep("time");
   };
 
-  const selectTime = (time: TimeBlock) => {
+  const joinWaitlist = async () => {
+    setLoading(true);
+    setError("");
+    try {
+      await api.joinWaitlist(props.serviceId, selectedDate());
+      WebApp.HapticFeedback.notificationOccurred("success");
+      setWaitlisted(true);
+    } catch (e: any) {
+      setError(e.message || "Не удалось встать в очередь");
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  /** Hold the block while the client confirms, so nobody takes it meanwhile. */
+  const selectTime = async (time: TimeBlock) => {
     WebApp.HapticFeedback.selectionChanged();
-    setSelectedTime(time);
-    setStep("confirm");
+    setLoading(true);
+    setError("");
+    try {
+      setHold(await api.createHold(props.serviceId, selectedDate(), time.start_time));
+      setSelectedTime(time);
+      setStep("confirm");
+    } catch (e: any) {
+      WebApp.HapticFeedback.notificationOccurred("error");
+      setError(e.message || "Это время уже занято");
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  const changeTime = () => {
+    const held = hold();
+    if (held) api.releaseHold(held.id).catch(() => {});
+    setHold(null);
+    setError("");
+    setStep("time");
   };
 
   const stopPolling = () => {
@@ -62,8 +115,12 @@ export default function BookingPage(props: Props) {
     pollTimer = setInterval(async () => {
       try {
         const status = await api.getBookingStatus(id);
-        if (status.payment_status === "paid" && status.status === "confirmed") {
+        if (
+          status.payment_status === "paid" &&
+          (status.status === "confirmed" || status.status === "awaiting_approval")
+        ) {
           stopPolling();
+          setAwaitingApproval(status.status === "awaiting_approval");
           WebApp.HapticFeedback.notificationOccurred("success");
           setStep("done");
         } else if (status.status === "expired" || status.status === "cancelled") {
@@ -97,17 +154,27 @@ export default function BookingPage(props: Props) {
         props.serviceId,
         selectedDate(),
         time.start_time,
-        props.withLowerLashes
+        props.withLowerLashes,
+        contact(),
+        paymentPlan(),
+        tip()
       );
 
+      setPaidOnline(!!result.payment_method);
       if (result.payment_url) {
         setPaymentUrl(result.payment_url);
         // Open payment page
         WebApp.openLink(result.payment_url);
         setStep("paying");
         startPolling(result.booking.id);
+      } else if (result.payment_method === "telegram") {
+        // Invoice is waiting in the bot chat
+        setPaymentUrl(null);
+        setStep("paying");
+        startPolling(result.booking.id);
       } else {
-        // No payment required (shouldn't happen, but fallback)
+        // No prepayment: confirmed right away (or sent to the master for approval)
+        setAwaitingApproval(result.booking.status === "awaiting_approval");
         WebApp.HapticFeedback.notificationOccurred("success");
         setStep("done");
       }
@@ -165,6 +232,7 @@ export default function BookingPage(props: Props) {
           <Calendar
             serviceId={props.serviceId}
             onSelect={selectDate}
+            waitlistMode
           />
         </div>
       </Show>
@@ -192,6 +260,21 @@ export default function BookingPage(props: Props) {
             <Show when={timesData()?.times?.length} fallback={
               <div class="text-center py-8" style={{ color: "var(--hint)" }}>
                 <p>Нет свободного времени</p>
+                <Show when={!waitlisted()} fallback={
+                  <p class="text-sm mt-3">🔔 Напишем в боте, как только время освободится</p>
+                }>
+                  <button
+                    class="mt-3 text-sm font-medium"
+                    style={{ color: "var(--link)" }}
+                    disabled={loading()}
+                    onClick={joinWaitlist}
+                  >
+                    🔔 Сообщить, если освободится
+                  </button>
+                </Show>
+                <Show when={error()}>
+                  <p class="text-sm mt-2" style={{ color: "#d32f2f" }}>{error()}</p>
+                </Show>
               </div>
             }>
               <div class="grid grid-cols-3 gap-2">
@@ -199,6 +282,7 @@ export default function BookingPage(props: Props) {
                   {(time) => (
                     <button
                       class="chip chip-inactive text-center justify-center"
+                      disabled={loading()}
                       onClick={() => selectTime(time)}
                     >
                       {formatTime(time.start_time)}
@@ -206,6 +290,9 @@ export default function BookingPage(props: Props) {
                   )}
                 </For>
               </div>
+              <Show when={error()}>
+                <p class="text-sm mt-2 text-center" style={{ color: "#d32f2f" }}>{error()}</p>
+              </Show>
             </Show>
           </Show>
 
@@ -245,6 +332,11 @@ export default function BookingPage(props: Props) {
                   {formatTime(selectedTime()!.start_time)} — {formatTime(selectedTime()!.end_time)}
                 </span>
               </div>
+              <Show when={hold()}>
+                <p class="text-xs text-right" style={{ color: "var(--hint)" }}>
+                  Время закреплено за тобой до {hold()!.expires_at.slice(11, 16)}
+                </p>
+              </Show>
               <div
                 class="flex justify-between pt-2 border-t"
                 style={{ "border-color": "var(--secondary-bg)" }}
@@ -257,27 +349,98 @@ export default function BookingPage(props: Props) {
             </div>
           </div>
 
-          {/* Prepayment info block */}
-          <div
-            class="mt-3 p-4 rounded-xl"
-            style={{
-              background: "var(--secondary-bg)",
-              border: "1px solid rgba(232, 160, 191, 0.2)",
-            }}
-          >
-            <div class="flex items-center gap-2 mb-2">
-              <span class="text-lg">💳</span>
-              <span class="font-semibold text-sm" style={{ color: "var(--text)" }}>
-                Предоплата {formatPrice(PREPAID_AMOUNT)}
-              </span>
+          <Show when={props.prepaymentRequired} fallback={
+            <div
+              class="mt-3 p-4 rounded-xl"
+              style={{
+                background: "var(--secondary-bg)",
+                border: "1px solid rgba(232, 160, 191, 0.2)",
+              }}
+            >
+              <div class="flex items-center gap-2 mb-2">
+                <span class="text-lg">🤝</span>
+                <span class="font-semibold text-sm" style={{ color: "var(--text)" }}>
+                  Оплата на месте
+                </span>
+              </div>
+              <p class="text-xs" style={{ color: "var(--hint)" }}>
+                Предоплата не нужна — запись подтверждается сразу.
+              </p>
+            </div>
+          }>
+            {/* Payment plan */}
+            <div
+              class="mt-3 p-4 rounded-xl"
+              style={{
+                background: "var(--secondary-bg)",
+                border: "1px solid rgba(232, 160, 191, 0.2)",
+              }}
+            >
+              <div class="flex items-center gap-2 mb-2">
+                <span class="text-lg">💳</span>
+                <span class="font-semibold text-sm" style={{ color: "var(--text)" }}>
+                  Оплата онлайн
+                </span>
+              </div>
+              <div class="grid grid-cols-2 gap-2">
+                <button
+                  class={`chip text-center justify-center ${paymentPlan() === "deposit" ? "chip-active" : "chip-inactive"}`}
+                  onClick={() => setPaymentPlan("deposit")}
+                >
+                  Предоплата {formatPrice(PREPAID_AMOUNT)}
+                </button>
+                <button
+                  class={`chip text-center justify-center ${paymentPlan() === "full" ? "chip-active" : "chip-inactive"}`}
+                  onClick={() => setPaymentPlan("full")}
+                >
+                  Полностью {formatPrice(props.servicePrice)}
+                </button>
+              </div>
+              <p class="text-xs mt-2" style={{ color: "var(--hint)" }}>
+                {paymentPlan() === "deposit"
+                  ? "Для подтверждения записи необходима предоплата. Остаток оплачивается на месте."
+                  : "Визит оплачен полностью — на месте ничего платить не нужно."}
+              </p>
+              <p class="text-xs mt-1" style={{ color: "var(--hint)" }}>
+                При отмене менее чем за 24 часа предоплата не возвращается.
+              </p>
             </div>
-            <p class="text-xs" style={{ color: "var(--hint)" }}>
-              Для подтверждения записи необходима предоплата. Остаток оплачивается на месте.
-            </p>
-            <p class="text-xs mt-1" style={{ color: "var(--hint)" }}>
-              При отмене менее чем за 24 часа предоплата не возвращается.
-            </p>
-          </div>
+
+            {/* Tip */}
+            <div class="card mt-3">
+              <p class="text-sm font-medium mb-2" style={{ color: "var(--hint)" }}>
+                Чаевые мастеру
+              </p>
+              <div class="grid grid-cols-4 gap-2">
+                <For each={TIP_OPTIONS}>
+                  {(amount) => (
+                    <button
+                      class={`chip text-center justify-center ${tip() === amount ? "chip-active" : "chip-inactive"}`}
+                      onClick={() => setTip(amount)}
+                    >
+                      {amount ? formatPrice(amount) : "Без"}
+                    </button>
+                  )}
+                </For>
+              </div>
+            </div>
+
+            {/* Receipt contact (54-FZ) */}
+            <div class="card mt-3">
+              <p class="text-sm font-medium mb-2" style={{ color: "var(--hint)" }}>
+                Телефон или email для чека
+              </p>
+              <input
+                type="text"
+                inputmode="email"
+                class="w-full p-3 rounded-xl text-sm"
+                style={{ background: "var(--secondary-bg)", color: "var(--text)" }}
+                placeholder="+7 999 123-45-67 или mail@example.com"
+                value={contact()}
+                onInput={(e) => setContact(e.currentTarget.value)}
+              />
+            </div>
+          </Show>
 
           <Show when={error()}>
             <div
@@ -293,13 +456,17 @@ export default function BookingPage(props: Props) {
             disabled={loading()}
             onClick={confirmBooking}
           >
-            {loading() ? "Создаю запись..." : `💳 Оплатить ${formatPrice(PREPAID_AMOUNT)}`}
+            {loading()
+              ? "Создаю запись..."
+              : props.prepaymentRequired
+                ? `💳 Оплатить ${formatPrice(payNow())}`
+                : "✓ Записаться"}
           </button>
 
           <button
             class="mt-3 text-sm font-medium w-full text-center"
             style={{ color: "var(--link)" }}
-            onClick={() => setStep("time")}
+            onClick={changeTime}
           >
             ← Другое время
           </button>
@@ -314,16 +481,20 @@ export default function BookingPage(props: Props) {
             Ожидание оплаты...
           </h3>
           <p class="text-sm" style={{ color: "var(--hint)" }}>
-            Завершите оплату в открывшемся окне.
+            {paymentUrl()
+              ? "Завершите оплату в открывшемся окне."
+              : "Счёт на оплату отправлен в чат с ботом — оплатите его там."}
           </p>
           <p class="text-sm mt-1" style={{ color: "var(--hint)" }}>
             Страница обновится автоматически после оплаты.
           </p>
 
           <div class="mt-6 flex flex-col gap-2">
-            <button class="btn-primary" onClick={reopenPayment}>
-              🔄 Открыть оплату заново
-            </button>
+            <Show when={paymentUrl()}>
+              <button class="btn-primary" onClick={reopenPayment}>
+                🔄 Открыть оплату заново
+              </button>
+            </Show>
             <button
               class="btn-secondary w-full"
               onClick={() => {
@@ -342,21 +513,27 @@ export default function BookingPage(props: Props) {
       <Show when={step() === "done"}>
         <div class="px-4 text-center animate-slide-up py-8">
           <p class="text-5xl mb-4">🎉</p>
-          <h3 class="text-xl font-bold mb-2">Ты записана!</h3>
+          <h3 class="text-xl font-bold mb-2">
+            {awaitingApproval() ? "Заявка отправлена!" : "Ты записана!"}
+          </h3>
           <p class="text-sm mb-1" style={{ color: "var(--hint)" }}>
             {props.serviceName}
           </p>
           <p class="text-sm" style={{ color: "var(--hint)" }}>
             {friendlyDate(selectedDate())} в {formatTime(selectedTime()!.start_time)}
           </p>
-          <div
-            class="mt-3 inline-flex items-center gap-1.5 px-3 py-1.5 rounded-full text-xs font-medium"
-            style={{ background: "#e8f5e9", color: "#2e7d32" }}
-          >
-            ✓ Предоплата {formatPrice(PREPAID_AMOUNT)} оплачена
-          </div>
+          <Show when={paidOnline()}>
+            <div
+              class="mt-3 inline-flex items-center gap-1.5 px-3 py-1.5 rounded-full text-xs font-medium"
+              style={{ background: "#e8f5e9", color: "#2e7d32" }}
+            >
+              ✓ Оплачено {formatPrice(payNow())}
+            </div>
+          </Show>
           <p class="text-xs mt-4" style={{ color: "var(--hint)" }}>
-            Мы напомним тебе за день до визита 💕
+            {awaitingApproval()
+              ? "Мастер подтвердит запись — мы пришлём сообщение в чат с ботом 💕"
+              : "Мы напомним тебе за день до визита 💕"}
           </p>
 
           <div class="mt-6 flex flex-col gap-2">
@@ -30,7 +30,7 @@ export default function HomePage() {
       setWithLowerLashes(false);
     } else {
       // Коррекция — go straight to booking
-      goBooking(service.id, service.name, service.price, false);
+      goBooking(service.id, service.name, service.price, false, service.prepayment_required);
     }
   };
 
@@ -42,7 +42,7 @@ export default function HomePage() {
     const addon = addonInfo();
     const totalPrice = svc.price + (withLowerLashes() && addon ? addon.price : 0);
     const name = withLowerLashes() ? `${svc.name} + нижние` : svc.name;
-    goBooking(svc.id, name, totalPrice, withLowerLashes());
+    goBooking(svc.id, name, totalPrice, withLowerLashes(), svc.prepayment_required);
   };
 
   const cancelSelection = () => {
@@ -60,6 +60,16 @@ export default function MyBookingsPage() {
   };
 
   const paymentBadge = (booking: BookingDetail) => {
+    if (booking.status === "awaiting_approval") {
+      return (
+        <div
+          class="inline-flex items-center gap-1 px-2 py-0.5 rounded-full text-xs font-medium mt-1"
+          style={{ background: "#e3f2fd", color: "#1565c0" }}
+        >
+          🙋 Ждёт подтверждения мастера
+        </div>
+      );
+    }
     if (booking.payment_status === "paid") {
       return (
         <div
@@ -0,0 +1,116 @@
+//! Manual approval of new bookings: the server decides which bookings wait for
+//! the master and sends Approve/Decline buttons, the bot handles the answer.
+
+const APPROVE_PREFIX: &str = "approve:";
+const DECLINE_PREFIX: &str = "decline:";
+
+/// Which new bookings wait for the master's approval (env `BOOKING_APPROVAL`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ApprovalMode {
+    /// Every booking is confirmed once paid.
+    #[default]
+    Off,
+    /// Only clients without an earlier confirmed or completed booking.
+    NewClients,
+    /// Every booking.
+    All,
+}
+
+impl ApprovalMode {
+    /// Parse `off`, `new_clients` or `all`.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "off" => Some(ApprovalMode::Off),
+            "new_clients" => Some(ApprovalMode::NewClients),
+            "all" => Some(ApprovalMode::All),
+            _ => None,
+        }
+    }
+
+    /// Whether a booking by a client who has (or hasn't) visited before needs approval.
+    pub fn applies(self, returning_client: bool) -> bool {
+        match self {
+            ApprovalMode::Off => false,
+            ApprovalMode::NewClients => !returning_client,
+            ApprovalMode::All => true,
+        }
+    }
+}
+
+/// The master's answer to an approval request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Approve,
+    Decline,
+}
+
+/// Callback data of the Approve/Decline buttons.
+pub fn callback_data(decision: Decision, booking_id: i64) -> String {
+    match decision {
+        Decision::Approve => format!("{}{}", APPROVE_PREFIX, booking_id),
+        Decision::Decline => format!("{}{}", DECLINE_PREFIX, booking_id),
+    }
+}
+
+/// Decision and booking id from callback data written by [`callback_data`].
+pub fn parse_callback(data: &str) -> Option<(Decision, i64)> {
+    if let Some(id) = data.strip_prefix(APPROVE_PREFIX) {
+        return Some((Decision::Approve, id.parse().ok()?));
+    }
+    let id = data.strip_prefix(DECLINE_PREFIX)?;
+    Some((Decision::Decline, id.parse().ok()?))
+}
+
+/// The Approve/Decline buttons as (text, callback data); the server sends them
+/// through [`keyboard_json`], the bot builds its own markup from the same pairs.
+pub fn buttons(booking_id: i64) -> [(&'static str, String); 2] {
+    [
+        ("✅ Подтвердить", callback_data(Decision::Approve, booking_id)),
+        ("❌ Отклонить", callback_data(Decision::Decline, booking_id)),
+    ]
+}
+
+/// Bot API `reply_markup` with the Approve/Decline buttons.
+pub fn keyboard_json(booking_id: i64) -> serde_json::Value {
+    let row: Vec<serde_json::Value> = buttons(booking_id)
+        .into_iter()
+        .map(|(text, data)| serde_json::json!({ "text": text, "callback_data": data }))
+        .collect();
+    serde_json::json!({ "inline_keyboard": [row] })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_applies() {
+        assert!(!ApprovalMode::Off.applies(false));
+        assert!(ApprovalMode::NewClients.applies(false));
+        assert!(!ApprovalMode::NewClients.applies(true));
+        assert!(ApprovalMode::All.applies(true));
+        assert_eq!(ApprovalMode::parse("new_clients"), Some(ApprovalMode::NewClients));
+        assert_eq!(ApprovalMode::parse("sometimes"), None);
+    }
+
+    #[test]
+    fn test_callback_roundtrip() {
+        for decision in [Decision::Approve, Decision::Decline] {
+            assert_eq!(parse_callback(&callback_data(decision, 17)), Some((decision, 17)));
+        }
+        assert_eq!(parse_callback("cancel:17"), None);
+        assert_eq!(parse_callback("approve:abc"), None);
+    }
+
+    #[test]
+    fn test_keyboard_json_matches_buttons() {
+        let keyboard = keyboard_json(17);
+        let row = keyboard["inline_keyboard"][0].as_array().unwrap();
+        for ((text, data), button) in buttons(17).iter().zip(row) {
+            assert_eq!(button["text"], *text);
+            assert_eq!(button["callback_data"], *data);
+            assert_eq!(parse_callback(data).map(|(_, id)| id), Some(17));
+        }
+        assert_eq!(row.len(), 2);
+    }
+}
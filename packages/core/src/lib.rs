@@ -0,0 +1,19 @@
+//! Shared code for the API server and the Telegram bot: schema migrations,
+//! domain models, booking queries and the business rules both processes apply.
+
+pub mod approval;
+pub mod migrations;
+pub mod models;
+pub mod payments;
+pub mod receipts;
+pub mod refunds;
+pub mod repo;
+pub mod rules;
+pub mod slots;
+pub mod standing;
+pub mod telegram_payments;
+#[cfg(any(test, feature = "test-support"))]
+pub mod testing;
+pub mod time;
+pub mod visits;
+pub mod yookassa;
@@ -0,0 +1,302 @@
+//! Versioned schema migrations.
+//!
+//! Numbered SQL files live in `apps/server/migrations/` and are embedded at compile
+//! time, so the server and the bot always carry the same list. Each file runs once,
+//! inside its own transaction, and is recorded in `_migrations` with a SHA-256
+//! checksum. Editing an applied file or running against a newer schema is an error.
+
+use anyhow::{bail, Context};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+
+/// One embedded migration file.
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    /// File name without `.sql`, e.g. `001_init`.
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+macro_rules! migration {
+    ($name:literal) => {
+        Migration {
+            name: $name,
+            sql: include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/../../apps/server/migrations/",
+                $name,
+                ".sql"
+            )),
+        }
+    };
+}
+
+/// All migrations in apply order. New files must be appended here.
+pub const MIGRATIONS: &[Migration] = &[
+    migration!("001_init"),
+    migration!("002_dedup_services"),
+    migration!("003_new_catalog"),
+    migration!("004_delete_old_services"),
+    migration!("005_smart_slots"),
+    migration!("006_payments"),
+    migration!("007_indexes"),
+    migration!("008_schedule_template"),
+    migration!("009_service_buffers"),
+    migration!("010_reschedule_count"),
+    migration!("011_cancellation_policy"),
+    migration!("012_refunds"),
+    migration!("013_payment_method"),
+    migration!("014_payment_discrepancies"),
+    migration!("015_client_contact"),
+    migration!("016_amount_due"),
+    migration!("017_no_prepayment"),
+    migration!("018_booking_approval"),
+    migration!("019_admin_bookings"),
+    migration!("020_visit_outcome"),
+    migration!("021_client_standing"),
+    migration!("022_clients"),
+    migration!("023_blocklist"),
+    migration!("024_waitlist"),
+    migration!("025_slot_holds"),
+    migration!("026_materialized_days"),
+];
+
+/// Hex SHA-256 of a migration's SQL.
+pub fn checksum(sql: &str) -> String {
+    hex::encode(Sha256::digest(sql.as_bytes()))
+}
+
+/// Bring the database schema up to date.
+pub async fn run(pool: &SqlitePool) -> anyhow::Result<()> {
+    run_list(pool, MIGRATIONS).await
+}
+
+async fn run_list(pool: &SqlitePool, migrations: &[Migration]) -> anyhow::Result<()> {
+    // Enable WAL mode for better concurrent access (not allowed inside a transaction)
+    sqlx::query("PRAGMA journal_mode=WAL").execute(pool).await?;
+
+    prepare_table(pool, migrations).await?;
+
+    for migration in migrations {
+        // Server and bot may start together: re-check under the write lock
+        let mut tx = pool.begin_with("BEGIN IMMEDIATE").await?;
+
+        let recorded: Option<Option<String>> =
+            sqlx::query_scalar("SELECT checksum FROM _migrations WHERE name = ?")
+                .bind(migration.name)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+        let expected = checksum(migration.sql);
+        match recorded {
+            Some(Some(actual)) if actual != expected => bail!(
+                "migration {} was edited after it was applied (checksum {} != {})",
+                migration.name,
+                actual,
+                expected
+            ),
+            Some(_) => continue,
+            None => {}
+        }
+
+        sqlx::raw_sql(migration.sql)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("migration {} failed", migration.name))?;
+        sqlx::query("INSERT INTO _migrations (name, checksum) VALUES (?, ?)")
+            .bind(migration.name)
+            .bind(&expected)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        tracing::info!("Applied migration: {}", migration.name);
+    }
+
+    tracing::info!("Database migrations up to date");
+    Ok(())
+}
+
+/// Create `_migrations`, adopt rows written before checksums existed and refuse
+/// to run against a schema this binary doesn't know.
+async fn prepare_table(pool: &SqlitePool, migrations: &[Migration]) -> anyhow::Result<()> {
+    let mut tx = pool.begin_with("BEGIN IMMEDIATE").await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS _migrations (
+            name TEXT PRIMARY KEY,
+            applied_at TEXT NOT NULL DEFAULT (datetime('now'))
+        )",
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let has_checksum: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('_migrations') WHERE name = 'checksum'",
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if !has_checksum {
+        sqlx::query("ALTER TABLE _migrations ADD COLUMN checksum TEXT")
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let applied: Vec<(String, Option<String>)> =
+        sqlx::query_as("SELECT name, checksum FROM _migrations ORDER BY name")
+            .fetch_all(&mut *tx)
+            .await?;
+
+    for (name, recorded) in applied {
+        let Some(migration) = migrations.iter().find(|m| m.name == name) else {
+            bail!(
+                "database has migration {} unknown to this build — deploy a newer binary",
+                name
+            );
+        };
+        // Legacy rows from the hand-written runner: trust the file as shipped today
+        if recorded.is_none() {
+            sqlx::query("UPDATE _migrations SET checksum = ? WHERE name = ?")
+                .bind(checksum(migration.sql))
+                .bind(&name)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn memory_db() -> SqlitePool {
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap()
+    }
+
+    async fn applied_names(pool: &SqlitePool) -> Vec<String> {
+        sqlx::query_scalar("SELECT name FROM _migrations ORDER BY name")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_migrations_match_files_on_disk() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../../apps/server/migrations");
+        let mut files: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .filter_map(|e| e.ok()?.file_name().into_string().ok())
+            .filter_map(|f| f.strip_suffix(".sql").map(String::from))
+            .collect();
+        files.sort();
+        let listed: Vec<&str> = MIGRATIONS.iter().map(|m| m.name).collect();
+        assert_eq!(files, listed);
+    }
+
+    #[test]
+    fn test_migrations_numbered_in_order() {
+        for (i, m) in MIGRATIONS.iter().enumerate() {
+            assert!(
+                m.name.starts_with(&format!("{:03}_", i + 1)),
+                "{} out of sequence",
+                m.name
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fresh_database_applies_all() {
+        let pool = memory_db().await;
+        run(&pool).await.unwrap();
+        assert_eq!(applied_names(&pool).await.len(), MIGRATIONS.len());
+
+        // Second run is a no-op
+        run(&pool).await.unwrap();
+        assert_eq!(applied_names(&pool).await.len(), MIGRATIONS.len());
+
+        let services: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM services")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(services, 3);
+    }
+
+    #[tokio::test]
+    async fn test_edited_migration_fails() {
+        let pool = memory_db().await;
+        run(&pool).await.unwrap();
+        sqlx::query("UPDATE _migrations SET checksum = 'stale' WHERE name = '002_dedup_services'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let err = run(&pool).await.unwrap_err();
+        assert!(err.to_string().contains("002_dedup_services"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_migration_fails() {
+        let pool = memory_db().await;
+        run_list(&pool, &MIGRATIONS[..2]).await.unwrap();
+        let err = run_list(&pool, &MIGRATIONS[..1]).await.unwrap_err();
+        assert!(err.to_string().contains("002_dedup_services"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_migration_rolls_back() {
+        let pool = memory_db().await;
+        let broken = [
+            MIGRATIONS[0],
+            Migration {
+                name: "002_broken",
+                sql: "CREATE TABLE extra (id INTEGER); INSERT INTO missing VALUES (1);",
+            },
+        ];
+        assert!(run_list(&pool, &broken).await.is_err());
+        assert_eq!(applied_names(&pool).await, vec!["001_init"]);
+        let extra: bool = sqlx::query_scalar(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE name = 'extra'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert!(!extra);
+    }
+
+    #[tokio::test]
+    async fn test_adopts_legacy_rows() {
+        let pool = memory_db().await;
+        // Layout written by the old hand-written runner
+        sqlx::query(
+            "CREATE TABLE _migrations (
+                name TEXT PRIMARY KEY,
+                applied_at TEXT NOT NULL DEFAULT (datetime('now'))
+            )",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::raw_sql(MIGRATIONS[0].sql).execute(&pool).await.unwrap();
+        sqlx::query("INSERT INTO _migrations (name) VALUES ('001_init')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        run(&pool).await.unwrap();
+
+        let missing: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM _migrations WHERE checksum IS NULL")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(missing, 0);
+        assert_eq!(applied_names(&pool).await.len(), MIGRATIONS.len());
+    }
+}
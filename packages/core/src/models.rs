@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+
+use crate::rules;
+
+/// `client_tg_id` of a booking whose client has no Telegram (entered by the master).
+pub const NO_TELEGRAM: i64 = 0;
+
+// ── Database models ──
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Service {
+    pub id: i64,
+    pub name: String,
+    pub description: String,
+    pub price: i64,
+    pub duration_min: i64,
+    pub is_active: bool,
+    pub sort_order: i64,
+    pub service_type: String,
+    /// Blocked time before the appointment (prep), never shown as a booking.
+    pub buffer_before_min: i64,
+    /// Blocked time after the appointment (tool sanitizing, cleanup).
+    pub buffer_after_min: i64,
+    /// `false`: bookings are confirmed right away, paid at the visit.
+    pub prepayment_required: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AvailableSlot {
+    pub id: i64,
+    pub date: String,
+    pub start_time: String,
+    pub end_time: String,
+    pub is_booked: bool,
+    pub booking_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Booking {
+    pub id: i64,
+    pub service_id: i64,
+    pub slot_id: i64,
+    /// [`NO_TELEGRAM`] for clients the master booked by phone.
+    pub client_tg_id: i64,
+    pub client_username: Option<String>,
+    pub client_first_name: String,
+    pub status: String,
+    pub reminder_sent: bool,
+    pub created_at: String,
+    pub cancelled_at: Option<String>,
+    pub date: Option<String>,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub with_lower_lashes: bool,
+    pub payment_status: String,
+    pub yookassa_payment_id: Option<String>,
+    pub prepaid_amount: i64,
+    pub reschedule_count: i64,
+    /// yookassa | telegram | cash | none (no prepayment)
+    pub payment_method: String,
+    pub telegram_charge_id: Option<String>,
+    /// Receipt contact (54-FZ).
+    pub client_phone: Option<String>,
+    pub client_email: Option<String>,
+    /// Service + addon price at booking time, in RUB.
+    pub amount_due: i64,
+    /// Tip paid online on top of the price; part of `prepaid_amount`.
+    pub tip_amount: i64,
+    /// The master approves the booking before it is confirmed.
+    pub needs_approval: bool,
+    /// Taken at the visit, in RUB; set when the master closes it as completed.
+    pub amount_collected: Option<i64>,
+    pub closed_at: Option<String>,
+    /// Completed/No-show requests sent to the master (0, 1 after the visit, 2 next morning).
+    pub outcome_prompts: i64,
+    /// client | admin; `None` while active and for older cancellations.
+    pub cancelled_by: Option<String>,
+}
+
+/// A client's profile with stats derived from their bookings.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct Client {
+    pub tg_id: i64,
+    pub username: Option<String>,
+    pub first_name: String,
+    pub last_name: Option<String>,
+    pub phone: Option<String>,
+    pub lash_style: String,
+    pub curl: String,
+    pub length: String,
+    pub allergies: String,
+    pub notes: String,
+    #[serde(serialize_with = "serialize_tags")]
+    pub tags: String,
+    pub created_at: String,
+    pub last_seen_at: String,
+    /// Completed visits.
+    pub visit_count: i64,
+    /// Taken at visits plus prepayments kept, in RUB.
+    pub lifetime_spend: i64,
+    /// Date of the latest completed visit.
+    pub last_visit: Option<String>,
+}
+
+/// Comma-separated tags as a JSON array.
+fn serialize_tags<S: serde::Serializer>(tags: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(tags.split(',').map(str::trim).filter(|t| !t.is_empty()))
+}
+
+/// Someone the master refuses to book; matched by any of the identifiers set.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct BlockedClient {
+    pub id: i64,
+    pub tg_id: Option<i64>,
+    /// Without `@`, matched case-insensitively.
+    pub username: Option<String>,
+    pub phone: Option<String>,
+    /// For the master only, never shown to the client.
+    pub reason: String,
+    pub created_at: String,
+}
+
+/// What a blocked user is told; deliberately says nothing about the block.
+pub const BLOCKED_NOTICE: &str = "Онлайн-запись сейчас недоступна";
+
+/// One refund tier of the cancellation policy (`service_id` NULL = default policy).
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CancellationTier {
+    pub id: i64,
+    pub service_id: Option<i64>,
+    /// Applies when cancelling strictly more than this many hours ahead.
+    pub more_than_hours: i64,
+    pub refund_percent: i64,
+}
+
+/// One refund request for (part of) a booking's prepayment.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Refund {
+    pub id: i64,
+    pub booking_id: i64,
+    pub payment_id: String,
+    /// Set once YooKassa accepted the request.
+    pub yookassa_refund_id: Option<String>,
+    pub amount: i64,
+    /// pending | pending_manual (Telegram Payments, made by the master) | succeeded | canceled | failed
+    pub status: String,
+    pub reason: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+// ── Read models ──
+
+/// Booking joined with its service, as shown to clients, the admin and the bot.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct BookingDetail {
+    pub id: i64,
+    pub service_name: String,
+    pub service_price: i64,
+    pub date: String,
+    pub start_time: String,
+    pub end_time: String,
+    pub client_tg_id: i64,
+    pub client_username: Option<String>,
+    pub client_first_name: String,
+    pub status: String,
+    pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub with_lower_lashes: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_price: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payment_status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prepaid_amount: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tip_amount: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_phone: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount_collected: Option<i64>,
+}
+
+impl BookingDetail {
+    /// Left to collect at the visit, in RUB (0 once the visit is closed).
+    pub fn amount_to_collect(&self) -> i64 {
+        if self.is_closed() {
+            return 0;
+        }
+        rules::amount_to_collect(
+            self.total_price.unwrap_or(self.service_price),
+            self.prepaid_amount.unwrap_or(0),
+            self.tip_amount.unwrap_or(0),
+            self.payment_status.as_deref().unwrap_or("none"),
+        )
+    }
+
+    /// Marked completed or no-show.
+    pub fn is_closed(&self) -> bool {
+        matches!(self.status.as_str(), "completed" | "no_show")
+    }
+
+    /// "@username" when known, otherwise the first name (with the phone, if any).
+    pub fn client_mention(&self) -> String {
+        match (&self.client_username, &self.client_phone) {
+            (Some(username), _) => format!("@{}", username),
+            (None, Some(phone)) => format!("{} (+{})", self.client_first_name, phone),
+            (None, None) => self.client_first_name.clone(),
+        }
+    }
+}
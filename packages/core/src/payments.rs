@@ -0,0 +1,293 @@
+//! Payment providers behind one interface, so the booking flow doesn't depend on
+//! YooKassa directly and can run offline (tests, `PAYMENT_PROVIDER=mock`).
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+
+use crate::receipts::ReceiptData;
+use crate::yookassa::{self, ReceiptConfig, YooKassaProvider};
+
+/// What to charge for.
+#[derive(Debug, Clone)]
+pub struct PaymentRequest<'a> {
+    pub booking_id: i64,
+    pub amount: i64,
+    pub description: &'a str,
+    /// Where the client lands after paying.
+    pub return_url: &'a str,
+    /// Fiscal receipt data; attached when the provider fiscalizes.
+    pub receipt: Option<ReceiptData>,
+}
+
+/// What to refund.
+#[derive(Debug, Clone)]
+pub struct RefundRequest<'a> {
+    pub payment_id: &'a str,
+    pub amount: i64,
+    /// Must be stable per refund so retries don't refund twice.
+    pub idempotence_key: &'a str,
+    /// Refund receipt data, same lines as the payment's.
+    pub receipt: Option<&'a ReceiptData>,
+}
+
+/// A payment as accepted by the provider.
+#[derive(Debug, Clone)]
+pub struct CreatedPayment {
+    pub id: String,
+    pub confirmation_url: String,
+    /// pending | succeeded (the mock confirms immediately)
+    pub status: String,
+}
+
+/// Current state of a payment on the provider side.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentInfo {
+    pub id: String,
+    /// pending | waiting_for_capture | succeeded | canceled
+    pub status: String,
+    pub booking_id: Option<i64>,
+    /// Charged amount in rubles.
+    pub amount: i64,
+    /// Refunded so far, in rubles.
+    pub refunded: i64,
+}
+
+/// A refund as accepted by the provider.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefundCreated {
+    pub id: String,
+    /// pending | succeeded | canceled
+    pub status: String,
+}
+
+/// Webhook notification, already decoded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebhookEvent {
+    PaymentSucceeded { payment_id: String, booking_id: Option<i64> },
+    PaymentCanceled { payment_id: String, booking_id: Option<i64> },
+    Refund { refund_id: String, status: String },
+    /// Events we don't act on.
+    Other(String),
+}
+
+#[async_trait]
+pub trait PaymentProvider: Send + Sync {
+    /// Short name for logs.
+    fn name(&self) -> &'static str;
+
+    async fn create_payment(&self, request: &PaymentRequest<'_>) -> anyhow::Result<CreatedPayment>;
+
+    async fn refund(&self, request: &RefundRequest<'_>) -> anyhow::Result<RefundCreated>;
+
+    async fn fetch_payment(&self, payment_id: &str) -> anyhow::Result<PaymentInfo>;
+
+    fn parse_webhook(&self, body: &[u8]) -> anyhow::Result<WebhookEvent>;
+
+    /// Payments need [`PaymentRequest::receipt`] (client contact is mandatory).
+    fn receipts_required(&self) -> bool {
+        false
+    }
+
+    /// Networks webhooks are sent from (CIDR); `None` accepts any source.
+    fn webhook_sources(&self) -> Option<&'static [&'static str]> {
+        None
+    }
+}
+
+/// Build the provider selected by `PAYMENT_PROVIDER` (`yookassa` by default, or `mock`).
+pub fn from_env() -> anyhow::Result<Arc<dyn PaymentProvider>> {
+    let kind = std::env::var("PAYMENT_PROVIDER").unwrap_or_else(|_| "yookassa".into());
+    match kind.as_str() {
+        "yookassa" => {
+            let shop_id = std::env::var("YOOKASSA_SHOP_ID").unwrap_or_default();
+            let secret_key = std::env::var("YOOKASSA_SECRET_KEY").unwrap_or_default();
+            let base_url = std::env::var("YOOKASSA_API_URL")
+                .unwrap_or_else(|_| yookassa::DEFAULT_BASE_URL.into());
+            if shop_id.is_empty() {
+                tracing::warn!("YOOKASSA_SHOP_ID not set — payments and refunds will fail (PREPAYMENT_REQUIRED=false books without payment)");
+            }
+            let mut provider = YooKassaProvider::new(shop_id, secret_key, base_url);
+            if std::env::var("YOOKASSA_RECEIPTS").is_ok_and(|v| v == "true" || v == "1") {
+                provider = provider.with_receipts(ReceiptConfig::from_env()?);
+            }
+            Ok(Arc::new(provider))
+        }
+        "mock" => {
+            tracing::warn!("PAYMENT_PROVIDER=mock — payments are confirmed without charging");
+            Ok(Arc::new(MockProvider::auto_confirm()))
+        }
+        other => anyhow::bail!("PAYMENT_PROVIDER must be yookassa or mock, got {}", other),
+    }
+}
+
+// ── In-process fake ──
+
+/// Fake provider keeping payments and refunds in memory.
+///
+/// Webhooks use the YooKassa JSON format, so tests can replay real payloads.
+#[derive(Debug, Default)]
+pub struct MockProvider {
+    /// Confirm payments on creation instead of leaving them pending.
+    auto_confirm: bool,
+    state: Mutex<MockState>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    next_id: u64,
+    payments: HashMap<String, PaymentInfo>,
+    /// Idempotence key → refund, plus the amount refunded.
+    refunds: HashMap<String, (RefundCreated, i64)>,
+}
+
+impl MockProvider {
+    /// Payments stay `pending` until [`MockProvider::set_status`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Payments succeed immediately (dev mode).
+    pub fn auto_confirm() -> Self {
+        Self {
+            auto_confirm: true,
+            ..Self::default()
+        }
+    }
+
+    /// Change a payment's status, as if the client paid or abandoned it.
+    pub fn set_status(&self, payment_id: &str, status: &str) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(payment) = state.payments.get_mut(payment_id) {
+            payment.status = status.to_string();
+        }
+    }
+
+    /// Total refunded for a payment.
+    pub fn refunded(&self, payment_id: &str) -> i64 {
+        let state = self.state.lock().unwrap();
+        state
+            .refunds
+            .iter()
+            .filter(|(key, _)| key.starts_with(&format!("{}:", payment_id)))
+            .map(|(_, (_, amount))| amount)
+            .sum()
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for MockProvider {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    async fn create_payment(&self, request: &PaymentRequest<'_>) -> anyhow::Result<CreatedPayment> {
+        let mut state = self.state.lock().unwrap();
+        state.next_id += 1;
+        let id = format!("mock-pay-{}", state.next_id);
+        let status = if self.auto_confirm { "succeeded" } else { "pending" };
+        state.payments.insert(
+            id.clone(),
+            PaymentInfo {
+                id: id.clone(),
+                status: status.to_string(),
+                booking_id: Some(request.booking_id),
+                amount: request.amount,
+                refunded: 0,
+            },
+        );
+        Ok(CreatedPayment {
+            id,
+            confirmation_url: request.return_url.to_string(),
+            status: status.to_string(),
+        })
+    }
+
+    async fn refund(&self, request: &RefundRequest<'_>) -> anyhow::Result<RefundCreated> {
+        let (payment_id, amount) = (request.payment_id, request.amount);
+        let mut state = self.state.lock().unwrap();
+        // Keys are scoped per payment so `refunded()` can sum them
+        let key = format!("{}:{}", payment_id, request.idempotence_key);
+        if let Some((refund, _)) = state.refunds.get(&key) {
+            return Ok(refund.clone());
+        }
+        state.next_id += 1;
+        let refund = RefundCreated {
+            id: format!("mock-refund-{}", state.next_id),
+            status: "succeeded".into(),
+        };
+        state.refunds.insert(key, (refund.clone(), amount));
+        if let Some(payment) = state.payments.get_mut(payment_id) {
+            payment.refunded += amount;
+        }
+        Ok(refund)
+    }
+
+    async fn fetch_payment(&self, payment_id: &str) -> anyhow::Result<PaymentInfo> {
+        let state = self.state.lock().unwrap();
+        state
+            .payments
+            .get(payment_id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("unknown mock payment {}", payment_id))
+    }
+
+    fn parse_webhook(&self, body: &[u8]) -> anyhow::Result<WebhookEvent> {
+        yookassa::parse_event(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> PaymentRequest<'static> {
+        PaymentRequest {
+            booking_id: 7,
+            amount: 500,
+            description: "Предоплата",
+            return_url: "https://example.com",
+            receipt: None,
+        }
+    }
+
+    fn refund<'a>(payment_id: &'a str, amount: i64, key: &'a str) -> RefundRequest<'a> {
+        RefundRequest {
+            payment_id,
+            amount,
+            idempotence_key: key,
+            receipt: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mock_payment_lifecycle() {
+        let provider = MockProvider::new();
+        let payment = provider.create_payment(&request()).await.unwrap();
+        assert_eq!(payment.status, "pending");
+
+        provider.set_status(&payment.id, "succeeded");
+        let info = provider.fetch_payment(&payment.id).await.unwrap();
+        assert_eq!(info.status, "succeeded");
+        assert_eq!(info.booking_id, Some(7));
+        assert_eq!(info.amount, 500);
+
+        let auto = MockProvider::auto_confirm();
+        assert_eq!(auto.create_payment(&request()).await.unwrap().status, "succeeded");
+    }
+
+    #[tokio::test]
+    async fn test_mock_refund_is_idempotent() {
+        let provider = MockProvider::new();
+        let first = provider.refund(&refund("p1", 200, "refund-1")).await.unwrap();
+        let retry = provider.refund(&refund("p1", 200, "refund-1")).await.unwrap();
+        assert_eq!(first, retry);
+        provider.refund(&refund("p1", 100, "refund-2")).await.unwrap();
+        assert_eq!(provider.refunded("p1"), 300);
+
+        let payment = provider.create_payment(&request()).await.unwrap();
+        provider.refund(&refund(&payment.id, 150, "refund-3")).await.unwrap();
+        assert_eq!(provider.fetch_payment(&payment.id).await.unwrap().refunded, 150);
+    }
+}
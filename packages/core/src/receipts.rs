@@ -0,0 +1,313 @@
+//! Fiscal receipts (54-FZ) for prepayments and refunds.
+//!
+//! Bookings carry the client's contact; the lines come from what the booking was
+//! charged: the service with the lower-lashes addon at `amount_due`, and the tip.
+//! Providers that fiscalize (YooKassa with receipts enabled) split the charged amount
+//! across the lines: a payment pays the tip first, a refund returns the service first.
+
+use sqlx::SqlitePool;
+
+/// Where the receipt is sent. At least one field is set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Customer {
+    /// Digits only, `7XXXXXXXXXX`.
+    pub phone: Option<String>,
+    pub email: Option<String>,
+}
+
+/// One position of the receipt at its full price.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceiptLine {
+    pub description: String,
+    /// Full price in rubles.
+    pub price: i64,
+    /// Tips are charged at their own price, never a share of a deposit.
+    pub tip: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceiptData {
+    pub customer: Customer,
+    pub lines: Vec<ReceiptLine>,
+    /// What the booking is charged online (`prepaid_amount`), in rubles.
+    pub paid: i64,
+}
+
+/// Normalize a Russian phone number to `7XXXXXXXXXX`.
+pub fn normalize_phone(input: &str) -> Option<String> {
+    let digits: String = input.chars().filter(|c| c.is_ascii_digit()).collect();
+    match digits.len() {
+        10 if digits.starts_with('9') => Some(format!("7{}", digits)),
+        11 if digits.starts_with('7') || digits.starts_with('8') => Some(format!("7{}", &digits[1..])),
+        _ => None,
+    }
+}
+
+/// Light check: the provider validates the address itself.
+pub fn is_valid_email(input: &str) -> bool {
+    let Some((local, domain)) = input.split_once('@') else {
+        return false;
+    };
+    !local.is_empty() && domain.contains('.') && !input.contains(char::is_whitespace)
+}
+
+/// Split `amount` rubles across `lines`, in kopecks.
+///
+/// Tip lines get their own price first, as far as `amount` goes; the rest is split
+/// over the other lines in proportion to their prices. The last of them takes the
+/// rounding remainder so the parts add up exactly.
+pub fn split_amount(lines: &[ReceiptLine], amount: i64) -> Vec<i64> {
+    let mut left = amount * 100;
+    let mut parts = vec![0; lines.len()];
+    for (part, line) in parts.iter_mut().zip(lines).filter(|(_, l)| l.tip) {
+        *part = (line.price * 100).min(left);
+        left -= *part;
+    }
+
+    let others: Vec<usize> = (0..lines.len()).filter(|&i| !lines[i].tip).collect();
+    let prices: Vec<i64> = lines.iter().map(|l| l.price).collect();
+    if others.is_empty() {
+        // Tips only: anything above them stays on the last one
+        if let Some(part) = parts.last_mut() {
+            *part += left;
+        }
+    } else {
+        spread(&mut parts, &others, &prices, left);
+    }
+    parts
+}
+
+/// Split a refund of `amount` rubles out of `paid` across `lines`, in kopecks.
+///
+/// Each line gives back at most what [`split_amount`] charged it for `paid`: the
+/// service lines first, in proportion, then the tips. A partial refund so never
+/// returns a tip while the service keeps the money.
+pub fn split_refund(lines: &[ReceiptLine], paid: i64, amount: i64) -> Vec<i64> {
+    let charged = split_amount(lines, paid);
+    let mut left = amount * 100;
+    let mut parts = vec![0; lines.len()];
+
+    let services: Vec<usize> = (0..lines.len()).filter(|&i| !lines[i].tip).collect();
+    let services_paid: i64 = services.iter().map(|&i| charged[i]).sum();
+    let to_services = left.min(services_paid);
+    spread(&mut parts, &services, &charged, to_services);
+    left -= to_services;
+
+    for i in (0..lines.len()).filter(|&i| lines[i].tip) {
+        parts[i] = charged[i].min(left);
+        left -= parts[i];
+    }
+    // More than was paid: the provider rejects it, but keep the sum exact
+    if let Some(part) = parts.last_mut() {
+        *part += left;
+    }
+    parts
+}
+
+/// Spread `total` kopecks over `parts[indices]` in proportion to `weights`; the
+/// last index takes the rounding remainder (all of it when the weights are zero).
+fn spread(parts: &mut [i64], indices: &[usize], weights: &[i64], total: i64) {
+    let Some(&last) = indices.last() else {
+        return;
+    };
+    let weight: i64 = indices.iter().map(|&i| weights[i]).sum();
+    if weight > 0 {
+        for &i in indices {
+            parts[i] = total * weights[i] / weight;
+        }
+    }
+    let assigned: i64 = indices.iter().map(|&i| parts[i]).sum();
+    parts[last] += total - assigned;
+}
+
+/// Whether `amount` covers the whole order (`full_prepayment`) or part of it.
+pub fn is_full_prepayment(lines: &[ReceiptLine], amount: i64) -> bool {
+    amount >= lines.iter().map(|l| l.price).sum::<i64>()
+}
+
+#[derive(sqlx::FromRow)]
+struct BookingRow {
+    client_phone: Option<String>,
+    client_email: Option<String>,
+    service_name: String,
+    service_price: i64,
+    with_lower_lashes: bool,
+    amount_due: i64,
+    tip_amount: i64,
+    prepaid_amount: i64,
+}
+
+/// Receipt data for a booking; `None` when the client left no contact.
+///
+/// Prices are the ones the booking was made at, so a later price change doesn't alter
+/// a refund receipt. Bookings from before `amount_due` fall back to the current prices.
+pub async fn for_booking(db: &SqlitePool, booking_id: i64) -> Result<Option<ReceiptData>, sqlx::Error> {
+    let row: Option<BookingRow> = sqlx::query_as(
+        "SELECT b.client_phone, b.client_email, s.name AS service_name, s.price AS service_price,
+         b.with_lower_lashes, b.amount_due, b.tip_amount, b.prepaid_amount
+         FROM bookings b JOIN services s ON s.id = b.service_id
+         WHERE b.id = ?",
+    )
+    .bind(booking_id)
+    .fetch_optional(db)
+    .await?;
+
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    if row.client_phone.is_none() && row.client_email.is_none() {
+        return Ok(None);
+    }
+
+    let mut lines = Vec::new();
+    if row.amount_due > 0 {
+        let description = if row.with_lower_lashes {
+            format!("{} + нижние ресницы", row.service_name)
+        } else {
+            row.service_name
+        };
+        lines.push(ReceiptLine {
+            description,
+            price: row.amount_due,
+            tip: false,
+        });
+    } else {
+        lines.push(ReceiptLine {
+            description: row.service_name,
+            price: row.service_price,
+            tip: false,
+        });
+    }
+    if row.amount_due <= 0 && row.with_lower_lashes {
+        let addon: Option<(String, i64)> = sqlx::query_as(
+            "SELECT name, price FROM services WHERE service_type = 'addon' AND is_active = 1 LIMIT 1",
+        )
+        .fetch_optional(db)
+        .await?;
+        let (description, price) = addon.unwrap_or_else(|| ("Нижние ресницы".into(), 500));
+        lines.push(ReceiptLine {
+            description,
+            price,
+            tip: false,
+        });
+    }
+    if row.tip_amount > 0 {
+        lines.push(ReceiptLine {
+            description: "Чаевые мастеру".into(),
+            price: row.tip_amount,
+            tip: true,
+        });
+    }
+
+    Ok(Some(ReceiptData {
+        customer: Customer {
+            phone: row.client_phone,
+            email: row.client_email,
+        },
+        lines,
+        paid: row.prepaid_amount,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn line(price: i64) -> ReceiptLine {
+        ReceiptLine {
+            description: "x".into(),
+            price,
+            tip: false,
+        }
+    }
+
+    fn tip(price: i64) -> ReceiptLine {
+        ReceiptLine {
+            description: "Чаевые мастеру".into(),
+            price,
+            tip: true,
+        }
+    }
+
+    #[test]
+    fn test_normalize_phone() {
+        assert_eq!(normalize_phone("+7 (999) 123-45-67").as_deref(), Some("79991234567"));
+        assert_eq!(normalize_phone("89991234567").as_deref(), Some("79991234567"));
+        assert_eq!(normalize_phone("9991234567").as_deref(), Some("79991234567"));
+        assert_eq!(normalize_phone("12345"), None);
+    }
+
+    #[test]
+    fn test_email_check() {
+        assert!(is_valid_email("anya@mail.ru"));
+        assert!(!is_valid_email("anya"));
+        assert!(!is_valid_email("@mail.ru"));
+        assert!(!is_valid_email("an ya@mail.ru"));
+    }
+
+    #[test]
+    fn test_split_proportional_and_exact() {
+        // 500 ₽ prepayment on 2500 + 500: 416.66 + 83.34
+        let parts = split_amount(&[line(2500), line(500)], 500);
+        assert_eq!(parts, vec![41_666, 8_334]);
+        assert_eq!(parts.iter().sum::<i64>(), 50_000);
+    }
+
+    #[test]
+    fn test_split_single_line() {
+        assert_eq!(split_amount(&[line(3000)], 500), vec![50_000]);
+        assert_eq!(split_amount(&[line(0)], 500), vec![50_000]);
+    }
+
+    #[test]
+    fn test_split_tip_at_its_own_amount() {
+        // 500 ₽ deposit + 300 ₽ tip on a 3000 ₽ service
+        assert_eq!(split_amount(&[line(3000), tip(300)], 800), vec![50_000, 30_000]);
+        // Full prepayment
+        assert_eq!(split_amount(&[line(3000), tip(300)], 3300), vec![300_000, 30_000]);
+        // A refund smaller than the tip
+        assert_eq!(split_amount(&[line(3000), tip(300)], 100), vec![0, 10_000]);
+    }
+
+    #[test]
+    fn test_refund_returns_service_before_tip() {
+        let lines = [line(3000), tip(300)];
+        // Half of a full 3300 ₽ payment comes off the service
+        assert_eq!(split_refund(&lines, 3300, 1650), vec![165_000, 0]);
+        // All of it returns each line's share
+        assert_eq!(split_refund(&lines, 3300, 3300), vec![300_000, 30_000]);
+        // 500 ₽ deposit + 300 ₽ tip: the tip only once the deposit is back
+        assert_eq!(split_refund(&lines, 800, 600), vec![50_000, 10_000]);
+        assert_eq!(split_refund(&[line(2500), line(500)], 500, 250), vec![20_833, 4_167]);
+    }
+
+    #[tokio::test]
+    async fn test_lines_from_booked_amounts() {
+        let db = testing::memory_db().await;
+        let booking = testing::paid_booking(&db, "2099-01-01", "10:00", "12:00", 500).await;
+        sqlx::query(
+            "UPDATE bookings SET with_lower_lashes = 1, client_phone = '79991234567',
+             amount_due = 3000, tip_amount = 300",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        // The price changed after the booking was made
+        sqlx::query("UPDATE services SET price = price + 1000")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let receipt = for_booking(&db, booking.id).await.unwrap().unwrap();
+        let prices: Vec<(i64, bool)> = receipt.lines.iter().map(|l| (l.price, l.tip)).collect();
+        assert_eq!(prices, vec![(3000, false), (300, true)]);
+        assert!(receipt.lines[0].description.ends_with("+ нижние ресницы"));
+    }
+
+    #[test]
+    fn test_full_prepayment() {
+        assert!(!is_full_prepayment(&[line(2500)], 500));
+        assert!(is_full_prepayment(&[line(400), line(100)], 500));
+    }
+}
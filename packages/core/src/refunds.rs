@@ -0,0 +1,521 @@
+//! Refunds on cancellation, applied the same way from the web app, the admin
+//! panel and the bot.
+
+use sqlx::SqlitePool;
+
+use crate::models::{Booking, CancellationTier, Refund};
+use crate::rules::{self, RefundDecision};
+use crate::time::hours_until;
+use crate::payments::{PaymentProvider, RefundRequest};
+use crate::receipts;
+
+/// Cancellation tiers for a service: its own rows, or the default policy.
+pub async fn policy_for_service(
+    db: &SqlitePool,
+    service_id: i64,
+) -> Result<Vec<CancellationTier>, sqlx::Error> {
+    sqlx::query_as::<_, CancellationTier>(
+        "SELECT id, service_id, more_than_hours, refund_percent FROM cancellation_tiers
+         WHERE service_id = ?
+            OR (service_id IS NULL
+                AND NOT EXISTS (SELECT 1 FROM cancellation_tiers WHERE service_id = ?))
+         ORDER BY more_than_hours DESC",
+    )
+    .bind(service_id)
+    .bind(service_id)
+    .fetch_all(db)
+    .await
+}
+
+/// Evaluate the cancellation policy for cancelling `booking` now.
+///
+/// - `admin_refund`: amount chosen by the admin, bypasses the policy tiers.
+///
+/// A booking the master hasn't approved yet is always refunded in full.
+pub async fn evaluate_cancellation(
+    db: &SqlitePool,
+    booking: &Booking,
+    admin_refund: Option<i64>,
+) -> Result<Option<RefundDecision>, sqlx::Error> {
+    let admin_refund = admin_refund
+        .or((booking.status == "awaiting_approval").then_some(booking.prepaid_amount));
+    let tiers = policy_for_service(db, booking.service_id).await?;
+    let date = booking.date.as_deref().unwrap_or("2099-01-01");
+    let time = booking.start_time.as_deref().unwrap_or("00:00");
+    // Default to refundable on parse error
+    let hours_left = hours_until(date, time).unwrap_or(i64::MAX);
+    Ok(rules::evaluate_refund(
+        &tiers,
+        &booking.payment_status,
+        booking.prepaid_amount,
+        admin_refund,
+        hours_left,
+    ))
+}
+
+/// What a cancellation did with the prepayment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefundOutcome {
+    /// User-facing line for the client and the admin.
+    pub text: String,
+    /// Set when the master has to return the money by hand.
+    pub manual: Option<ManualRefund>,
+}
+
+impl RefundOutcome {
+    fn automatic(text: String) -> Self {
+        Self { text, manual: None }
+    }
+
+    /// `text` followed by the manual refund notice, for messages to the admin.
+    pub fn admin_text(&self) -> String {
+        match &self.manual {
+            Some(manual) => format!("{}\n\n{}", self.text, manual.notice()),
+            None => self.text.clone(),
+        }
+    }
+}
+
+/// A refund of a Telegram Payments charge, recorded as `pending_manual`: the Bot API
+/// can't refund provider payments, so the master returns it in the provider's dashboard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManualRefund {
+    /// Id of the `refunds` row, for [`complete_manual_refund`].
+    pub refund_id: i64,
+    pub booking_id: i64,
+    /// `telegram_payment_charge_id` of the prepayment.
+    pub charge_id: String,
+    pub amount: i64,
+}
+
+impl ManualRefund {
+    /// Admin notice with what to look up in the provider's dashboard.
+    pub fn notice(&self) -> String {
+        format!(
+            "⚠️ Нужен ручной возврат #{} на {} ₽ по записи #{} (оплата через Telegram)\n\
+             telegram_payment_charge_id: {}",
+            self.refund_id, self.amount, self.booking_id, self.charge_id
+        )
+    }
+}
+
+/// Process refund logic for a booking cancellation.
+///
+/// - `admin_refund`: amount chosen by the admin (admin cancel); `None` applies the policy.
+///
+/// Returns the outcome to show, `None` when nothing was paid. Telegram Payments
+/// refunds come back with [`RefundOutcome::manual`] set; the caller notifies the admin.
+pub async fn process_refund_if_needed(
+    db: &SqlitePool,
+    payments: &dyn PaymentProvider,
+    booking: &Booking,
+    admin_refund: Option<i64>,
+) -> Option<RefundOutcome> {
+    let decision = match evaluate_cancellation(db, booking, admin_refund).await {
+        Ok(decision) => decision?,
+        Err(e) => {
+            tracing::error!("Failed to load cancellation policy for booking {}: {}", booking.id, e);
+            return Some(RefundOutcome::automatic("Возврат будет обработан вручную".into()));
+        }
+    };
+
+    // Earlier partial refunds (e.g. issued by the admin) reduce what's left
+    let remaining = match refundable_amount(db, booking).await {
+        Ok(remaining) => remaining,
+        Err(e) => {
+            tracing::error!("Failed to load refunds for booking {}: {}", booking.id, e);
+            return Some(RefundOutcome::automatic("Возврат будет обработан вручную".into()));
+        }
+    };
+    let amount = decision.amount.min(remaining);
+    if amount == 0 {
+        return Some(RefundOutcome::automatic(decision.explanation));
+    }
+
+    match issue_refund(db, payments, booking, amount, "cancellation").await {
+        Ok(refund) if refund.status == MANUAL_STATUS => Some(RefundOutcome {
+            text: format!("{}. Деньги вернёт мастер в течение нескольких дней", decision.explanation),
+            manual: Some(ManualRefund {
+                refund_id: refund.id,
+                booking_id: booking.id,
+                charge_id: refund.payment_id,
+                amount,
+            }),
+        }),
+        Ok(_) => Some(RefundOutcome::automatic(decision.explanation)),
+        Err(e) => {
+            tracing::error!("Refund failed for booking {}: {}", booking.id, e);
+            Some(RefundOutcome::automatic("Возврат будет обработан вручную".into()))
+        }
+    }
+}
+
+/// Prepayment not yet refunded or being refunded.
+pub async fn refundable_amount(db: &SqlitePool, booking: &Booking) -> Result<i64, sqlx::Error> {
+    let refunded: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(amount), 0) FROM refunds
+         WHERE booking_id = ? AND status IN ('pending', 'pending_manual', 'succeeded')",
+    )
+    .bind(booking.id)
+    .fetch_one(db)
+    .await?;
+    Ok((booking.prepaid_amount - refunded).max(0))
+}
+
+/// Refund status of a Telegram Payments charge the master returns by hand.
+pub const MANUAL_STATUS: &str = "pending_manual";
+
+/// Whether the booking has a prepayment [`issue_refund`] can refund: a YooKassa
+/// payment, or a Telegram Payments charge.
+pub fn has_refundable_payment(booking: &Booking) -> bool {
+    let payment = if booking.payment_method == "telegram" {
+        &booking.telegram_charge_id
+    } else {
+        &booking.yookassa_payment_id
+    };
+    matches!(booking.payment_status.as_str(), "paid" | "partially_refunded") && payment.is_some()
+}
+
+/// Refund `amount` of a booking's prepayment and record it in `refunds`.
+///
+/// Telegram Payments charges are only recorded, as [`MANUAL_STATUS`] with the charge id.
+/// The caller checks `amount` against [`refundable_amount`].
+pub async fn issue_refund(
+    db: &SqlitePool,
+    payments: &dyn PaymentProvider,
+    booking: &Booking,
+    amount: i64,
+    reason: &str,
+) -> anyhow::Result<Refund> {
+    if booking.payment_method == "telegram" {
+        return record_manual_refund(db, booking, amount, reason).await;
+    }
+
+    let payment_id = booking
+        .yookassa_payment_id
+        .as_deref()
+        .ok_or_else(|| anyhow::anyhow!("booking {} has no payment", booking.id))?;
+
+    // Record first so a crash mid-request still leaves a trace to reconcile
+    let refund_id = sqlx::query(
+        "INSERT INTO refunds (booking_id, payment_id, amount, reason) VALUES (?, ?, ?, ?)",
+    )
+    .bind(booking.id)
+    .bind(payment_id)
+    .bind(amount)
+    .bind(reason)
+    .execute(db)
+    .await?
+    .last_insert_rowid();
+
+    let idempotence_key = format!("refund-{}", refund_id);
+    let receipt = receipts::for_booking(db, booking.id).await?;
+    let request = RefundRequest {
+        payment_id,
+        amount,
+        idempotence_key: &idempotence_key,
+        receipt: receipt.as_ref(),
+    };
+    match payments.refund(&request).await {
+        Ok(created) => {
+            sqlx::query(
+                "UPDATE refunds SET yookassa_refund_id = ?, status = ?,
+                 updated_at = datetime('now', '+3 hours') WHERE id = ?",
+            )
+            .bind(&created.id)
+            .bind(&created.status)
+            .bind(refund_id)
+            .execute(db)
+            .await?;
+            sync_payment_status(db, booking.id).await?;
+        }
+        Err(e) => {
+            sqlx::query(
+                "UPDATE refunds SET status = 'failed', updated_at = datetime('now', '+3 hours') WHERE id = ?",
+            )
+            .bind(refund_id)
+            .execute(db)
+            .await?;
+            return Err(e);
+        }
+    }
+
+    let refund = sqlx::query_as::<_, Refund>("SELECT * FROM refunds WHERE id = ?")
+        .bind(refund_id)
+        .fetch_one(db)
+        .await?;
+    Ok(refund)
+}
+
+/// Record a refund of a Telegram Payments charge for the master to make by hand.
+async fn record_manual_refund(
+    db: &SqlitePool,
+    booking: &Booking,
+    amount: i64,
+    reason: &str,
+) -> anyhow::Result<Refund> {
+    let charge_id = booking
+        .telegram_charge_id
+        .as_deref()
+        .ok_or_else(|| anyhow::anyhow!("booking {} has no Telegram charge", booking.id))?;
+    let refund = sqlx::query_as::<_, Refund>(
+        "INSERT INTO refunds (booking_id, payment_id, amount, status, reason)
+         VALUES (?, ?, ?, ?, ?) RETURNING *",
+    )
+    .bind(booking.id)
+    .bind(charge_id)
+    .bind(amount)
+    .bind(MANUAL_STATUS)
+    .bind(reason)
+    .fetch_one(db)
+    .await?;
+    tracing::warn!(booking_id = booking.id, amount, "Telegram Payments refund left to the master");
+    Ok(refund)
+}
+
+/// Apply a refund status reported by the payment provider (webhook). Returns the booking id
+/// when the refund is known.
+pub async fn update_refund_status(
+    db: &SqlitePool,
+    yookassa_refund_id: &str,
+    status: &str,
+) -> Result<Option<i64>, sqlx::Error> {
+    let booking_id: Option<i64> = sqlx::query_scalar(
+        "UPDATE refunds SET status = ?, updated_at = datetime('now', '+3 hours')
+         WHERE yookassa_refund_id = ? RETURNING booking_id",
+    )
+    .bind(status)
+    .bind(yookassa_refund_id)
+    .fetch_optional(db)
+    .await?;
+
+    if let Some(booking_id) = booking_id {
+        sync_payment_status(db, booking_id).await?;
+    }
+    Ok(booking_id)
+}
+
+/// Mark a [`MANUAL_STATUS`] refund as made by the master and update the booking's
+/// payment status. Returns `None` when there is no such refund pending.
+pub async fn complete_manual_refund(
+    db: &SqlitePool,
+    refund_id: i64,
+) -> Result<Option<Refund>, sqlx::Error> {
+    let refund = sqlx::query_as::<_, Refund>(
+        "UPDATE refunds SET status = 'succeeded', updated_at = datetime('now', '+3 hours')
+         WHERE id = ? AND status = ? RETURNING *",
+    )
+    .bind(refund_id)
+    .bind(MANUAL_STATUS)
+    .fetch_optional(db)
+    .await?;
+
+    if let Some(refund) = &refund {
+        sync_payment_status(db, refund.booking_id).await?;
+    }
+    Ok(refund)
+}
+
+/// Derive `bookings.payment_status` from the succeeded refunds.
+async fn sync_payment_status(db: &SqlitePool, booking_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE bookings SET payment_status = CASE
+             WHEN refunded >= prepaid_amount THEN 'refunded'
+             ELSE 'partially_refunded'
+         END
+         FROM (SELECT COALESCE(SUM(amount), 0) AS refunded FROM refunds
+               WHERE booking_id = ? AND status = 'succeeded')
+         WHERE id = ? AND refunded > 0
+         AND payment_status IN ('paid', 'partially_refunded', 'refunded')",
+    )
+    .bind(booking_id)
+    .bind(booking_id)
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{payments::MockProvider, testing};
+
+    #[tokio::test]
+    async fn test_service_override_replaces_default() {
+        let db = testing::memory_db().await;
+        let ids: Vec<i64> = sqlx::query_scalar("SELECT id FROM services ORDER BY id LIMIT 2")
+            .fetch_all(&db)
+            .await
+            .unwrap();
+
+        sqlx::query(
+            "INSERT INTO cancellation_tiers (service_id, more_than_hours, refund_percent)
+             VALUES (?, 48, 100), (?, 24, 50)",
+        )
+        .bind(ids[0])
+        .bind(ids[0])
+        .execute(&db)
+        .await
+        .unwrap();
+
+        let own = policy_for_service(&db, ids[0]).await.unwrap();
+        assert_eq!(own.len(), 2);
+        assert_eq!(own[0].more_than_hours, 48);
+
+        let default = policy_for_service(&db, ids[1]).await.unwrap();
+        assert_eq!(default.len(), 1);
+        assert_eq!(default[0].service_id, None);
+    }
+
+    async fn paid_booking_db() -> (SqlitePool, Booking) {
+        let db = testing::memory_db().await;
+        let booking = testing::paid_booking(&db, "2099-01-01", "10:00", "12:00", 500).await;
+        (db, booking)
+    }
+
+    #[tokio::test]
+    async fn test_unapproved_booking_refunded_in_full() {
+        let (db, mut booking) = paid_booking_db().await;
+        // Too late for any refund under the default policy
+        booking.date = Some("2000-01-01".into());
+        let late = evaluate_cancellation(&db, &booking, None).await.unwrap().unwrap();
+        assert_eq!(late.amount, 0);
+
+        booking.status = "awaiting_approval".into();
+        let unapproved = evaluate_cancellation(&db, &booking, None).await.unwrap().unwrap();
+        assert_eq!(unapproved.amount, 500);
+    }
+
+    async fn payment_status(db: &SqlitePool) -> String {
+        sqlx::query_scalar("SELECT payment_status FROM bookings WHERE id = 1")
+            .fetch_one(db)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_refund_webhook_updates_booking() {
+        let (db, booking) = paid_booking_db().await;
+        sqlx::query(
+            "INSERT INTO refunds (booking_id, payment_id, yookassa_refund_id, amount)
+             VALUES (1, 'pay-1', 'rf-1', 200), (1, 'pay-1', 'rf-2', 300)",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        assert_eq!(refundable_amount(&db, &booking).await.unwrap(), 0);
+
+        assert_eq!(update_refund_status(&db, "rf-1", "succeeded").await.unwrap(), Some(1));
+        assert_eq!(payment_status(&db).await, "partially_refunded");
+
+        update_refund_status(&db, "rf-2", "succeeded").await.unwrap();
+        assert_eq!(payment_status(&db).await, "refunded");
+
+        assert_eq!(update_refund_status(&db, "unknown", "succeeded").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_failed_refunds_free_the_amount() {
+        let (db, booking) = paid_booking_db().await;
+        sqlx::query(
+            "INSERT INTO refunds (booking_id, payment_id, amount, status)
+             VALUES (1, 'pay-1', 500, 'failed'), (1, 'pay-1', 100, 'canceled')",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        assert_eq!(refundable_amount(&db, &booking).await.unwrap(), 500);
+        assert_eq!(payment_status(&db).await, "paid");
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_refund_through_provider() {
+        let (db, booking) = paid_booking_db().await;
+        let provider = MockProvider::new();
+
+        let info = process_refund_if_needed(&db, &provider, &booking, None).await.unwrap();
+        assert_eq!(info.manual, None);
+        assert_eq!(provider.refunded("pay-1"), 500);
+        assert_eq!(payment_status(&db).await, "refunded");
+
+        // Nothing left to refund on a repeated cancellation
+        process_refund_if_needed(&db, &provider, &booking, None).await;
+        assert_eq!(provider.refunded("pay-1"), 500);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_after_partial_refund_returns_the_rest() {
+        let (db, booking) = paid_booking_db().await;
+        let provider = MockProvider::new();
+
+        issue_refund(&db, &provider, &booking, 200, "admin").await.unwrap();
+        assert_eq!(payment_status(&db).await, "partially_refunded");
+
+        let booking = sqlx::query_as::<_, Booking>("SELECT * FROM bookings WHERE id = 1")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        let outcome = process_refund_if_needed(&db, &provider, &booking, None).await.unwrap();
+        assert_eq!(outcome.manual, None);
+        assert_eq!(provider.refunded("pay-1"), 500);
+        assert_eq!(payment_status(&db).await, "refunded");
+    }
+
+    #[tokio::test]
+    async fn test_telegram_charge_refunded_by_hand() {
+        let (db, mut booking) = paid_booking_db().await;
+        booking.payment_method = "telegram".into();
+        booking.yookassa_payment_id = None;
+        booking.telegram_charge_id = Some("tg-charge-1".into());
+        let provider = MockProvider::new();
+
+        let outcome = process_refund_if_needed(&db, &provider, &booking, None).await.unwrap();
+        assert_eq!(
+            outcome.manual,
+            Some(ManualRefund {
+                refund_id: 1,
+                booking_id: 1,
+                charge_id: "tg-charge-1".into(),
+                amount: 500,
+            })
+        );
+        assert!(outcome.admin_text().contains("tg-charge-1"));
+        assert!(!outcome.text.contains("tg-charge-1"));
+
+        let (status, payment_id): (String, String) =
+            sqlx::query_as("SELECT status, payment_id FROM refunds WHERE booking_id = 1")
+                .fetch_one(&db)
+                .await
+                .unwrap();
+        assert_eq!(status, MANUAL_STATUS);
+        assert_eq!(payment_id, "tg-charge-1");
+        // Nothing went to the provider, and the amount is not refunded twice
+        assert_eq!(provider.refunded("tg-charge-1"), 0);
+        assert_eq!(refundable_amount(&db, &booking).await.unwrap(), 0);
+        assert_eq!(payment_status(&db).await, "paid");
+
+        // The master reports the money returned
+        let refund = complete_manual_refund(&db, 1).await.unwrap().unwrap();
+        assert_eq!(refund.status, "succeeded");
+        assert_eq!(payment_status(&db).await, "refunded");
+        assert!(complete_manual_refund(&db, 1).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_partial_manual_refund_of_telegram_charge() {
+        let (db, mut booking) = paid_booking_db().await;
+        booking.payment_method = "telegram".into();
+        booking.yookassa_payment_id = None;
+        assert!(!has_refundable_payment(&booking));
+        booking.telegram_charge_id = Some("tg-charge-1".into());
+        assert!(has_refundable_payment(&booking));
+        let provider = MockProvider::new();
+
+        let refund = issue_refund(&db, &provider, &booking, 200, "admin").await.unwrap();
+        assert_eq!(refund.status, MANUAL_STATUS);
+        assert_eq!(refundable_amount(&db, &booking).await.unwrap(), 300);
+
+        complete_manual_refund(&db, refund.id).await.unwrap().unwrap();
+        assert_eq!(payment_status(&db).await, "partially_refunded");
+    }
+}
@@ -0,0 +1,583 @@
+//! Booking queries used by both the server and the bot.
+
+use sqlx::SqlitePool;
+
+use crate::models::{BlockedClient, Booking, BookingDetail, Client, NO_TELEGRAM};
+use crate::standing::Standing;
+use crate::visits::Outcome;
+
+/// The shared SELECT columns for booking detail queries.
+pub const BOOKING_DETAIL_SELECT: &str =
+    "SELECT b.id, s.name as service_name, s.price as service_price,
+            COALESCE(b.date, sl.date) as date,
+            COALESCE(b.start_time, sl.start_time) as start_time,
+            COALESCE(b.end_time, sl.end_time) as end_time,
+            b.client_tg_id, b.client_username, b.client_first_name,
+            b.status, b.created_at,
+            CASE WHEN b.with_lower_lashes = 1 THEN 1 ELSE 0 END as with_lower_lashes,
+            CASE WHEN b.amount_due > 0 THEN b.amount_due
+                 WHEN b.with_lower_lashes = 1
+                 THEN s.price + COALESCE((SELECT price FROM services WHERE service_type = 'addon' AND is_active = 1 LIMIT 1), 500)
+                 ELSE s.price
+            END as total_price,
+            b.payment_status,
+            b.prepaid_amount,
+            b.tip_amount,
+            b.client_phone,
+            b.amount_collected
+     FROM bookings b
+     JOIN services s ON s.id = b.service_id
+     LEFT JOIN available_slots sl ON sl.id = b.slot_id";
+
+/// Client profile columns plus stats over their bookings; filter on `c`.
+pub const CLIENT_SELECT: &str =
+    "SELECT c.tg_id, c.username, c.first_name, c.last_name, c.phone,
+            c.lash_style, c.curl, c.length, c.allergies, c.notes, c.tags,
+            c.created_at, c.last_seen_at,
+            (SELECT COUNT(*) FROM bookings b
+             WHERE b.client_tg_id = c.tg_id AND b.status = 'completed') AS visit_count,
+            (SELECT COALESCE(SUM(COALESCE(b.amount_collected, 0)
+                + CASE WHEN b.payment_status IN ('paid', 'partially_refunded', 'refunded')
+                       THEN b.prepaid_amount ELSE 0 END
+                - COALESCE((SELECT SUM(r.amount) FROM refunds r
+                            WHERE r.booking_id = b.id AND r.status = 'succeeded'), 0)), 0)
+             FROM bookings b WHERE b.client_tg_id = c.tg_id) AS lifetime_spend,
+            (SELECT MAX(b.date) FROM bookings b
+             WHERE b.client_tg_id = c.tg_id AND b.status = 'completed') AS last_visit
+     FROM clients c";
+
+/// Statuses that hold slots and count as an upcoming visit.
+const ACTIVE: &str = "b.status IN ('confirmed', 'pending_payment', 'awaiting_approval')";
+
+/// Active bookings plus closed visits: everything that shows in a day's schedule.
+const ON_SCHEDULE: &str =
+    "b.status IN ('confirmed', 'pending_payment', 'awaiting_approval', 'completed', 'no_show')";
+
+/// One booking with its service.
+pub async fn booking_detail(db: &SqlitePool, id: i64) -> Result<Option<BookingDetail>, sqlx::Error> {
+    let sql = format!("{} WHERE b.id = ?", BOOKING_DETAIL_SELECT);
+    sqlx::query_as::<_, BookingDetail>(&sql)
+        .bind(id)
+        .fetch_optional(db)
+        .await
+}
+
+/// A client's upcoming confirmed, unpaid and unapproved bookings, soonest first.
+pub async fn upcoming_client_bookings(
+    db: &SqlitePool,
+    client_tg_id: i64,
+) -> Result<Vec<BookingDetail>, sqlx::Error> {
+    let sql = format!(
+        "{} WHERE b.client_tg_id = ? AND {}
+         AND COALESCE(b.date, sl.date) >= date('now', '+3 hours')
+         ORDER BY COALESCE(b.date, sl.date) ASC, COALESCE(b.start_time, sl.start_time) ASC",
+        BOOKING_DETAIL_SELECT, ACTIVE
+    );
+    sqlx::query_as::<_, BookingDetail>(&sql)
+        .bind(client_tg_id)
+        .fetch_all(db)
+        .await
+}
+
+/// Upcoming active bookings made by any of the given Telegram id, username or phone, soonest first.
+pub async fn upcoming_matching_bookings(
+    db: &SqlitePool,
+    tg_id: Option<i64>,
+    username: Option<&str>,
+    phone: Option<&str>,
+) -> Result<Vec<BookingDetail>, sqlx::Error> {
+    let sql = format!(
+        "{} WHERE (b.client_tg_id = ? OR b.client_username = ? COLLATE NOCASE OR b.client_phone = ?)
+         AND {} AND COALESCE(b.date, sl.date) >= date('now', '+3 hours')
+         ORDER BY COALESCE(b.date, sl.date) ASC, COALESCE(b.start_time, sl.start_time) ASC",
+        BOOKING_DETAIL_SELECT, ACTIVE
+    );
+    sqlx::query_as::<_, BookingDetail>(&sql)
+        .bind(tg_id)
+        .bind(username)
+        .bind(phone)
+        .fetch_all(db)
+        .await
+}
+
+/// Active bookings and closed visits on `date`, in time order.
+pub async fn day_bookings(db: &SqlitePool, date: &str) -> Result<Vec<BookingDetail>, sqlx::Error> {
+    let sql = format!(
+        "{} WHERE COALESCE(b.date, sl.date) = ? AND {}
+         ORDER BY COALESCE(b.start_time, sl.start_time) ASC",
+        BOOKING_DETAIL_SELECT, ON_SCHEDULE
+    );
+    sqlx::query_as::<_, BookingDetail>(&sql)
+        .bind(date)
+        .fetch_all(db)
+        .await
+}
+
+/// Confirmed bookings on `date` whose client hasn't been reminded yet (and has Telegram).
+pub async fn bookings_to_remind(
+    db: &SqlitePool,
+    date: &str,
+) -> Result<Vec<BookingDetail>, sqlx::Error> {
+    let sql = format!(
+        "{} WHERE COALESCE(b.date, sl.date) = ? AND b.status = 'confirmed' AND b.reminder_sent = 0
+         AND b.client_tg_id != {}",
+        BOOKING_DETAIL_SELECT, NO_TELEGRAM
+    );
+    sqlx::query_as::<_, BookingDetail>(&sql)
+        .bind(date)
+        .fetch_all(db)
+        .await
+}
+
+/// An active (confirmed, unpaid or unapproved) booking, optionally restricted to its owner.
+pub async fn active_booking(
+    db: &SqlitePool,
+    id: i64,
+    client_tg_id: Option<i64>,
+) -> Result<Option<Booking>, sqlx::Error> {
+    sqlx::query_as::<_, Booking>(
+        "SELECT * FROM bookings WHERE id = ? AND (? IS NULL OR client_tg_id = ?)
+         AND status IN ('confirmed', 'pending_payment', 'awaiting_approval')",
+    )
+    .bind(id)
+    .bind(client_tg_id)
+    .bind(client_tg_id)
+    .fetch_optional(db)
+    .await
+}
+
+/// Confirm a booking awaiting payment: `payment_status` becomes `paid`, and the
+/// status `confirmed`, or `awaiting_approval` when the master vets the booking.
+///
+/// `paid_amount` overrides `prepaid_amount` when the provider reports what was charged.
+/// Returns `false` when the booking was no longer pending (repeated notification,
+/// expired before the payment arrived).
+pub async fn confirm_payment(
+    db: &SqlitePool,
+    booking_id: i64,
+    paid_amount: Option<i64>,
+    telegram_charge_id: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let updated = sqlx::query(
+        "UPDATE bookings SET payment_status = 'paid',
+         status = CASE WHEN needs_approval = 1 THEN 'awaiting_approval' ELSE 'confirmed' END,
+         prepaid_amount = COALESCE(?, prepaid_amount),
+         telegram_charge_id = COALESCE(?, telegram_charge_id)
+         WHERE id = ? AND status = 'pending_payment'",
+    )
+    .bind(paid_amount)
+    .bind(telegram_charge_id)
+    .bind(booking_id)
+    .execute(db)
+    .await?
+    .rows_affected();
+    Ok(updated > 0)
+}
+
+/// Confirm a booking the master approved. Returns `false` when it no longer
+/// awaits approval (already answered, expired or cancelled by the client).
+pub async fn approve_booking(db: &SqlitePool, booking_id: i64) -> Result<bool, sqlx::Error> {
+    let updated = sqlx::query(
+        "UPDATE bookings SET status = 'confirmed' WHERE id = ? AND status = 'awaiting_approval'",
+    )
+    .bind(booking_id)
+    .execute(db)
+    .await?
+    .rows_affected();
+    Ok(updated > 0)
+}
+
+/// Close a confirmed booking whose appointment has started as completed (with the
+/// amount taken on site) or no-show. Slots stay booked: the time was spent.
+///
+/// Returns `false` when the booking isn't confirmed or hasn't started yet.
+pub async fn close_visit(
+    db: &SqlitePool,
+    booking_id: i64,
+    outcome: Outcome,
+    amount_collected: i64,
+) -> Result<bool, sqlx::Error> {
+    let updated = sqlx::query(
+        "UPDATE bookings SET status = ?, amount_collected = ?,
+         closed_at = datetime('now', '+3 hours')
+         WHERE id = ? AND status = 'confirmed'
+         AND date || ' ' || start_time <= strftime('%Y-%m-%d %H:%M', 'now', '+3 hours')",
+    )
+    .bind(outcome.status())
+    .bind(amount_collected)
+    .bind(booking_id)
+    .execute(db)
+    .await?
+    .rows_affected();
+    Ok(updated > 0)
+}
+
+/// Correct the amount taken at a completed visit. Returns `false` for other bookings.
+pub async fn set_amount_collected(
+    db: &SqlitePool,
+    booking_id: i64,
+    amount: i64,
+) -> Result<bool, sqlx::Error> {
+    let updated = sqlx::query(
+        "UPDATE bookings SET amount_collected = ? WHERE id = ? AND status = 'completed'",
+    )
+    .bind(amount)
+    .bind(booking_id)
+    .execute(db)
+    .await?
+    .rows_affected();
+    Ok(updated > 0)
+}
+
+/// Who cancelled a booking; late client cancellations count against the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CancelledBy {
+    Client,
+    Admin,
+}
+
+impl CancelledBy {
+    fn as_str(self) -> &'static str {
+        match self {
+            CancelledBy::Client => "client",
+            CancelledBy::Admin => "admin",
+        }
+    }
+}
+
+/// Mark a booking cancelled and free its slots in one transaction.
+pub async fn cancel_booking(
+    db: &SqlitePool,
+    booking: &Booking,
+    by: CancelledBy,
+) -> Result<(), sqlx::Error> {
+    let mut tx = db.begin().await?;
+    sqlx::query(
+        "UPDATE bookings SET status = 'cancelled', cancelled_at = datetime('now', '+3 hours'),
+         cancelled_by = ? WHERE id = ?",
+    )
+    .bind(by.as_str())
+    .bind(booking.id)
+    .execute(&mut *tx)
+    .await?;
+    free_booking_slots(&mut tx, booking.id, booking.slot_id).await?;
+    tx.commit().await
+}
+
+/// A client's no-shows and late cancellations since the master last reset them.
+///
+/// A client cancellation is late when made less than `late_cancel_hours` before the appointment.
+pub async fn client_standing(
+    db: &SqlitePool,
+    client_tg_id: i64,
+    late_cancel_hours: i64,
+) -> Result<Standing, sqlx::Error> {
+    sqlx::query_as::<_, Standing>(
+        "SELECT COALESCE(SUM(b.status = 'no_show'), 0) AS no_shows,
+                COALESCE(SUM(b.status = 'cancelled' AND b.cancelled_by = 'client'
+                    AND b.cancelled_at > datetime(b.date || ' ' || b.start_time, ?)), 0)
+                    AS late_cancellations
+         FROM bookings b
+         LEFT JOIN client_standing_resets r ON r.client_tg_id = b.client_tg_id
+         WHERE b.client_tg_id = ?
+         AND COALESCE(b.closed_at, b.cancelled_at) > COALESCE(r.reset_at, '')",
+    )
+    .bind(format!("-{} hours", late_cancel_hours))
+    .bind(client_tg_id)
+    .fetch_one(db)
+    .await
+}
+
+/// Create or refresh a client's profile from their Telegram identity.
+pub async fn upsert_client(
+    db: &SqlitePool,
+    tg_id: i64,
+    username: Option<&str>,
+    first_name: &str,
+    last_name: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO clients (tg_id, username, first_name, last_name) VALUES (?, ?, ?, ?)
+         ON CONFLICT(tg_id) DO UPDATE SET username = excluded.username,
+         first_name = excluded.first_name, last_name = excluded.last_name,
+         last_seen_at = datetime('now', '+3 hours')",
+    )
+    .bind(tg_id)
+    .bind(username)
+    .bind(first_name)
+    .bind(last_name)
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// A client's profile, if they have one.
+pub async fn client(db: &SqlitePool, tg_id: i64) -> Result<Option<Client>, sqlx::Error> {
+    sqlx::query_as::<_, Client>(&format!("{} WHERE c.tg_id = ?", CLIENT_SELECT))
+        .bind(tg_id)
+        .fetch_optional(db)
+        .await
+}
+
+/// The block-list entry matching a user's Telegram id, username (in any case,
+/// as Telegram treats them), or phone (the given one or the one on their profile).
+pub async fn blocked_client(
+    db: &SqlitePool,
+    tg_id: i64,
+    username: Option<&str>,
+    phone: Option<&str>,
+) -> Result<Option<BlockedClient>, sqlx::Error> {
+    sqlx::query_as::<_, BlockedClient>(
+        "SELECT id, tg_id, username, phone, reason, created_at FROM blocked_clients
+         WHERE tg_id = ?1 OR username = ?2 COLLATE NOCASE
+         OR phone = ?3 OR phone = (SELECT phone FROM clients WHERE tg_id = ?1)
+         ORDER BY tg_id IS NULL, id LIMIT 1",
+    )
+    .bind(tg_id)
+    .bind(username)
+    .bind(phone)
+    .fetch_optional(db)
+    .await
+}
+
+/// Forget a client's no-shows and late cancellations so far.
+pub async fn reset_client_standing(db: &SqlitePool, client_tg_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO client_standing_resets (client_tg_id, reset_at)
+         VALUES (?, datetime('now', '+3 hours'))
+         ON CONFLICT(client_tg_id) DO UPDATE SET reset_at = excluded.reset_at",
+    )
+    .bind(client_tg_id)
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Free all slots belonging to a booking.
+///
+/// Ad hoc slots (made for an admin booking outside open hours) are dropped instead,
+/// unless a booking still references them.
+pub async fn free_booking_slots(
+    conn: &mut sqlx::SqliteConnection,
+    booking_id: i64,
+    slot_id: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "DELETE FROM available_slots WHERE booking_id = ? AND ad_hoc = 1
+         AND id NOT IN (SELECT slot_id FROM bookings)",
+    )
+    .bind(booking_id)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query("UPDATE available_slots SET is_booked = 0, booking_id = NULL WHERE booking_id = ?")
+        .bind(booking_id)
+        .execute(&mut *conn)
+        .await?;
+
+    // Also free by slot_id for backward compat
+    sqlx::query("UPDATE available_slots SET is_booked = 0, booking_id = NULL WHERE id = ?")
+        .bind(slot_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    async fn seeded_db() -> SqlitePool {
+        let db = testing::memory_db().await;
+        testing::paid_booking(&db, "2099-01-01", "10:00", "12:00", 500).await;
+        db
+    }
+
+    #[tokio::test]
+    async fn test_active_booking_checks_owner() {
+        let db = seeded_db().await;
+        assert!(active_booking(&db, 1, Some(42)).await.unwrap().is_some());
+        assert!(active_booking(&db, 1, Some(7)).await.unwrap().is_none());
+        assert!(active_booking(&db, 1, None).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_frees_slots() {
+        let db = seeded_db().await;
+        let booking = active_booking(&db, 1, None).await.unwrap().unwrap();
+        cancel_booking(&db, &booking, CancelledBy::Admin).await.unwrap();
+
+        assert!(active_booking(&db, 1, None).await.unwrap().is_none());
+        let booked: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM available_slots WHERE is_booked = 1")
+                .fetch_one(&db)
+                .await
+                .unwrap();
+        assert_eq!(booked, 0);
+        assert!(day_bookings(&db, "2099-01-01").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_close_visit() {
+        let db = seeded_db().await;
+        // Not before the appointment
+        assert!(!close_visit(&db, 1, Outcome::Completed, 2000).await.unwrap());
+        sqlx::query("UPDATE bookings SET date = '2000-01-01'")
+            .execute(&db)
+            .await
+            .unwrap();
+        assert!(close_visit(&db, 1, Outcome::Completed, 2000).await.unwrap());
+        // Only once, and the collected amount can be corrected afterwards
+        assert!(!close_visit(&db, 1, Outcome::NoShow, 0).await.unwrap());
+        assert!(set_amount_collected(&db, 1, 2300).await.unwrap());
+
+        let detail = booking_detail(&db, 1).await.unwrap().unwrap();
+        assert_eq!(detail.status, "completed");
+        assert_eq!(detail.amount_collected, Some(2300));
+        assert_eq!(detail.amount_to_collect(), 0);
+        // Still on the day's schedule, but no longer cancellable
+        assert_eq!(day_bookings(&db, "2000-01-01").await.unwrap().len(), 1);
+        assert!(active_booking(&db, 1, None).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_client_standing() {
+        let db = seeded_db().await;
+        sqlx::query("UPDATE bookings SET date = '2000-01-01'")
+            .execute(&db)
+            .await
+            .unwrap();
+        assert!(close_visit(&db, 1, Outcome::NoShow, 0).await.unwrap());
+        // Cancelled by the client an hour before the appointment
+        sqlx::query(
+            "INSERT INTO bookings (service_id, slot_id, client_tg_id, client_first_name, status,
+             date, start_time, end_time, cancelled_at, cancelled_by)
+             VALUES ((SELECT MIN(id) FROM services), 1, 42, 'Аня', 'cancelled',
+             '2000-01-02', '10:00', '11:00', '2000-01-02 09:00:00', 'client'),
+             ((SELECT MIN(id) FROM services), 1, 42, 'Аня', 'cancelled',
+             '2000-01-03', '10:00', '11:00', '2000-01-01 09:00:00', 'client')",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        let standing = client_standing(&db, 42, 24).await.unwrap();
+        assert_eq!(standing, Standing { no_shows: 1, late_cancellations: 1 });
+        assert_eq!(client_standing(&db, 7, 24).await.unwrap(), Standing::default());
+
+        reset_client_standing(&db, 42).await.unwrap();
+        assert_eq!(client_standing(&db, 42, 24).await.unwrap(), Standing::default());
+    }
+
+    #[tokio::test]
+    async fn test_client_profile_stats() {
+        let db = seeded_db().await;
+        upsert_client(&db, 42, None, "Аня", None).await.unwrap();
+        upsert_client(&db, 42, Some("anya"), "Анна", None).await.unwrap();
+        sqlx::query("UPDATE bookings SET date = '2000-01-01'")
+            .execute(&db)
+            .await
+            .unwrap();
+        assert!(close_visit(&db, 1, Outcome::Completed, 2000).await.unwrap());
+
+        let client = client(&db, 42).await.unwrap().unwrap();
+        assert_eq!(client.first_name, "Анна");
+        assert_eq!(client.username.as_deref(), Some("anya"));
+        assert_eq!(client.visit_count, 1);
+        // 2000 on site + 500 prepaid
+        assert_eq!(client.lifetime_spend, 2500);
+        assert_eq!(client.last_visit.as_deref(), Some("2000-01-01"));
+    }
+
+    #[tokio::test]
+    async fn test_blocked_client() {
+        let db = seeded_db().await;
+        upsert_client(&db, 42, Some("anya"), "Аня", None).await.unwrap();
+        assert!(blocked_client(&db, 42, Some("anya"), None).await.unwrap().is_none());
+
+        sqlx::query("INSERT INTO blocked_clients (username, reason) VALUES ('Anya', 'грубила')")
+            .execute(&db)
+            .await
+            .unwrap();
+        let entry = blocked_client(&db, 42, Some("anya"), None).await.unwrap().unwrap();
+        assert_eq!(entry.reason, "грубила");
+        assert!(blocked_client(&db, 7, Some("ANYA"), None).await.unwrap().is_some());
+        assert!(blocked_client(&db, 42, None, None).await.unwrap().is_none());
+
+        // Phone from the booking form or from the profile
+        sqlx::query("INSERT INTO blocked_clients (phone) VALUES ('79991234567')")
+            .execute(&db)
+            .await
+            .unwrap();
+        assert!(blocked_client(&db, 7, None, Some("79991234567")).await.unwrap().is_some());
+        sqlx::query("UPDATE clients SET phone = '79991234567' WHERE tg_id = 42")
+            .execute(&db)
+            .await
+            .unwrap();
+        assert!(blocked_client(&db, 42, None, None).await.unwrap().is_some());
+
+        sqlx::query("UPDATE bookings SET client_username = 'anya'")
+            .execute(&db)
+            .await
+            .unwrap();
+        for (tg_id, username) in [(Some(42), None), (None, Some("ANYA"))] {
+            let upcoming = upcoming_matching_bookings(&db, tg_id, username, None).await.unwrap();
+            assert_eq!(upcoming.len(), 1);
+        }
+        assert!(upcoming_matching_bookings(&db, Some(7), None, None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_drops_ad_hoc_slots() {
+        let db = seeded_db().await;
+        sqlx::query("UPDATE available_slots SET ad_hoc = 1")
+            .execute(&db)
+            .await
+            .unwrap();
+        let booking = active_booking(&db, 1, None).await.unwrap().unwrap();
+        cancel_booking(&db, &booking, CancelledBy::Admin).await.unwrap();
+
+        // Slot 1 is still the cancelled booking's slot_id, so it stays (free)
+        let left: Vec<i64> = sqlx::query_scalar("SELECT id FROM available_slots WHERE is_booked = 0")
+            .fetch_all(&db)
+            .await
+            .unwrap();
+        assert_eq!(left, vec![1]);
+    }
+
+    #[tokio::test]
+    async fn test_detail_queries() {
+        let db = seeded_db().await;
+        let detail = booking_detail(&db, 1).await.unwrap().unwrap();
+        assert_eq!(detail.client_mention(), "Аня");
+        assert_eq!(detail.prepaid_amount, Some(500));
+        assert_eq!(upcoming_client_bookings(&db, 42).await.unwrap().len(), 1);
+        assert_eq!(bookings_to_remind(&db, "2099-01-01").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_payment_waits_for_approval() {
+        let db = seeded_db().await;
+        sqlx::query(
+            "UPDATE bookings SET status = 'pending_payment', payment_status = 'pending',
+             needs_approval = 1 WHERE id = 1",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        assert!(confirm_payment(&db, 1, None, None).await.unwrap());
+        let booking = active_booking(&db, 1, None).await.unwrap().unwrap();
+        assert_eq!(booking.status, "awaiting_approval");
+        assert_eq!(booking.payment_status, "paid");
+        // Unapproved bookings hold their slots but get no reminder
+        assert_eq!(day_bookings(&db, "2099-01-01").await.unwrap().len(), 1);
+        assert!(bookings_to_remind(&db, "2099-01-01").await.unwrap().is_empty());
+
+        assert!(approve_booking(&db, 1).await.unwrap());
+        assert!(!approve_booking(&db, 1).await.unwrap());
+        let booking = active_booking(&db, 1, None).await.unwrap().unwrap();
+        assert_eq!(booking.status, "confirmed");
+    }
+}
@@ -0,0 +1,182 @@
+//! Booking business rules shared by the server and the bot.
+
+use crate::models::CancellationTier;
+
+/// Prepayment amount in RUB.
+pub const PREPAID_AMOUNT: i64 = 500;
+
+/// Largest tip accepted online, in RUB.
+pub const MAX_TIP: i64 = 10_000;
+
+/// What is left to collect at the visit, in RUB.
+///
+/// A paid prepayment counts towards `amount_due` minus the tip it includes;
+/// anything unpaid (pending, refunded, none) leaves the full price to collect.
+pub fn amount_to_collect(amount_due: i64, prepaid_amount: i64, tip_amount: i64, payment_status: &str) -> i64 {
+    if payment_status != "paid" {
+        return amount_due;
+    }
+    (amount_due - (prepaid_amount - tip_amount)).max(0)
+}
+
+/// How much of the prepayment goes back on cancellation, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefundDecision {
+    /// Amount to return in RUB, 0 when the prepayment is kept.
+    pub amount: i64,
+    /// User-facing explanation shown to the client and the admin.
+    pub explanation: String,
+}
+
+/// Evaluate the cancellation policy for a booking.
+///
+/// - `tiers`: the policy that applies to the booking's service.
+/// - `admin_refund`: amount chosen by the admin; bypasses the tiers.
+/// - `hours_left`: whole hours until the appointment.
+///
+/// Returns `None` when nothing was paid. A partially refunded prepayment is still
+/// evaluated in full; the caller caps the refund at what is left.
+pub fn evaluate_refund(
+    tiers: &[CancellationTier],
+    payment_status: &str,
+    prepaid_amount: i64,
+    admin_refund: Option<i64>,
+    hours_left: i64,
+) -> Option<RefundDecision> {
+    if !matches!(payment_status, "paid" | "partially_refunded") {
+        return None;
+    }
+
+    if let Some(amount) = admin_refund {
+        let amount = amount.clamp(0, prepaid_amount);
+        let explanation = if amount == prepaid_amount {
+            format!("Предоплата {} ₽ будет возвращена", prepaid_amount)
+        } else if amount == 0 {
+            format!("Предоплата {} ₽ не возвращается (решение мастера)", prepaid_amount)
+        } else {
+            format!("Будет возвращено {} ₽ из {} ₽ (решение мастера)", amount, prepaid_amount)
+        };
+        return Some(RefundDecision { amount, explanation });
+    }
+
+    let tier = tiers
+        .iter()
+        .filter(|t| hours_left > t.more_than_hours)
+        .max_by_key(|t| t.more_than_hours);
+    let percent = tier.map_or(0, |t| t.refund_percent.clamp(0, 100));
+    let amount = prepaid_amount * percent / 100;
+
+    let explanation = match tier {
+        Some(t) if percent == 100 => format!(
+            "Предоплата {} ₽ будет возвращена (отмена более чем за {}ч)",
+            prepaid_amount, t.more_than_hours
+        ),
+        Some(t) if percent > 0 => format!(
+            "Будет возвращено {} ₽ из {} ₽ ({}% при отмене более чем за {}ч)",
+            amount, prepaid_amount, percent, t.more_than_hours
+        ),
+        _ => {
+            // Point the client at the nearest tier they missed
+            let missed = tiers
+                .iter()
+                .filter(|t| t.refund_percent > 0 && t.more_than_hours >= hours_left)
+                .map(|t| t.more_than_hours)
+                .min();
+            match missed {
+                Some(h) => format!(
+                    "Предоплата {} ₽ не возвращается (отмена менее чем за {}ч)",
+                    prepaid_amount, h
+                ),
+                None => format!("Предоплата {} ₽ не возвращается", prepaid_amount),
+            }
+        }
+    };
+
+    Some(RefundDecision { amount, explanation })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tier(more_than_hours: i64, refund_percent: i64) -> CancellationTier {
+        CancellationTier {
+            id: 0,
+            service_id: None,
+            more_than_hours,
+            refund_percent,
+        }
+    }
+
+    /// "full refund > 48h, 50% refund 24–48h, none < 24h"
+    fn tiered() -> Vec<CancellationTier> {
+        vec![tier(48, 100), tier(24, 50)]
+    }
+
+    #[test]
+    fn test_unpaid_never_refunds() {
+        assert_eq!(evaluate_refund(&tiered(), "pending", 500, Some(500), 100), None);
+        assert_eq!(evaluate_refund(&tiered(), "none", 0, None, 100), None);
+        assert_eq!(evaluate_refund(&tiered(), "refunded", 500, None, 100), None);
+    }
+
+    #[test]
+    fn test_partially_refunded_still_refunds() {
+        let d = evaluate_refund(&tiered(), "partially_refunded", 500, None, 49).unwrap();
+        assert_eq!(d.amount, 500);
+    }
+
+    #[test]
+    fn test_full_refund_tier() {
+        let d = evaluate_refund(&tiered(), "paid", 500, None, 49).unwrap();
+        assert_eq!(d.amount, 500);
+        assert!(d.explanation.contains("48ч"));
+    }
+
+    #[test]
+    fn test_partial_refund_tier() {
+        let d = evaluate_refund(&tiered(), "paid", 500, None, 48).unwrap();
+        assert_eq!(d.amount, 250);
+        assert!(d.explanation.contains("50%"));
+    }
+
+    #[test]
+    fn test_no_refund_below_tiers() {
+        let d = evaluate_refund(&tiered(), "paid", 500, None, 24).unwrap();
+        assert_eq!(d.amount, 0);
+        assert!(d.explanation.contains("менее чем за 24ч"));
+    }
+
+    #[test]
+    fn test_default_policy_matches_legacy_rule() {
+        let tiers = vec![tier(24, 100)];
+        assert_eq!(evaluate_refund(&tiers, "paid", 500, None, 25).unwrap().amount, 500);
+        assert_eq!(evaluate_refund(&tiers, "paid", 500, None, 24).unwrap().amount, 0);
+    }
+
+    #[test]
+    fn test_empty_policy_never_refunds() {
+        let d = evaluate_refund(&[], "paid", 500, None, 1000).unwrap();
+        assert_eq!(d.amount, 0);
+    }
+
+    #[test]
+    fn test_amount_to_collect() {
+        // Deposit: 2500 ₽ visit, 500 ₽ paid
+        assert_eq!(amount_to_collect(2500, 500, 0, "paid"), 2000);
+        // Full payment with a 300 ₽ tip
+        assert_eq!(amount_to_collect(2500, 2800, 300, "paid"), 0);
+        // Deposit with a tip: the tip doesn't count towards the price
+        assert_eq!(amount_to_collect(2500, 700, 200, "paid"), 2000);
+        assert_eq!(amount_to_collect(2500, 2500, 0, "pending"), 2500);
+        assert_eq!(amount_to_collect(2500, 2500, 0, "refunded"), 2500);
+    }
+
+    #[test]
+    fn test_admin_refund_overrides_tiers() {
+        assert_eq!(evaluate_refund(&tiered(), "paid", 500, Some(500), 1).unwrap().amount, 500);
+        assert_eq!(evaluate_refund(&tiered(), "paid", 500, Some(200), 1).unwrap().amount, 200);
+        assert_eq!(evaluate_refund(&tiered(), "paid", 500, Some(9000), 1).unwrap().amount, 500);
+        assert_eq!(evaluate_refund(&tiered(), "paid", 500, Some(0), 100).unwrap().amount, 0);
+    }
+}
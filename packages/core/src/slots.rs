@@ -0,0 +1,115 @@
+//! Slot generation shared by the server (`open_day`, weekly schedule) and the bot (`/openday`).
+
+use crate::time::minutes_to_time;
+
+/// Allowed base slot lengths in minutes.
+pub const SLOT_GRANULARITIES: [u32; 3] = [15, 30, 60];
+
+/// Base slot length from env SLOT_GRANULARITY_MIN (default 60).
+///
+/// Both processes cut days with it, so anything but 15, 30 or 60 stops startup.
+pub fn granularity_from_env() -> anyhow::Result<u32> {
+    let Ok(raw) = std::env::var("SLOT_GRANULARITY_MIN") else {
+        return Ok(60);
+    };
+    match raw.trim().parse() {
+        Ok(min) if SLOT_GRANULARITIES.contains(&min) => Ok(min),
+        _ => anyhow::bail!("SLOT_GRANULARITY_MIN must be one of 15, 30, 60, got {}", raw),
+    }
+}
+
+/// Slot boundaries for a working day: `[start_h, end_h)` cut into `granularity_min` pieces.
+pub fn day_slot_times(start_h: u32, end_h: u32, granularity_min: u32) -> Vec<(String, String)> {
+    (start_h * 60..end_h * 60)
+        .step_by(granularity_min.max(1) as usize)
+        .map(|m| {
+            (
+                minutes_to_time(m),
+                minutes_to_time(m + granularity_min),
+            )
+        })
+        .collect()
+}
+
+/// Create `granularity_min` slots for `date` between `start_h` and `end_h`.
+///
+/// Idempotent: slots overlapping an existing one are skipped. Returns the number of new slots.
+pub async fn open_day_slots(
+    db: &sqlx::SqlitePool,
+    date: &str,
+    start_h: u32,
+    end_h: u32,
+    granularity_min: u32,
+) -> Result<u32, sqlx::Error> {
+    let mut added = 0;
+    for (start, end) in day_slot_times(start_h, end_h, granularity_min) {
+        // Idempotent: skip if an overlapping slot already exists (free ad hoc ones don't count)
+        let exists: bool = sqlx::query_scalar(
+            "SELECT COUNT(*) > 0 FROM available_slots
+             WHERE date = ? AND start_time < ? AND end_time > ? AND (ad_hoc = 0 OR is_booked = 1)",
+        )
+        .bind(date)
+        .bind(&end)
+        .bind(&start)
+        .fetch_one(db)
+        .await?;
+
+        if !exists {
+            sqlx::query("INSERT INTO available_slots (date, start_time, end_time) VALUES (?, ?, ?)")
+                .bind(date)
+                .bind(&start)
+                .bind(&end)
+                .execute(db)
+                .await?;
+            added += 1;
+        }
+    }
+    Ok(added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn test_day_slot_times_hourly() {
+        let slots = day_slot_times(12, 15, 60);
+        assert_eq!(
+            slots,
+            vec![
+                ("12:00".to_string(), "13:00".to_string()),
+                ("13:00".to_string(), "14:00".to_string()),
+                ("14:00".to_string(), "15:00".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_day_slot_times_quarter_hours() {
+        let slots = day_slot_times(10, 11, 15);
+        assert_eq!(slots.len(), 4);
+        assert_eq!(slots[1], ("10:15".to_string(), "10:30".to_string()));
+        assert_eq!(slots[3].1, "11:00");
+    }
+
+    #[test]
+    fn test_day_slot_times_until_midnight() {
+        let slots = day_slot_times(23, 24, 30);
+        assert_eq!(slots.last().unwrap().1, "24:00");
+    }
+
+    #[test]
+    fn test_day_slot_times_empty_range() {
+        assert!(day_slot_times(12, 12, 60).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_open_day_slots_skips_existing() {
+        let db = testing::memory_db().await;
+        testing::open_slots(&db, "2030-01-01", &[("12:00", "13:00")]).await;
+
+        assert_eq!(open_day_slots(&db, "2030-01-01", 12, 14, 30).await.unwrap(), 2);
+        assert_eq!(open_day_slots(&db, "2030-01-01", 12, 14, 30).await.unwrap(), 0);
+    }
+}
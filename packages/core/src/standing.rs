@@ -0,0 +1,134 @@
+//! A client's standing: no-shows and late cancellations counted from booking
+//! history, and the restrictions they trigger (env `CLIENT_RESTRICTIONS`).
+
+use serde::Serialize;
+
+/// Counters since the client's last reset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, sqlx::FromRow)]
+pub struct Standing {
+    pub no_shows: i64,
+    /// Client cancellations closer to the appointment than `LATE_CANCEL_HOURS`.
+    pub late_cancellations: i64,
+}
+
+/// What a client may still do online, mildest first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Restriction {
+    #[default]
+    None,
+    /// The whole price up front, even where prepayment is otherwise off.
+    FullPrepayment,
+    /// No online booking; the master books the client by hand.
+    Blocked,
+}
+
+/// Which counter a rule looks at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Counter {
+    NoShows,
+    LateCancellations,
+}
+
+/// "At `threshold` or more of `counter`, apply `restriction`".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule {
+    pub counter: Counter,
+    pub threshold: i64,
+    pub restriction: Restriction,
+}
+
+/// Parse comma-separated rules like `no_show:2=full_prepayment,late_cancel:3=block`.
+///
+/// An empty string means no rules.
+pub fn parse_rules(value: &str) -> Result<Vec<Rule>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .map(|rule| {
+            let invalid = || format!("invalid restriction rule {:?}", rule);
+            let (condition, restriction) = rule.split_once('=').ok_or_else(invalid)?;
+            let (counter, threshold) = condition.split_once(':').ok_or_else(invalid)?;
+            let counter = match counter.trim() {
+                "no_show" => Counter::NoShows,
+                "late_cancel" => Counter::LateCancellations,
+                _ => return Err(invalid()),
+            };
+            let threshold: i64 = threshold.trim().parse().map_err(|_| invalid())?;
+            let restriction = match restriction.trim() {
+                "full_prepayment" => Restriction::FullPrepayment,
+                "block" => Restriction::Blocked,
+                _ => return Err(invalid()),
+            };
+            if threshold < 1 {
+                return Err(invalid());
+            }
+            Ok(Rule {
+                counter,
+                threshold,
+                restriction,
+            })
+        })
+        .collect()
+}
+
+impl Standing {
+    /// The strictest restriction any rule triggers.
+    pub fn restriction(&self, rules: &[Rule]) -> Restriction {
+        rules
+            .iter()
+            .filter(|rule| {
+                let count = match rule.counter {
+                    Counter::NoShows => self.no_shows,
+                    Counter::LateCancellations => self.late_cancellations,
+                };
+                count >= rule.threshold
+            })
+            .map(|rule| rule.restriction)
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = parse_rules("no_show:2=full_prepayment, late_cancel:3=block").unwrap();
+        assert_eq!(
+            rules,
+            vec![
+                Rule {
+                    counter: Counter::NoShows,
+                    threshold: 2,
+                    restriction: Restriction::FullPrepayment,
+                },
+                Rule {
+                    counter: Counter::LateCancellations,
+                    threshold: 3,
+                    restriction: Restriction::Blocked,
+                },
+            ]
+        );
+        assert!(parse_rules("").unwrap().is_empty());
+        assert!(parse_rules("no_show:0=block").is_err());
+        assert!(parse_rules("no_show=block").is_err());
+        assert!(parse_rules("late:2=block").is_err());
+    }
+
+    #[test]
+    fn test_strictest_restriction_wins() {
+        let rules = parse_rules("no_show:2=full_prepayment,no_show:3=block").unwrap();
+        let standing = |no_shows| Standing {
+            no_shows,
+            late_cancellations: 0,
+        };
+        assert_eq!(standing(1).restriction(&rules), Restriction::None);
+        assert_eq!(standing(2).restriction(&rules), Restriction::FullPrepayment);
+        assert_eq!(standing(5).restriction(&rules), Restriction::Blocked);
+        assert_eq!(standing(5).restriction(&[]), Restriction::None);
+    }
+}
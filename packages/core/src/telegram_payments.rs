@@ -0,0 +1,126 @@
+//! Prepayment through Telegram Payments: the server sends an invoice to the
+//! client's chat, the bot answers `pre_checkout_query` and records `successful_payment`.
+
+use crate::models::Booking;
+
+const PAYLOAD_PREFIX: &str = "booking:";
+
+/// Invoice payload identifying the booking.
+pub fn invoice_payload(booking_id: i64) -> String {
+    format!("{}{}", PAYLOAD_PREFIX, booking_id)
+}
+
+/// Booking id from an invoice payload written by [`invoice_payload`].
+pub fn parse_invoice_payload(payload: &str) -> Option<i64> {
+    payload.strip_prefix(PAYLOAD_PREFIX)?.parse().ok()
+}
+
+/// Decide whether a pre-checkout query may proceed; the error is shown to the client.
+///
+/// `total_amount` is in kopecks, as Telegram reports it.
+pub fn check_pre_checkout(booking: Option<&Booking>, total_amount: i64) -> Result<(), &'static str> {
+    let booking = booking.ok_or("Запись не найдена")?;
+    if booking.status != "pending_payment" {
+        return Err("Время на оплату истекло — запишись заново");
+    }
+    if total_amount != booking.prepaid_amount * 100 {
+        return Err("Сумма счёта не совпадает с предоплатой");
+    }
+    Ok(())
+}
+
+/// Send an invoice to the client's chat via the Bot API.
+///
+/// `prices` are `(label, rubles)` lines; they must add up to the booking's `prepaid_amount`.
+pub async fn send_invoice(
+    bot_token: &str,
+    provider_token: &str,
+    chat_id: i64,
+    booking_id: i64,
+    description: &str,
+    prices: &[(&str, i64)],
+) -> anyhow::Result<()> {
+    let url = format!("https://api.telegram.org/bot{}/sendInvoice", bot_token);
+    let prices: Vec<serde_json::Value> = prices
+        .iter()
+        .map(|(label, amount)| serde_json::json!({ "label": label, "amount": amount * 100 }))
+        .collect();
+    let body = serde_json::json!({
+        "chat_id": chat_id,
+        "title": "Оплата записи",
+        "description": description,
+        "payload": invoice_payload(booking_id),
+        "provider_token": provider_token,
+        "currency": "RUB",
+        "prices": prices
+    });
+
+    let resp = reqwest::Client::new().post(&url).json(&body).send().await?;
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let text = resp.text().await.unwrap_or_default();
+        tracing::error!("sendInvoice failed: {} - {}", status, text);
+        anyhow::bail!("Telegram sendInvoice error: {}", status);
+    }
+
+    tracing::info!(booking_id, chat_id, "Telegram invoice sent");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn booking(status: &str) -> Booking {
+        Booking {
+            id: 5,
+            service_id: 1,
+            slot_id: 1,
+            client_tg_id: 42,
+            client_username: None,
+            client_first_name: "Аня".into(),
+            status: status.into(),
+            reminder_sent: false,
+            created_at: "2026-03-01 10:00:00".into(),
+            cancelled_at: None,
+            date: Some("2026-03-02".into()),
+            start_time: Some("10:00".into()),
+            end_time: Some("12:00".into()),
+            with_lower_lashes: false,
+            payment_status: "pending".into(),
+            yookassa_payment_id: None,
+            prepaid_amount: 500,
+            reschedule_count: 0,
+            payment_method: "telegram".into(),
+            telegram_charge_id: None,
+            client_phone: None,
+            client_email: None,
+            amount_due: 2500,
+            tip_amount: 0,
+            needs_approval: false,
+            amount_collected: None,
+            closed_at: None,
+            outcome_prompts: 0,
+            cancelled_by: None,
+        }
+    }
+
+    #[test]
+    fn test_payload_roundtrip() {
+        assert_eq!(parse_invoice_payload(&invoice_payload(17)), Some(17));
+        assert_eq!(parse_invoice_payload("order:17"), None);
+        assert_eq!(parse_invoice_payload("booking:abc"), None);
+    }
+
+    #[test]
+    fn test_pre_checkout_accepts_pending_booking() {
+        assert!(check_pre_checkout(Some(&booking("pending_payment")), 50_000).is_ok());
+    }
+
+    #[test]
+    fn test_pre_checkout_rejects() {
+        assert!(check_pre_checkout(None, 50_000).is_err());
+        assert!(check_pre_checkout(Some(&booking("expired")), 50_000).is_err());
+        assert!(check_pre_checkout(Some(&booking("pending_payment")), 500).is_err());
+    }
+}
@@ -0,0 +1,92 @@
+//! Database fixtures for tests of the core crate, the server and the bot
+//! (feature `test-support`).
+
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+use crate::{
+    models::Booking,
+    time::{minutes_to_time, time_to_minutes},
+};
+
+/// Client of the bookings made by [`paid_booking`].
+pub const CLIENT_TG_ID: i64 = 42;
+
+/// An in-memory database with every migration applied.
+pub async fn memory_db() -> SqlitePool {
+    let db = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    crate::migrations::run(&db).await.unwrap();
+    db
+}
+
+/// Free slots on `date`, one per `(start, end)` pair; returns their ids.
+pub async fn open_slots(db: &SqlitePool, date: &str, times: &[(&str, &str)]) -> Vec<i64> {
+    let mut ids = Vec::with_capacity(times.len());
+    for (start, end) in times {
+        let id = sqlx::query(
+            "INSERT INTO available_slots (date, start_time, end_time) VALUES (?, ?, ?)",
+        )
+        .bind(date)
+        .bind(start)
+        .bind(end)
+        .execute(db)
+        .await
+        .unwrap()
+        .last_insert_rowid();
+        ids.push(id);
+    }
+    ids
+}
+
+/// A confirmed booking of [`CLIENT_TG_ID`] on `date` from `start` to `end`, holding
+/// hourly slots over that span, with `prepaid` ₽ paid through YooKassa payment "pay-1".
+pub async fn paid_booking(
+    db: &SqlitePool,
+    date: &str,
+    start: &str,
+    end: &str,
+    prepaid: i64,
+) -> Booking {
+    let (from, to) = (time_to_minutes(start).unwrap(), time_to_minutes(end).unwrap());
+    let hours: Vec<(String, String)> = (from..to)
+        .step_by(60)
+        .map(|m| (minutes_to_time(m), minutes_to_time((m + 60).min(to))))
+        .collect();
+    let times: Vec<(&str, &str)> = hours.iter().map(|(s, e)| (s.as_str(), e.as_str())).collect();
+    let slot_ids = open_slots(db, date, &times).await;
+
+    let id = sqlx::query(
+        "INSERT INTO bookings (service_id, slot_id, client_tg_id, client_first_name,
+         status, date, start_time, end_time, payment_status, payment_method,
+         yookassa_payment_id, prepaid_amount)
+         VALUES ((SELECT MIN(id) FROM services), ?, ?, 'Аня',
+         'confirmed', ?, ?, ?, 'paid', 'yookassa', 'pay-1', ?)",
+    )
+    .bind(slot_ids[0])
+    .bind(CLIENT_TG_ID)
+    .bind(date)
+    .bind(start)
+    .bind(end)
+    .bind(prepaid)
+    .execute(db)
+    .await
+    .unwrap()
+    .last_insert_rowid();
+    for slot_id in slot_ids {
+        sqlx::query("UPDATE available_slots SET is_booked = 1, booking_id = ? WHERE id = ?")
+            .bind(id)
+            .bind(slot_id)
+            .execute(db)
+            .await
+            .unwrap();
+    }
+
+    sqlx::query_as::<_, Booking>("SELECT * FROM bookings WHERE id = ?")
+        .bind(id)
+        .fetch_one(db)
+        .await
+        .unwrap()
+}
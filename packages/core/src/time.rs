@@ -0,0 +1,90 @@
+//! Moscow-time clock and "HH:MM" helpers. All dates in the database are Moscow local.
+
+use chrono::{FixedOffset, Utc};
+
+/// Moscow timezone offset (UTC+3).
+const MSK_OFFSET_SECS: i32 = 3 * 3600;
+
+/// Moscow timezone (UTC+3).
+pub fn moscow_now() -> chrono::DateTime<FixedOffset> {
+    let msk = FixedOffset::east_opt(MSK_OFFSET_SECS).unwrap();
+    Utc::now().with_timezone(&msk)
+}
+
+pub fn moscow_today() -> String {
+    moscow_now().format("%Y-%m-%d").to_string()
+}
+
+/// Parse "HH:MM" into minutes since midnight.
+pub fn time_to_minutes(time: &str) -> Option<u32> {
+    let (hour, min) = time.split_once(':')?;
+    let hour: u32 = hour.parse().ok()?;
+    let min: u32 = min.parse().ok()?;
+    (min < 60).then_some(hour * 60 + min)
+}
+
+/// Format minutes since midnight as "HH:MM".
+pub fn minutes_to_time(minutes: u32) -> String {
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Time from now (Moscow time) until `date` `time`; `None` on parse error.
+fn time_until(date: &str, time: &str) -> Option<chrono::TimeDelta> {
+    let appointment =
+        chrono::NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M")
+            .ok()?;
+    Some(appointment - moscow_now().naive_local())
+}
+
+/// Whole hours from now (Moscow time) until `date` `time`; `None` on parse error.
+pub fn hours_until(date: &str, time: &str) -> Option<i64> {
+    time_until(date, time).map(|d| d.num_hours())
+}
+
+/// Whole minutes from now (Moscow time) until `date` `time`; `None` on parse error.
+pub fn minutes_until(date: &str, time: &str) -> Option<i64> {
+    time_until(date, time).map(|d| d.num_minutes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_to_minutes_basic() {
+        assert_eq!(time_to_minutes("10:30"), Some(630));
+        assert_eq!(time_to_minutes("24:00"), Some(1440));
+    }
+
+    #[test]
+    fn test_time_to_minutes_invalid() {
+        assert_eq!(time_to_minutes("garbage"), None);
+        assert_eq!(time_to_minutes("10:75"), None);
+    }
+
+    #[test]
+    fn test_minutes_to_time_roundtrip() {
+        assert_eq!(minutes_to_time(615), "10:15");
+        assert_eq!(minutes_to_time(time_to_minutes("23:45").unwrap()), "23:45");
+    }
+
+    #[test]
+    fn test_hours_until_invalid() {
+        assert_eq!(hours_until("not-a-date", "10:00"), None);
+        assert_eq!(hours_until("2026-03-01", "garbage"), None);
+    }
+
+    #[test]
+    fn test_hours_until_past_is_negative() {
+        assert!(hours_until("2000-01-01", "10:00").unwrap() < 0);
+    }
+
+    #[test]
+    fn test_minutes_until_within_the_hour() {
+        let earlier = moscow_now() - chrono::TimeDelta::minutes(30);
+        let (date, time) = (earlier.format("%Y-%m-%d").to_string(), earlier.format("%H:%M").to_string());
+        // Half an hour ago is still "0 hours" away
+        assert_eq!(hours_until(&date, &time), Some(0));
+        assert!(minutes_until(&date, &time).unwrap() < 0);
+    }
+}
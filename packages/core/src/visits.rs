@@ -0,0 +1,79 @@
+//! Closing past visits: the master marks a confirmed booking `completed` (with the
+//! amount taken on site) or `no_show`. The server asks via Completed/No-show
+//! buttons after the appointment, the bot handles the answer.
+
+use serde::Deserialize;
+
+const COMPLETED_PREFIX: &str = "visit_done:";
+const NO_SHOW_PREFIX: &str = "visit_noshow:";
+
+/// How a visit ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Completed,
+    NoShow,
+}
+
+impl Outcome {
+    /// Booking status for this outcome.
+    pub fn status(self) -> &'static str {
+        match self {
+            Outcome::Completed => "completed",
+            Outcome::NoShow => "no_show",
+        }
+    }
+}
+
+/// Callback data of the Completed/No-show buttons.
+pub fn callback_data(outcome: Outcome, booking_id: i64) -> String {
+    match outcome {
+        Outcome::Completed => format!("{}{}", COMPLETED_PREFIX, booking_id),
+        Outcome::NoShow => format!("{}{}", NO_SHOW_PREFIX, booking_id),
+    }
+}
+
+/// Outcome and booking id from callback data written by [`callback_data`].
+pub fn parse_callback(data: &str) -> Option<(Outcome, i64)> {
+    if let Some(id) = data.strip_prefix(COMPLETED_PREFIX) {
+        return Some((Outcome::Completed, id.parse().ok()?));
+    }
+    let id = data.strip_prefix(NO_SHOW_PREFIX)?;
+    Some((Outcome::NoShow, id.parse().ok()?))
+}
+
+/// Bot API `reply_markup` with one Completed/No-show row per `(booking_id, label)`.
+pub fn keyboard_json(visits: &[(i64, String)]) -> serde_json::Value {
+    let rows: Vec<serde_json::Value> = visits
+        .iter()
+        .map(|(id, label)| {
+            serde_json::json!([
+                { "text": format!("✅ {}", label), "callback_data": callback_data(Outcome::Completed, *id) },
+                { "text": "🚫 Не пришла", "callback_data": callback_data(Outcome::NoShow, *id) }
+            ])
+        })
+        .collect();
+    serde_json::json!({ "inline_keyboard": rows })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_callback_roundtrip() {
+        for outcome in [Outcome::Completed, Outcome::NoShow] {
+            assert_eq!(parse_callback(&callback_data(outcome, 9)), Some((outcome, 9)));
+        }
+        assert_eq!(parse_callback("approve:9"), None);
+        assert_eq!(parse_callback("visit_done:x"), None);
+    }
+
+    #[test]
+    fn test_keyboard_has_row_per_visit() {
+        let keyboard = keyboard_json(&[(1, "12:00 Аня".into()), (2, "15:00 Вера".into())]);
+        let rows = keyboard["inline_keyboard"].as_array().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1][1]["callback_data"], "visit_noshow:2");
+    }
+}
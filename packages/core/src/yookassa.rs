@@ -0,0 +1,489 @@
+//! YooKassa implementation of [`PaymentProvider`].
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::payments::{
+    CreatedPayment, PaymentInfo, PaymentProvider, PaymentRequest, RefundCreated, RefundRequest,
+    WebhookEvent,
+};
+use crate::receipts::{self, ReceiptData};
+
+/// Production API root; override with `YOOKASSA_API_URL` (e.g. a local stub).
+pub const DEFAULT_BASE_URL: &str = "https://api.yookassa.ru/v3";
+
+/// Networks YooKassa sends HTTP notifications from (published in the API docs).
+pub const WEBHOOK_SOURCES: &[&str] = &[
+    "185.71.76.0/27",
+    "185.71.77.0/27",
+    "77.75.153.0/25",
+    "77.75.156.11/32",
+    "77.75.156.35/32",
+    "77.75.154.128/25",
+    "2a02:5180::/32",
+];
+
+/// Receipt settings of the shop (54-FZ).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReceiptConfig {
+    /// YooKassa `vat_code`: 1 = без НДС, 2 = 0%, 3 = 10%, 4 = 20%, …
+    pub vat_code: u8,
+    /// YooKassa `tax_system_code` (1 = ОСН … 6 = ПСН); only needed with several systems.
+    pub tax_system_code: Option<u8>,
+}
+
+impl ReceiptConfig {
+    /// Read `YOOKASSA_VAT_CODE` (default 1) and `YOOKASSA_TAX_SYSTEM_CODE`.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let vat_code = match std::env::var("YOOKASSA_VAT_CODE") {
+            Ok(v) => v.parse().ok().filter(|c| (1..=12).contains(c)).ok_or_else(|| {
+                anyhow::anyhow!("YOOKASSA_VAT_CODE must be 1..12, got {}", v)
+            })?,
+            Err(_) => 1,
+        };
+        let tax_system_code = match std::env::var("YOOKASSA_TAX_SYSTEM_CODE") {
+            Ok(v) => Some(v.parse().ok().filter(|c| (1..=6).contains(c)).ok_or_else(|| {
+                anyhow::anyhow!("YOOKASSA_TAX_SYSTEM_CODE must be 1..6, got {}", v)
+            })?),
+            Err(_) => None,
+        };
+        Ok(Self {
+            vat_code,
+            tax_system_code,
+        })
+    }
+}
+
+pub struct YooKassaProvider {
+    shop_id: String,
+    secret_key: String,
+    base_url: String,
+    client: reqwest::Client,
+    /// Attach receipts to payments and refunds.
+    receipts: Option<ReceiptConfig>,
+}
+
+impl YooKassaProvider {
+    pub fn new(shop_id: String, secret_key: String, base_url: String) -> Self {
+        Self {
+            shop_id,
+            secret_key,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+            receipts: None,
+        }
+    }
+
+    pub fn with_receipts(mut self, config: ReceiptConfig) -> Self {
+        self.receipts = Some(config);
+        self
+    }
+
+    /// Add `receipt` to a request body when receipts are on.
+    fn attach_receipt(
+        &self,
+        body: &mut serde_json::Value,
+        data: Option<&ReceiptData>,
+        amount: i64,
+        kind: ReceiptKind,
+    ) {
+        let Some(config) = self.receipts else {
+            return;
+        };
+        match data {
+            Some(data) => body["receipt"] = receipt_json(&config, data, amount, kind),
+            None => tracing::warn!("Receipts enabled but the booking has no client contact"),
+        }
+    }
+
+    /// POST a JSON body and return the decoded response, failing on non-2xx.
+    async fn post(
+        &self,
+        path: &str,
+        idempotence_key: &str,
+        body: &serde_json::Value,
+    ) -> anyhow::Result<serde_json::Value> {
+        let resp = self
+            .client
+            .post(format!("{}{}", self.base_url, path))
+            .basic_auth(&self.shop_id, Some(&self.secret_key))
+            .header("Idempotence-Key", idempotence_key)
+            .json(body)
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            tracing::error!("YooKassa POST {} failed: {} - {}", path, status, text);
+            anyhow::bail!("YooKassa API error: {}", status);
+        }
+
+        Ok(resp.json().await?)
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for YooKassaProvider {
+    fn name(&self) -> &'static str {
+        "yookassa"
+    }
+
+    async fn create_payment(&self, request: &PaymentRequest<'_>) -> anyhow::Result<CreatedPayment> {
+        let idempotence_key = format!(
+            "booking-{}-{}",
+            request.booking_id,
+            chrono::Utc::now().timestamp_millis()
+        );
+
+        let mut body = serde_json::json!({
+            "amount": {
+                "value": format!("{}.00", request.amount),
+                "currency": "RUB"
+            },
+            "capture": true,
+            "confirmation": {
+                "type": "redirect",
+                "return_url": request.return_url
+            },
+            "description": request.description,
+            "metadata": {
+                "booking_id": request.booking_id.to_string()
+            }
+        });
+        self.attach_receipt(&mut body, request.receipt.as_ref(), request.amount, ReceiptKind::Payment);
+
+        let json = self.post("/payments", &idempotence_key, &body).await?;
+
+        let payment = CreatedPayment {
+            id: json["id"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("Missing payment id in YooKassa response"))?
+                .to_string(),
+            confirmation_url: json["confirmation"]["confirmation_url"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("Missing confirmation URL in YooKassa response"))?
+                .to_string(),
+            status: json["status"].as_str().unwrap_or("pending").to_string(),
+        };
+
+        tracing::info!(
+            booking_id = request.booking_id,
+            payment_id = %payment.id,
+            "YooKassa payment created"
+        );
+        Ok(payment)
+    }
+
+    async fn refund(&self, request: &RefundRequest<'_>) -> anyhow::Result<RefundCreated> {
+        let payment_id = request.payment_id;
+        let mut body = serde_json::json!({
+            "payment_id": payment_id,
+            "amount": {
+                "value": format!("{}.00", request.amount),
+                "currency": "RUB"
+            }
+        });
+        self.attach_receipt(&mut body, request.receipt, request.amount, ReceiptKind::Refund);
+
+        let json = self.post("/refunds", request.idempotence_key, &body).await?;
+
+        let refund = RefundCreated {
+            id: json["id"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("Missing refund id in YooKassa response"))?
+                .to_string(),
+            status: json["status"].as_str().unwrap_or("pending").to_string(),
+        };
+
+        tracing::info!(payment_id, refund_id = %refund.id, status = %refund.status, "YooKassa refund created");
+        Ok(refund)
+    }
+
+    async fn fetch_payment(&self, payment_id: &str) -> anyhow::Result<PaymentInfo> {
+        let resp = self
+            .client
+            .get(format!("{}/payments/{}", self.base_url, payment_id))
+            .basic_auth(&self.shop_id, Some(&self.secret_key))
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            anyhow::bail!("YooKassa API error: {}", resp.status());
+        }
+
+        let object: EventObject = resp.json().await?;
+        Ok(object.into_payment())
+    }
+
+    fn parse_webhook(&self, body: &[u8]) -> anyhow::Result<WebhookEvent> {
+        parse_event(body)
+    }
+
+    fn receipts_required(&self) -> bool {
+        self.receipts.is_some()
+    }
+
+    fn webhook_sources(&self) -> Option<&'static [&'static str]> {
+        Some(WEBHOOK_SOURCES)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReceiptKind {
+    Payment,
+    Refund,
+}
+
+/// YooKassa `receipt` object for `amount` rubles spread over the lines.
+///
+/// A refund receipt keeps the payment's mode and takes the money back from the
+/// service lines before the tip.
+fn receipt_json(
+    config: &ReceiptConfig,
+    data: &ReceiptData,
+    amount: i64,
+    kind: ReceiptKind,
+) -> serde_json::Value {
+    let (charged, parts) = match kind {
+        ReceiptKind::Payment => (amount, receipts::split_amount(&data.lines, amount)),
+        ReceiptKind::Refund => (data.paid, receipts::split_refund(&data.lines, data.paid, amount)),
+    };
+    let payment_mode = if receipts::is_full_prepayment(&data.lines, charged) {
+        "full_prepayment"
+    } else {
+        "partial_prepayment"
+    };
+
+    let items: Vec<serde_json::Value> = data
+        .lines
+        .iter()
+        .zip(parts)
+        // Nothing charged or returned on this line
+        .filter(|(_, kopecks)| *kopecks > 0)
+        .map(|(line, kopecks)| {
+            serde_json::json!({
+                // YooKassa limit: 128 characters
+                "description": line.description.chars().take(128).collect::<String>(),
+                "quantity": "1.00",
+                "amount": {
+                    "value": format!("{}.{:02}", kopecks / 100, kopecks % 100),
+                    "currency": "RUB"
+                },
+                "vat_code": config.vat_code,
+                "payment_mode": payment_mode,
+                "payment_subject": "service"
+            })
+        })
+        .collect();
+
+    let mut customer = serde_json::Map::new();
+    if let Some(phone) = &data.customer.phone {
+        customer.insert("phone".into(), phone.clone().into());
+    }
+    if let Some(email) = &data.customer.email {
+        customer.insert("email".into(), email.clone().into());
+    }
+
+    let mut receipt = serde_json::json!({
+        "customer": customer,
+        "items": items,
+    });
+    if let Some(code) = config.tax_system_code {
+        receipt["tax_system_code"] = code.into();
+    }
+    receipt
+}
+
+// ── Webhook payloads ──
+
+#[derive(Debug, Deserialize)]
+struct Notification {
+    event: String,
+    object: EventObject,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventObject {
+    id: String,
+    status: String,
+    metadata: Option<serde_json::Value>,
+    amount: Option<Amount>,
+    refunded_amount: Option<Amount>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Amount {
+    /// Decimal string, e.g. `"500.00"`.
+    value: String,
+}
+
+impl EventObject {
+    fn booking_id(&self) -> Option<i64> {
+        self.metadata
+            .as_ref()?
+            .get("booking_id")?
+            .as_str()?
+            .parse()
+            .ok()
+    }
+
+    fn into_payment(self) -> PaymentInfo {
+        PaymentInfo {
+            booking_id: self.booking_id(),
+            amount: self
+                .amount
+                .as_ref()
+                .and_then(|a| parse_rubles(&a.value))
+                .unwrap_or(0),
+            refunded: self
+                .refunded_amount
+                .as_ref()
+                .and_then(|a| parse_rubles(&a.value))
+                .unwrap_or(0),
+            id: self.id,
+            status: self.status,
+        }
+    }
+}
+
+/// Whole rubles from a YooKassa amount string (`"500.00"` → 500).
+fn parse_rubles(value: &str) -> Option<i64> {
+    let amount: f64 = value.parse().ok()?;
+    Some(amount.round() as i64)
+}
+
+/// Decode a YooKassa HTTP notification.
+pub fn parse_event(body: &[u8]) -> anyhow::Result<WebhookEvent> {
+    let notification: Notification = serde_json::from_slice(body)?;
+    let booking_id = notification.object.booking_id();
+    let object = notification.object;
+
+    Ok(match notification.event.as_str() {
+        "payment.succeeded" => WebhookEvent::PaymentSucceeded {
+            payment_id: object.id,
+            booking_id,
+        },
+        "payment.canceled" => WebhookEvent::PaymentCanceled {
+            payment_id: object.id,
+            booking_id,
+        },
+        event if event.starts_with("refund.") => WebhookEvent::Refund {
+            refund_id: object.id,
+            status: object.status,
+        },
+        other => WebhookEvent::Other(other.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_payment_succeeded() {
+        let body = br#"{"type":"notification","event":"payment.succeeded",
+            "object":{"id":"2d1b","status":"succeeded","metadata":{"booking_id":"42"}}}"#;
+        assert_eq!(
+            parse_event(body).unwrap(),
+            WebhookEvent::PaymentSucceeded {
+                payment_id: "2d1b".into(),
+                booking_id: Some(42)
+            }
+        );
+    }
+
+    #[test]
+    fn test_payment_object_amount() {
+        let object: EventObject = serde_json::from_str(
+            r#"{"id":"p","status":"succeeded","amount":{"value":"500.00","currency":"RUB"},
+                "refunded_amount":{"value":"250.00","currency":"RUB"},
+                "metadata":{"booking_id":"3"}}"#,
+        )
+        .unwrap();
+        let info = object.into_payment();
+        assert_eq!(info.amount, 500);
+        assert_eq!(info.refunded, 250);
+        assert_eq!(info.booking_id, Some(3));
+    }
+
+    #[test]
+    fn test_receipt_items_add_up() {
+        let data = ReceiptData {
+            customer: receipts::Customer {
+                phone: Some("79991234567".into()),
+                email: None,
+            },
+            lines: vec![
+                receipts::ReceiptLine {
+                    description: "Наращивание 2D".into(),
+                    price: 2500,
+                    tip: false,
+                },
+                receipts::ReceiptLine {
+                    description: "Нижние ресницы".into(),
+                    price: 500,
+                    tip: false,
+                },
+            ],
+            paid: 500,
+        };
+        let config = ReceiptConfig {
+            vat_code: 1,
+            tax_system_code: Some(2),
+        };
+        let receipt = receipt_json(&config, &data, 500, ReceiptKind::Payment);
+        assert_eq!(receipt["customer"]["phone"], "79991234567");
+        assert_eq!(receipt["tax_system_code"], 2);
+        let items = receipt["items"].as_array().unwrap();
+        assert_eq!(items[0]["amount"]["value"], "416.66");
+        assert_eq!(items[1]["amount"]["value"], "83.34");
+        assert_eq!(items[0]["payment_mode"], "partial_prepayment");
+    }
+
+    #[test]
+    fn test_refund_receipt_keeps_the_tip() {
+        let line = |description: &str, price, tip| receipts::ReceiptLine {
+            description: description.into(),
+            price,
+            tip,
+        };
+        let data = ReceiptData {
+            customer: receipts::Customer {
+                phone: Some("79991234567".into()),
+                email: None,
+            },
+            lines: vec![line("Наращивание 2D", 3000, false), line("Чаевые мастеру", 300, true)],
+            paid: 3300,
+        };
+        let config = ReceiptConfig {
+            vat_code: 1,
+            tax_system_code: None,
+        };
+        // 50% of a full payment back
+        let receipt = receipt_json(&config, &data, 1650, ReceiptKind::Refund);
+        let items = receipt["items"].as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["amount"]["value"], "1650.00");
+        assert_eq!(items[0]["payment_mode"], "full_prepayment");
+    }
+
+    #[test]
+    fn test_parse_refund_and_unknown_events() {
+        let refund = br#"{"event":"refund.succeeded","object":{"id":"rf","status":"succeeded"}}"#;
+        assert_eq!(
+            parse_event(refund).unwrap(),
+            WebhookEvent::Refund {
+                refund_id: "rf".into(),
+                status: "succeeded".into()
+            }
+        );
+
+        let other = br#"{"event":"payment.waiting_for_capture","object":{"id":"p","status":"waiting_for_capture"}}"#;
+        assert_eq!(
+            parse_event(other).unwrap(),
+            WebhookEvent::Other("payment.waiting_for_capture".into())
+        );
+        assert!(parse_event(b"not json").is_err());
+    }
+}